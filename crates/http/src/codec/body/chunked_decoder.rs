@@ -4,11 +4,16 @@
 //! as specified in [RFC 7230 Section 4.1](https://tools.ietf.org/html/rfc7230#section-4.1).
 //!
 //! The chunked encoding allows the sender to transmit message data in a series of chunks,
-//! indicating the size of each chunk before its data.
+//! indicating the size of each chunk before its data. The last chunk may be followed by
+//! trailer fields, which are collected into a [`HeaderMap`].
 
+use crate::codec::header::HeaderLimits;
 use crate::protocol::{ParseError, PayloadItem};
+use crate::{ensure, is_forbidden_trailer};
 use ChunkedState::*;
 use bytes::{Buf, Bytes, BytesMut};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use httparse::Status;
 use std::io;
 use std::io::ErrorKind;
 use std::task::Poll;
@@ -23,10 +28,19 @@ use tracing::trace;
 /// - Then the chunk data and CRLF
 /// - A zero-sized chunk indicates the end of the message
 ///
+/// When a maximum size is set, decoding fails with [`ParseError::PayloadTooLarge`] as soon as
+/// a chunk size line announces more data than the limit allows.
+///
+/// Trailer fields are subject to the same size and count limits as request headers, and
+/// fields that must not appear in trailers, like `Content-Length` or `Host`, are rejected.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChunkedDecoder {
     state: ChunkedState,
     remaining_size: u64,
+    /// Total size of the chunks announced so far
+    total_size: u64,
+    max_size: Option<u64>,
+    trailer_limits: HeaderLimits,
 }
 
 impl ChunkedDecoder {
@@ -34,7 +48,55 @@ impl ChunkedDecoder {
     ///
     /// The decoder starts in the Size state, ready to read the size of the first chunk.
     pub fn new() -> Self {
-        Self { state: Size, remaining_size: 0 }
+        Self { state: Size, remaining_size: 0, total_size: 0, max_size: None, trailer_limits: HeaderLimits::default() }
+    }
+
+    /// Creates a new ChunkedDecoder instance that rejects bodies larger than `max_size` bytes.
+    pub fn with_max_size(max_size: u64) -> Self {
+        Self { max_size: Some(max_size), ..Self::new() }
+    }
+
+    /// Sets the limits enforced while reading trailer fields.
+    pub fn with_trailer_limits(mut self, trailer_limits: HeaderLimits) -> Self {
+        self.trailer_limits = trailer_limits;
+        self
+    }
+
+    /// Parses the trailer section after the last chunk, up to and including the final CRLF.
+    ///
+    /// Returns `Ok(None)` while the section is incomplete.
+    fn read_trailers(&self, src: &mut BytesMut) -> Result<Option<HeaderMap>, ParseError> {
+        // the common case, no trailer fields at all
+        if src.starts_with(b"\r\n") {
+            src.advance(2);
+            return Ok(Some(HeaderMap::new()));
+        }
+
+        let max_bytes = self.trailer_limits.max_header_bytes();
+        let max_num = self.trailer_limits.max_header_num();
+
+        let mut fields = vec![httparse::EMPTY_HEADER; max_num];
+        let (len, fields) = match httparse::parse_headers(src, &mut fields) {
+            Ok(Status::Complete((len, fields))) => (len, fields),
+            Ok(Status::Partial) => {
+                ensure!(src.len() <= max_bytes, ParseError::too_large_header(src.len(), max_bytes));
+                return Ok(None);
+            }
+            Err(httparse::Error::TooManyHeaders) => return Err(ParseError::too_many_headers(max_num)),
+            Err(e) => return Err(ParseError::invalid_header(format!("invalid trailer: {e}"))),
+        };
+        ensure!(len <= max_bytes, ParseError::too_large_header(len, max_bytes));
+
+        let mut trailers = HeaderMap::with_capacity(fields.len());
+        for field in fields {
+            let name = HeaderName::from_bytes(field.name.as_bytes()).map_err(ParseError::invalid_header)?;
+            ensure!(!is_forbidden_trailer(&name), ParseError::invalid_header(format!("{name} is not allowed in trailers")));
+            let value = HeaderValue::from_bytes(field.value).map_err(ParseError::invalid_header)?;
+            trailers.append(name, value);
+        }
+
+        src.advance(len);
+        Ok(Some(trailers))
     }
 }
 
@@ -54,14 +116,8 @@ enum ChunkedState {
     BodyCr,
     /// Read LF after chunk data
     BodyLf,
-    /// Read optional trailer fields
-    Trailer,
-    /// Read LF after trailer
-    TrailerLf,
-    /// Read final CR
-    EndCr,
-    /// Read final LF
-    EndLf,
+    /// Read optional trailer fields and the final CRLF
+    Trailers,
     /// Final state after reading last chunk
     End,
 }
@@ -74,6 +130,7 @@ impl Decoder for ChunkedDecoder {
     ///
     /// # Returns
     /// - `Ok(Some(PayloadItem::Chunk(bytes)))` when a chunk is successfully decoded
+    /// - `Ok(Some(PayloadItem::Trailers(trailers)))` when the message ends with trailer fields
     /// - `Ok(Some(PayloadItem::Eof))` when the final chunk is processed
     /// - `Ok(None)` when more data is needed
     /// - `Err(ParseError)` if the chunked encoding is invalid
@@ -89,14 +146,38 @@ impl Decoder for ChunkedDecoder {
                 return Ok(None);
             }
 
+            if self.state == Trailers {
+                let Some(trailers) = self.read_trailers(src)? else {
+                    return Ok(None);
+                };
+                self.state = End;
+                if !trailers.is_empty() {
+                    trace!(len = trailers.len(), "read chunked trailers");
+                    return Ok(Some(PayloadItem::Trailers(trailers)));
+                }
+                continue;
+            }
+
             let mut buf = None;
 
-            self.state = match self.state.step(src, &mut self.remaining_size, &mut buf) {
+            let new_state = match self.state.step(src, &mut self.remaining_size, &mut buf) {
                 Poll::Pending => return Ok(None),
                 Poll::Ready(Ok(new_state)) => new_state,
                 Poll::Ready(Err(e)) => return Err(ParseError::io(e)),
             };
 
+            // a new chunk starts, check its announced size against the limit before reading it
+            if self.state == SizeLf && new_state == Body {
+                self.total_size = self.total_size.saturating_add(self.remaining_size);
+                if let Some(max_size) = self.max_size
+                    && self.total_size > max_size
+                {
+                    return Err(ParseError::payload_too_large(self.total_size, max_size));
+                }
+            }
+
+            self.state = new_state;
+
             if let Some(bytes) = buf {
                 trace!(len = bytes.len(), "read chunked bytes");
                 return Ok(Some(PayloadItem::Chunk(bytes)));
@@ -137,10 +218,8 @@ impl ChunkedState {
             Body => ChunkedState::read_body(src, remaining_size, buf),
             BodyCr => ChunkedState::read_body_cr(src),
             BodyLf => ChunkedState::read_body_lf(src),
-            Trailer => ChunkedState::read_trailer(src),
-            TrailerLf => ChunkedState::read_trailer_lf(src),
-            EndCr => ChunkedState::read_end_cr(src),
-            EndLf => ChunkedState::read_end_lf(src),
+            // trailers need the decoder limits, they are read by `ChunkedDecoder::read_trailers`
+            Trailers => Poll::Ready(Ok(Trailers)),
             End => Poll::Ready(Ok(End)),
         }
     }
@@ -248,14 +327,14 @@ impl ChunkedState {
     /// is the last chunk (size = 0).
     ///
     /// # State Transitions
-    /// - On LF with size 0: Move to EndCr state for final CRLF
+    /// - On LF with size 0: Move to Trailers state for the trailer fields and final CRLF
     /// - On LF with size > 0: Move to Body state to read chunk data
     /// - On any other byte: Return error
     fn read_size_lf(src: &mut BytesMut, size_per_chunk: &mut u64) -> Poll<Result<ChunkedState, io::Error>> {
         match try_next_byte!(src) {
             b'\n' => {
                 if *size_per_chunk == 0 {
-                    Poll::Ready(Ok(EndCr))
+                    Poll::Ready(Ok(Trailers))
                 } else {
                     Poll::Ready(Ok(Body))
                 }
@@ -331,65 +410,6 @@ impl ChunkedState {
             _ => Poll::Ready(Err(io::Error::new(ErrorKind::InvalidInput, "invalid chunk body LF"))),
         }
     }
-
-    /// Processes optional trailer fields after the last chunk.
-    ///
-    /// The chunked encoding format allows for trailer fields after the
-    /// zero-length chunk. This implementation reads but ignores them.
-    ///
-    /// # State Transitions
-    /// - On CR: Move to TrailerLf state
-    /// - On any other byte: Stay in Trailer state
-    fn read_trailer(src: &mut BytesMut) -> Poll<Result<ChunkedState, io::Error>> {
-        match try_next_byte!(src) {
-            b'\r' => Poll::Ready(Ok(TrailerLf)),
-            _ => Poll::Ready(Ok(Trailer)),
-        }
-    }
-
-    /// Validates the LF byte after a trailer field.
-    ///
-    /// After a trailer field's CR, this function expects an LF byte.
-    ///
-    /// # State Transitions
-    /// - On LF: Move to EndCr state
-    /// - On any other byte: Return error
-    fn read_trailer_lf(src: &mut BytesMut) -> Poll<Result<ChunkedState, io::Error>> {
-        match try_next_byte!(src) {
-            b'\n' => Poll::Ready(Ok(EndCr)),
-            _ => Poll::Ready(Err(io::Error::new(ErrorKind::InvalidInput, "invalid trailer end LF"))),
-        }
-    }
-
-    /// Validates the final CR byte of the chunked message.
-    ///
-    /// After all chunks and trailers, this function expects a CR byte
-    /// as part of the terminating CRLF.
-    ///
-    /// # State Transitions
-    /// - On CR: Move to EndLf state
-    /// - On any other byte: Move to Trailer state to handle as trailer field
-    fn read_end_cr(src: &mut BytesMut) -> Poll<Result<ChunkedState, io::Error>> {
-        match try_next_byte!(src) {
-            b'\r' => Poll::Ready(Ok(EndLf)),
-            _ => Poll::Ready(Ok(Trailer)),
-        }
-    }
-
-    /// Validates the final LF byte of the chunked message.
-    ///
-    /// After the final CR byte, this function expects an LF byte to
-    /// complete the chunked message.
-    ///
-    /// # State Transitions
-    /// - On LF: Move to End state
-    /// - On any other byte: Return error
-    fn read_end_lf(src: &mut BytesMut) -> Poll<Result<ChunkedState, io::Error>> {
-        match try_next_byte!(src) {
-            b'\n' => Poll::Ready(Ok(End)),
-            _ => Poll::Ready(Err(io::Error::new(ErrorKind::InvalidInput, "invalid chunk end LF"))),
-        }
-    }
 }
 
 #[cfg(test)]
@@ -459,14 +479,57 @@ mod tests {
 
     #[test]
     fn test_chunks_with_trailers() {
-        let mut buffer: BytesMut = BytesMut::from(&b"5\r\nhello\r\n0\r\nTrailer: value\r\n\r\n"[..]);
+        let mut buffer: BytesMut = BytesMut::from(&b"5\r\nhello\r\n0\r\nX-Checksum: abc\r\nX-Trace: 1\r\nX-Trace: 2\r\n\r\n"[..]);
         let mut decoder = ChunkedDecoder::new();
 
         let chunk = decoder.decode(&mut buffer).unwrap().unwrap();
         assert_eq!(chunk.as_bytes().unwrap(), &Bytes::copy_from_slice(b"hello"));
 
+        let trailers = decoder.decode(&mut buffer).unwrap().unwrap().into_trailers().unwrap();
+        assert_eq!(trailers.len(), 3);
+        assert_eq!(trailers.get("x-checksum").unwrap(), "abc");
+        assert_eq!(trailers.get_all("x-trace").iter().collect::<Vec<_>>(), vec!["1", "2"]);
+
         let eof = decoder.decode(&mut buffer).unwrap().unwrap();
         assert!(eof.is_eof());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_incomplete_trailers() {
+        let mut buffer: BytesMut = BytesMut::from(&b"0\r\nX-Checksum: a"[..]);
+        let mut decoder = ChunkedDecoder::new();
+
+        assert!(decoder.decode(&mut buffer).unwrap().is_none());
+
+        buffer.extend_from_slice(b"bc\r\n\r");
+        assert!(decoder.decode(&mut buffer).unwrap().is_none());
+
+        buffer.extend_from_slice(b"\nPOST");
+        let trailers = decoder.decode(&mut buffer).unwrap().unwrap().into_trailers().unwrap();
+        assert_eq!(trailers.get("x-checksum").unwrap(), "abc");
+        assert!(decoder.decode(&mut buffer).unwrap().unwrap().is_eof());
+        assert_eq!(buffer, &b"POST"[..]);
+    }
+
+    #[test]
+    fn test_forbidden_trailer() {
+        let mut buffer: BytesMut = BytesMut::from(&b"0\r\nContent-Length: 5\r\n\r\n"[..]);
+        let result = ChunkedDecoder::new().decode(&mut buffer);
+        assert!(matches!(result, Err(ParseError::InvalidHeader { .. })));
+    }
+
+    #[test]
+    fn test_trailer_limits() {
+        let limits = HeaderLimits::default().with_max_header_num(1);
+        let mut buffer: BytesMut = BytesMut::from(&b"0\r\nX-A: 1\r\nX-B: 2\r\n\r\n"[..]);
+        let result = ChunkedDecoder::new().with_trailer_limits(limits).decode(&mut buffer);
+        assert!(matches!(result, Err(ParseError::TooManyHeaders { max_num: 1 })));
+
+        let limits = HeaderLimits::default().with_max_header_bytes(16);
+        let mut buffer: BytesMut = BytesMut::from(&b"0\r\nX-Checksum: 0123456789"[..]);
+        let result = ChunkedDecoder::new().with_trailer_limits(limits).decode(&mut buffer);
+        assert!(matches!(result, Err(ParseError::TooLargeHeader { max_size: 16, .. })));
     }
 
     #[test]
@@ -539,4 +602,21 @@ mod tests {
         let eof = decoder.decode(&mut buffer).unwrap().unwrap();
         assert!(eof.is_eof());
     }
+
+    #[test]
+    fn test_max_size() {
+        let mut buffer: BytesMut = BytesMut::from(&b"5\r\nhello\r\n5\r\nworld\r\n0\r\n\r\n"[..]);
+        let mut decoder = ChunkedDecoder::with_max_size(10);
+
+        assert_eq!(decoder.decode(&mut buffer).unwrap().unwrap().as_bytes().unwrap(), &Bytes::copy_from_slice(b"hello"));
+        assert_eq!(decoder.decode(&mut buffer).unwrap().unwrap().as_bytes().unwrap(), &Bytes::copy_from_slice(b"world"));
+        assert!(decoder.decode(&mut buffer).unwrap().unwrap().is_eof());
+
+        let mut buffer: BytesMut = BytesMut::from(&b"5\r\nhello\r\n6\r\nworld!\r\n0\r\n\r\n"[..]);
+        let mut decoder = ChunkedDecoder::with_max_size(10);
+
+        assert!(decoder.decode(&mut buffer).unwrap().unwrap().is_chunk());
+        let result = decoder.decode(&mut buffer);
+        assert!(matches!(result, Err(ParseError::PayloadTooLarge { current_size: 11, max_size: 10 })));
+    }
 }
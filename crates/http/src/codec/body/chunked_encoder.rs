@@ -4,8 +4,10 @@
 //! as specified in [RFC 7230 Section 4.1](https://tools.ietf.org/html/rfc7230#section-4.1).
 //!
 //! The chunked encoding allows the sender to transmit message data in a series of chunks,
-//! where each chunk is prefixed with its size in hexadecimal format.
+//! where each chunk is prefixed with its size in hexadecimal format. Trailer fields can
+//! follow the last chunk.
 
+use crate::is_forbidden_trailer;
 use crate::protocol::{PayloadItem, SendError};
 use bytes::{Buf, BytesMut};
 use std::io::Write;
@@ -17,11 +19,13 @@ use tokio_util::codec::Encoder;
 /// - Each chunk starts with its size in hexadecimal
 /// - Followed by CRLF
 /// - Then the chunk data and CRLF
-/// - A zero-sized chunk indicates the end of the message
+/// - A zero-sized chunk indicates the end of the message, optionally followed by trailer fields
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChunkedEncoder {
     /// Indicates if the final zero-length chunk has been sent
     eof: bool,
+    /// Indicates if the final zero-length chunk and trailer fields were written, only the final CRLF is missing
+    trailers: bool,
     /// Size of the current chunk being sent
     send_size: usize,
 }
@@ -31,7 +35,7 @@ impl ChunkedEncoder {
     ///
     /// The encoder starts in a non-EOF state, ready to encode chunks.
     pub fn new() -> Self {
-        Self { eof: false, send_size: 0 }
+        Self { eof: false, trailers: false, send_size: 0 }
     }
 
     /// Returns whether the encoder has finished sending all chunks.
@@ -45,26 +49,28 @@ impl ChunkedEncoder {
 /// Implementation of the Encoder trait for chunked transfer encoding.
 ///
 /// This implementation handles encoding of PayloadItems into chunked format:
-/// - For PayloadItem::Chunk, writes the chunk size, data and terminating CRLF
-/// - For PayloadItem::Eof, writes the final zero-length chunk
+/// - For `PayloadItem::Chunk`, writes the chunk size, data and terminating CRLF, a chunk can't follow the trailer fields
+/// - For `PayloadItem::Trailers`, writes the final zero-length chunk followed by the trailer fields
+/// - For `PayloadItem::Eof`, writes the final zero-length chunk, or just the final CRLF after trailers
 impl<D: Buf> Encoder<PayloadItem<D>> for ChunkedEncoder {
     type Error = SendError;
 
     /// Encodes a PayloadItem into chunked transfer encoding format.
     ///
     /// # Arguments
-    /// * `item` - The PayloadItem to encode (either Chunk or Eof)
+    /// * `item` - The `PayloadItem` to encode (`Chunk`, `Trailers` or `Eof`)
     /// * `dst` - The output buffer to write the encoded data to
     ///
     /// # Returns
     /// * `Ok(())` if encoding succeeds
-    /// * `Err(SendError)` if encoding fails
+    /// * `Err(SendError)` if encoding fails, or a chunk follows the trailer fields
     fn encode(&mut self, item: PayloadItem<D>, dst: &mut BytesMut) -> Result<(), Self::Error> {
         if self.eof {
             return Ok(());
         }
 
         match item {
+            PayloadItem::Chunk(_) if self.trailers => Err(SendError::invalid_body("data chunk after the trailer fields")),
             PayloadItem::Chunk(bytes) => {
                 // Write chunk size in hex followed by CRLF
                 write!(helper::Writer(dst), "{:X}\r\n", bytes.remaining())?;
@@ -75,10 +81,30 @@ impl<D: Buf> Encoder<PayloadItem<D>> for ChunkedEncoder {
                 dst.extend_from_slice(b"\r\n");
                 Ok(())
             }
+            PayloadItem::Trailers(trailers) => {
+                // Write final zero-length chunk once, followed by the trailer fields
+                if !self.trailers {
+                    self.trailers = true;
+                    dst.extend_from_slice(b"0\r\n");
+                }
+                for (name, value) in trailers.iter().filter(|(name, _)| !is_forbidden_trailer(name)) {
+                    dst.reserve(name.as_str().len() + value.len() + 4);
+                    dst.extend_from_slice(name.as_str().as_bytes());
+                    dst.extend_from_slice(b": ");
+                    dst.extend_from_slice(value.as_bytes());
+                    dst.extend_from_slice(b"\r\n");
+                }
+                Ok(())
+            }
             PayloadItem::Eof => {
                 self.eof = true;
-                // Write final zero-length chunk
-                dst.extend_from_slice(b"0\r\n\r\n");
+                if self.trailers {
+                    // Write the CRLF ending the trailer section
+                    dst.extend_from_slice(b"\r\n");
+                } else {
+                    // Write final zero-length chunk
+                    dst.extend_from_slice(b"0\r\n\r\n");
+                }
                 Ok(())
             }
         }
@@ -106,3 +132,52 @@ mod helper {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use http::HeaderMap;
+
+    #[test]
+    fn test_chunks_and_eof() {
+        let mut encoder = ChunkedEncoder::new();
+        let mut dst = BytesMut::new();
+
+        encoder.encode(PayloadItem::Chunk(Bytes::from_static(b"hello")), &mut dst).unwrap();
+        encoder.encode(PayloadItem::<Bytes>::Eof, &mut dst).unwrap();
+
+        assert_eq!(dst, &b"5\r\nhello\r\n0\r\n\r\n"[..]);
+        assert!(encoder.is_finish());
+    }
+
+    #[test]
+    fn test_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", "5d41".parse().unwrap());
+        trailers.insert("content-length", "5".parse().unwrap());
+
+        let mut encoder = ChunkedEncoder::new();
+        let mut dst = BytesMut::new();
+
+        encoder.encode(PayloadItem::Chunk(Bytes::from_static(b"hello")), &mut dst).unwrap();
+        encoder.encode(PayloadItem::<Bytes>::Trailers(trailers), &mut dst).unwrap();
+        assert!(!encoder.is_finish());
+        encoder.encode(PayloadItem::<Bytes>::Eof, &mut dst).unwrap();
+
+        assert_eq!(dst, &b"5\r\nhello\r\n0\r\nx-checksum: 5d41\r\n\r\n"[..]);
+        assert!(encoder.is_finish());
+    }
+
+    #[test]
+    fn test_chunk_after_trailers_is_error() {
+        let mut encoder = ChunkedEncoder::new();
+        let mut dst = BytesMut::new();
+
+        encoder.encode(PayloadItem::<Bytes>::Trailers(HeaderMap::new()), &mut dst).unwrap();
+        let result = encoder.encode(PayloadItem::Chunk(Bytes::from_static(b"late")), &mut dst);
+
+        assert!(matches!(result, Err(SendError::InvalidBody { .. })));
+        assert_eq!(dst, &b"0\r\n"[..]);
+    }
+}
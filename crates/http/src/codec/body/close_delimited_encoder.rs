@@ -0,0 +1,89 @@
+//! Encoder implementation for close-delimited HTTP message payloads.
+//!
+//! HTTP/1.0 has no chunked transfer encoding, so a response whose length is unknown
+//! up front is sent as raw bytes and delimited by closing the connection, as described in
+//! [RFC 9112 Section 6.3](https://www.rfc-editor.org/rfc/rfc9112#section-6.3).
+
+use crate::protocol::{PayloadItem, SendError};
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::Encoder;
+
+/// An encoder for payloads that end when the connection is closed.
+///
+/// The encoder writes chunk data as-is without any framing; the connection must be
+/// closed after the final EOF marker so the peer can detect the end of the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseDelimitedEncoder {
+    /// Indicates if the final EOF marker has been received
+    eof: bool,
+}
+
+impl CloseDelimitedEncoder {
+    /// Creates a new `CloseDelimitedEncoder` instance.
+    pub fn new() -> Self {
+        Self { eof: false }
+    }
+
+    /// Returns whether the encoder has finished sending all data.
+    ///
+    /// Returns true if the EOF marker has been received.
+    pub fn is_finish(&self) -> bool {
+        self.eof
+    }
+}
+
+/// Implementation of the Encoder trait for close-delimited encoding.
+///
+/// Chunks are copied to the output buffer unchanged, and EOF only marks the encoder
+/// as finished since the end of the payload is signalled by closing the connection.
+impl<D: Buf> Encoder<PayloadItem<D>> for CloseDelimitedEncoder {
+    type Error = SendError;
+
+    /// Encodes a `PayloadItem` as raw bytes.
+    ///
+    /// # Arguments
+    /// * `item` - The `PayloadItem` to encode (either Chunk or Eof)
+    /// * `dst` - The output buffer to write the encoded data to
+    ///
+    /// # Returns
+    /// * `Ok(())` if encoding succeeds
+    /// * `Err(SendError)` if encoding fails
+    fn encode(&mut self, item: PayloadItem<D>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        if self.eof {
+            return Ok(());
+        }
+
+        match item {
+            PayloadItem::Chunk(bytes) => {
+                dst.extend_from_slice(bytes.chunk());
+                Ok(())
+            }
+            // a close-delimited body has no place for trailer fields
+            PayloadItem::Trailers(_) => Ok(()),
+            PayloadItem::Eof => {
+                self.eof = true;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_raw_bytes_until_eof() {
+        let mut encoder = CloseDelimitedEncoder::new();
+        let mut dst = BytesMut::new();
+
+        encoder.encode(PayloadItem::Chunk(Bytes::from_static(b"hello ")), &mut dst).unwrap();
+        encoder.encode(PayloadItem::Chunk(Bytes::from_static(b"world")), &mut dst).unwrap();
+        assert!(!encoder.is_finish());
+
+        encoder.encode(PayloadItem::<Bytes>::Eof, &mut dst).unwrap();
+        assert!(encoder.is_finish());
+        assert_eq!(&*dst, b"hello world");
+    }
+}
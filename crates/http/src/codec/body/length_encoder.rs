@@ -69,6 +69,8 @@ impl<D: Buf> Encoder<PayloadItem<D>> for LengthEncoder {
                 self.length -= bytes.remaining() as u64;
                 Ok(())
             }
+            // a fixed length body has no place for trailer fields
+            PayloadItem::Trailers(_) => Ok(()),
             PayloadItem::Eof => {
                 self.received_eof = true;
                 Ok(())
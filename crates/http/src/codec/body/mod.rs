@@ -13,6 +13,7 @@
 //!
 //! ## Encoders
 //! - [`chunked_encoder::ChunkedEncoder`]: Implements chunked transfer encoding
+//! - [`close_delimited_encoder::CloseDelimitedEncoder`]: Writes HTTP/1.0 payloads delimited by connection close
 //! - [`length_encoder::LengthEncoder`]: Handles fixed-length payload encoding
 //! - [`payload_encoder::PayloadEncoder`]: Main encoder that manages different encoding strategies
 //!
@@ -26,6 +27,7 @@
 
 mod chunked_decoder;
 mod chunked_encoder;
+mod close_delimited_encoder;
 mod length_decoder;
 mod length_encoder;
 mod payload_decoder;
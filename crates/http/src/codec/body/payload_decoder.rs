@@ -9,6 +9,7 @@
 
 use crate::codec::body::chunked_decoder::ChunkedDecoder;
 use crate::codec::body::length_decoder::LengthDecoder;
+use crate::codec::header::HeaderLimits;
 use crate::protocol::{ParseError, PayloadItem, PayloadSize};
 use bytes::BytesMut;
 use tokio_util::codec::Decoder;
@@ -50,6 +51,16 @@ impl PayloadDecoder {
         Self { kind: Kind::Chunked(ChunkedDecoder::new()) }
     }
 
+    /// Creates a `PayloadDecoder` for chunked transfer encoding that enforces `trailer_limits` on the
+    /// trailer fields and, if set, rejects bodies larger than `max_size` bytes.
+    pub fn chunked_with_limits(trailer_limits: HeaderLimits, max_size: Option<u64>) -> Self {
+        let decoder = match max_size {
+            Some(max_size) => ChunkedDecoder::with_max_size(max_size),
+            None => ChunkedDecoder::new(),
+        };
+        Self { kind: Kind::Chunked(decoder.with_trailer_limits(trailer_limits)) }
+    }
+
     /// Creates a PayloadDecoder for a fixed-length payload.
     ///
     /// # Arguments
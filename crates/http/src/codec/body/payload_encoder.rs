@@ -3,11 +3,13 @@
 //! This module provides a unified encoder for handling different types of HTTP message bodies:
 //! - Content-Length based payloads
 //! - Chunked transfer encoding
+//! - Close-delimited payloads for HTTP/1.0
 //! - Messages with no body
 //!
 //! The encoder automatically handles the appropriate encoding strategy based on the message headers.
 
 use crate::codec::body::chunked_encoder::ChunkedEncoder;
+use crate::codec::body::close_delimited_encoder::CloseDelimitedEncoder;
 use crate::codec::body::length_encoder::LengthEncoder;
 use crate::protocol::{PayloadItem, SendError};
 use bytes::{Buf, BytesMut};
@@ -15,9 +17,10 @@ use tokio_util::codec::Encoder;
 
 /// A unified encoder for handling HTTP message payloads.
 ///
-/// This encoder supports three payload types:
+/// This encoder supports four payload types:
 /// - Fixed length payloads (using Content-Length)
 /// - Chunked transfer encoding
+/// - Close-delimited payloads (HTTP/1.0 responses of unknown length)
 /// - No body
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PayloadEncoder {
@@ -34,6 +37,9 @@ enum Kind {
     /// Encode payload using chunked transfer encoding
     Chunked(ChunkedEncoder),
 
+    /// Encode payload as raw bytes delimited by closing the connection
+    CloseDelimited(CloseDelimitedEncoder),
+
     /// Handle messages with no body
     NoBody,
 }
@@ -49,6 +55,14 @@ impl PayloadEncoder {
         Self { kind: Kind::Chunked(ChunkedEncoder::new()) }
     }
 
+    /// Creates a `PayloadEncoder` for a payload delimited by closing the connection.
+    ///
+    /// This is used for HTTP/1.0 responses whose length is unknown, since chunked
+    /// transfer encoding is not available in HTTP/1.0.
+    pub fn close_delimited() -> Self {
+        Self { kind: Kind::CloseDelimited(CloseDelimitedEncoder::new()) }
+    }
+
     /// Creates a PayloadEncoder for a fixed-length payload.
     ///
     /// # Arguments
@@ -64,6 +78,7 @@ impl PayloadEncoder {
         match &self.kind {
             Kind::Length(_) => false,
             Kind::Chunked(_) => true,
+            Kind::CloseDelimited(_) => false,
             Kind::NoBody => false,
         }
     }
@@ -74,6 +89,7 @@ impl PayloadEncoder {
         match &self.kind {
             Kind::Length(_) => false,
             Kind::Chunked(_) => false,
+            Kind::CloseDelimited(_) => false,
             Kind::NoBody => true,
         }
     }
@@ -84,6 +100,7 @@ impl PayloadEncoder {
         match &self.kind {
             Kind::Length(_) => true,
             Kind::Chunked(_) => false,
+            Kind::CloseDelimited(_) => false,
             Kind::NoBody => false,
         }
     }
@@ -94,6 +111,7 @@ impl PayloadEncoder {
         match &self.kind {
             Kind::Length(encoder) => encoder.is_finish(),
             Kind::Chunked(encoder) => encoder.is_finish(),
+            Kind::CloseDelimited(encoder) => encoder.is_finish(),
             Kind::NoBody => true,
         }
     }
@@ -118,6 +136,7 @@ impl<D: Buf> Encoder<PayloadItem<D>> for PayloadEncoder {
         match &mut self.kind {
             Kind::Length(encoder) => encoder.encode(item, dst),
             Kind::Chunked(encoder) => encoder.encode(item, dst),
+            Kind::CloseDelimited(encoder) => encoder.encode(item, dst),
             Kind::NoBody => Ok(()),
         }
     }
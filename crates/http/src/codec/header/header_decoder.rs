@@ -14,10 +14,16 @@
 //!
 //! # Limits
 //!
+//! The limits are configured through [`HeaderLimits`], by default:
+//!
 //! - Maximum number of headers: 64
 //! - Maximum header size: 8KB
+//! - Maximum URI length: 8KB
 //! - Only supports HTTP/1.0 and HTTP/1.1 (HTTP/2 and HTTP/3 currently not supported)
 //!
+//! Header slots for up to 64 headers live on the stack; larger header count limits
+//! fall back to a heap allocated buffer.
+//!
 //! # Implementation Details
 //!
 //! The decoder works in multiple stages:
@@ -43,18 +49,104 @@ use crate::ensure;
 
 use crate::protocol::{ParseError, PayloadSize, RequestHeader};
 
-/// Maximum number of headers allowed in a request
-const MAX_HEADER_NUM: usize = 64;
+/// Number of header slots allocated on the stack, also the default maximum number of headers
+const STACK_HEADER_NUM: usize = 64;
+
+/// Default maximum size in bytes allowed for the entire header section
+const DEFAULT_MAX_HEADER_BYTES: usize = 8 * 1024;
+
+/// Default maximum length in bytes allowed for the request URI
+const DEFAULT_MAX_URI_LEN: usize = 8 * 1024;
+
+/// Limits applied while decoding request headers.
+///
+/// The size and count limits apply to the trailer fields of a chunked request body as well.
+///
+/// # Example
+///
+/// ```
+/// use micro_http::codec::{HeaderLimits, RequestDecoder};
+///
+/// let limits = HeaderLimits::default().with_max_header_bytes(32 * 1024).with_max_header_num(128);
+/// let decoder = RequestDecoder::with_limits(limits);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderLimits {
+    /// The maximum size of the request line and header fields together
+    header_bytes: usize,
+    /// The maximum number of header fields
+    header_num: usize,
+    /// The maximum length of the request URI
+    uri_len: usize,
+}
+
+impl Default for HeaderLimits {
+    fn default() -> Self {
+        Self { header_bytes: DEFAULT_MAX_HEADER_BYTES, header_num: STACK_HEADER_NUM, uri_len: DEFAULT_MAX_URI_LEN }
+    }
+}
+
+impl HeaderLimits {
+    /// Sets the maximum size of the request line and header fields together, defaults to 8KB.
+    #[must_use]
+    pub fn with_max_header_bytes(mut self, max_header_bytes: usize) -> Self {
+        self.header_bytes = max_header_bytes;
+        self
+    }
+
+    /// Sets the maximum number of header fields, defaults to 64.
+    #[must_use]
+    pub fn with_max_header_num(mut self, max_header_num: usize) -> Self {
+        self.header_num = max_header_num;
+        self
+    }
+
+    /// Sets the maximum length of the request URI, defaults to 8KB.
+    #[must_use]
+    pub fn with_max_uri_len(mut self, max_uri_len: usize) -> Self {
+        self.uri_len = max_uri_len;
+        self
+    }
+
+    /// Returns the maximum size of the request line and header fields together.
+    #[must_use]
+    pub fn max_header_bytes(&self) -> usize {
+        self.header_bytes
+    }
+
+    /// Returns the maximum number of header fields.
+    #[must_use]
+    pub fn max_header_num(&self) -> usize {
+        self.header_num
+    }
 
-/// Maximum size in bytes allowed for the entire header section
-const MAX_HEADER_BYTES: usize = 8 * 1024;
+    /// Returns the maximum length of the request URI.
+    #[must_use]
+    pub fn max_uri_len(&self) -> usize {
+        self.uri_len
+    }
+}
 
 /// Decoder for HTTP request headers implementing the [`Decoder`] trait.
 ///
 /// This decoder parses raw bytes into a structured [`RequestHeader`] and determines the
 /// appropriate [`PayloadDecoder`] based on the Content-Length and Transfer-Encoding headers.
-#[derive(Debug)]
-pub struct HeaderDecoder;
+#[derive(Debug, Default)]
+pub struct HeaderDecoder {
+    limits: HeaderLimits,
+}
+
+impl HeaderDecoder {
+    /// Creates a decoder with the given [`HeaderLimits`].
+    pub fn with_limits(limits: HeaderLimits) -> Self {
+        Self { limits }
+    }
+
+    /// Returns the limits enforced by this decoder.
+    pub fn limits(&self) -> HeaderLimits {
+        self.limits
+    }
+}
 
 impl Decoder for HeaderDecoder {
     type Item = (RequestHeader, PayloadSize);
@@ -75,8 +167,9 @@ impl Decoder for HeaderDecoder {
     /// # Errors
     ///
     /// Returns `ParseError` if:
-    /// - The number of headers exceeds `MAX_HEADER_NUM`
-    /// - The total header size exceeds `MAX_HEADER_BYTES`
+    /// - The number of headers exceeds the configured maximum header number
+    /// - The total header size exceeds the configured maximum header bytes
+    /// - The request URI exceeds the configured maximum URI length
     /// - The HTTP version is not supported
     /// - Headers contain invalid characters
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
@@ -86,84 +179,143 @@ impl Decoder for HeaderDecoder {
             return Ok(None);
         }
 
-        // Create an empty HTTP request parser and uninitialized headers array
-        let mut req = httparse::Request::new(&mut []);
-        let mut headers: [MaybeUninit<httparse::Header>; MAX_HEADER_NUM] = unsafe { MaybeUninit::uninit().assume_init() };
+        let max_header_num = self.limits.max_header_num();
+        if max_header_num <= STACK_HEADER_NUM {
+            // Uninitialized headers array and header indices on the stack for the common case
+            let mut headers: [MaybeUninit<httparse::Header>; STACK_HEADER_NUM] = unsafe { MaybeUninit::uninit().assume_init() };
+            let mut header_index: [HeaderIndex; STACK_HEADER_NUM] = EMPTY_HEADER_INDEX_ARRAY;
+
+            let Some(head) = parse_head(src, &self.limits, &mut headers[..max_header_num], &mut header_index)? else {
+                return Ok(None);
+            };
+            build_header(src, head, &header_index).map(Some)
+        } else {
+            // Larger limits don't fit on the stack, allocate the header slots instead
+            let mut headers = Vec::with_capacity(max_header_num);
+            headers.resize_with(max_header_num, MaybeUninit::uninit);
+            let mut header_index = vec![EMPTY_HEADER_INDEX; max_header_num];
+
+            let Some(head) = parse_head(src, &self.limits, &mut headers, &mut header_index)? else {
+                return Ok(None);
+            };
+            build_header(src, head, &header_index).map(Some)
+        }
+    }
+}
+
+/// Request line facts and header positions collected by [`parse_head`].
+struct ParsedHead {
+    body_offset: usize,
+    version: http::Version,
+    method: Method,
+    uri_path_index: (usize, usize),
+    header_count: usize,
+}
+
+/// Parses the request line and headers, enforcing the limits and recording header positions in `header_index`.
+///
+/// Returns `Ok(None)` if more data is needed.
+fn parse_head<'b>(
+    src: &'b [u8],
+    limits: &HeaderLimits,
+    headers: &mut [MaybeUninit<httparse::Header<'b>>],
+    header_index: &mut [HeaderIndex],
+) -> Result<Option<ParsedHead>, ParseError> {
+    let max_header_num = headers.len();
+
+    // Create an empty HTTP request parser
+    let mut req = httparse::Request::new(&mut []);
+
+    // Parse request headers using httparse, return error if exceeds max headers or invalid format
+    let parsed_result = req.parse_with_uninit_headers(src, headers).map_err(|e| match e {
+        Error::TooManyHeaders => ParseError::too_many_headers(max_header_num),
+        e => ParseError::invalid_header(e.to_string()),
+    });
 
-        // Parse request headers using httparse, return error if exceeds max headers or invalid format
-        let parsed_result = req.parse_with_uninit_headers(src, &mut headers).map_err(|e| match e {
-            Error::TooManyHeaders => ParseError::too_many_headers(MAX_HEADER_NUM),
-            e => ParseError::invalid_header(e.to_string()),
-        });
+    match parsed_result? {
+        // If parsing is complete, get the body offset
+        Status::Complete(body_offset) => {
+            trace!(body_size = body_offset, "parsed body size");
 
-        match parsed_result? {
-            // If parsing is complete, get the body offset
-            Status::Complete(body_offset) => {
-                trace!(body_size = body_offset, "parsed body size");
-                // Ensure request headers size does not exceed limit
-                ensure!(body_offset <= MAX_HEADER_BYTES, ParseError::too_large_header(body_offset, MAX_HEADER_BYTES));
+            let path = req.path.ok_or(ParseError::InvalidUri)?;
+            ensure!(path.len() <= limits.max_uri_len(), ParseError::uri_too_long(path.len(), limits.max_uri_len()));
 
-                let header_count = req.headers.len();
+            // Ensure request headers size does not exceed limit
+            ensure!(body_offset <= limits.max_header_bytes(), ParseError::too_large_header(body_offset, limits.max_header_bytes()));
 
-                ensure!(header_count <= MAX_HEADER_NUM, ParseError::too_many_headers(header_count));
+            let header_count = req.headers.len();
 
-                // Calculate and record byte range indices for each header
-                let mut header_index: [HeaderIndex; MAX_HEADER_NUM] = EMPTY_HEADER_INDEX_ARRAY;
-                HeaderIndex::record(src, req.headers, &mut header_index);
+            ensure!(header_count <= max_header_num, ParseError::too_many_headers(header_count));
 
-                // Build HTTP version based on version number
-                let version = match req.version {
-                    Some(0) => http::Version::HTTP_10,
-                    Some(1) => http::Version::HTTP_11,
-                    // Currently HTTP/2 and HTTP/3 not supported
-                    _ => return Err(ParseError::InvalidVersion(req.version)),
-                };
+            // Calculate and record byte range indices for each header
+            HeaderIndex::record(src, req.headers, header_index);
 
-                let method: Method =
-                    Method::from_bytes(req.method.ok_or(ParseError::InvalidMethod)?.as_bytes()).map_err(|_| ParseError::InvalidMethod)?;
+            // Build HTTP version based on version number
+            let version = match req.version {
+                Some(0) => http::Version::HTTP_10,
+                Some(1) => http::Version::HTTP_11,
+                // Currently HTTP/2 and HTTP/3 not supported
+                _ => return Err(ParseError::InvalidVersion(req.version)),
+            };
 
-                // record the uri path byte range
-                let uri_path_index = path_index(src, req.path.ok_or(ParseError::InvalidUri)?);
+            let method: Method =
+                Method::from_bytes(req.method.ok_or(ParseError::InvalidMethod)?.as_bytes()).map_err(|_| ParseError::InvalidMethod)?;
 
-                // Split header portion from source buffer
-                let header_bytes = src.split_to(body_offset).freeze();
+            // record the uri path byte range
+            let uri_path_index = path_index(src, path);
 
-                let uri_path_byte = header_bytes.slice(uri_path_index.0..uri_path_index.1);
-                let uri = Uri::from_maybe_shared(uri_path_byte).map_err(|_| ParseError::InvalidUri)?;
+            Ok(Some(ParsedHead { body_offset, version, method, uri_path_index, header_count }))
+        }
+        // If parsing incomplete, ensure current buffer size does not exceed limit
+        Status::Partial => {
+            // until the URI is complete, everything received after the method and its space may still be the URI
+            let uri_len = req.path.map_or_else(|| partial_uri_len(src), str::len);
+            ensure!(uri_len <= limits.max_uri_len(), ParseError::uri_too_long(uri_len, limits.max_uri_len()));
+            ensure!(src.len() <= limits.max_header_bytes(), ParseError::too_large_header(src.len(), limits.max_header_bytes()));
+            Ok(None)
+        }
+    }
+}
 
-                // Build request header using parsed method, URI and version
-                let mut header_builder = Request::builder().method(method).uri(uri).version(version);
+/// Splits the parsed header section off `src` and builds the [`RequestHeader`] from it.
+fn build_header(src: &mut BytesMut, head: ParsedHead, header_index: &[HeaderIndex]) -> Result<(RequestHeader, PayloadSize), ParseError> {
+    // Split header portion from source buffer
+    let header_bytes = src.split_to(head.body_offset).freeze();
 
-                // Build headers
-                let headers = header_builder.headers_mut().unwrap();
-                headers.reserve(header_count);
+    let uri_path_byte = header_bytes.slice(head.uri_path_index.0..head.uri_path_index.1);
+    let uri = Uri::from_maybe_shared(uri_path_byte).map_err(|_| ParseError::InvalidUri)?;
 
-                // Iterate header indices and build each header
-                for index in &header_index[..header_count] {
-                    // Safe to unwrap since httparse verified header name is valid ASCII
-                    let name = HeaderName::from_bytes(&header_bytes[index.name.0..index.name.1]).unwrap();
+    // Build request header using parsed method, URI and version
+    let mut header_builder = Request::builder().method(head.method).uri(uri).version(head.version);
 
-                    // inspired by active-web:
-                    // Safe to use from_maybe_shared_unchecked since httparse verified
-                    // header value contains only visible ASCII chars
-                    let value = unsafe { HeaderValue::from_maybe_shared_unchecked(header_bytes.slice(index.value.0..index.value.1)) };
+    // Build headers
+    let headers = header_builder.headers_mut().unwrap();
+    headers.reserve(head.header_count);
 
-                    headers.append(name, value);
-                }
+    // Iterate header indices and build each header
+    for index in &header_index[..head.header_count] {
+        // Safe to unwrap since httparse verified header name is valid ASCII
+        let name = HeaderName::from_bytes(&header_bytes[index.name.0..index.name.1]).unwrap();
 
-                // Build final request header and payload decoder
-                let header = RequestHeader::from(header_builder.body(()).unwrap());
-                let payload_decoder = parse_payload(&header)?;
+        // inspired by active-web:
+        // Safe to use from_maybe_shared_unchecked since httparse verified
+        // header value contains only visible ASCII chars
+        let value = unsafe { HeaderValue::from_maybe_shared_unchecked(header_bytes.slice(index.value.0..index.value.1)) };
 
-                Ok(Some((header, payload_decoder)))
-            }
-            // If parsing incomplete, ensure current buffer size does not exceed limit
-            Status::Partial => {
-                ensure!(src.len() <= MAX_HEADER_BYTES, ParseError::too_large_header(src.len(), MAX_HEADER_BYTES));
-                Ok(None)
-            }
-        }
+        headers.append(name, value);
     }
+
+    // Build final request header and payload decoder
+    let header = RequestHeader::from(header_builder.body(()).unwrap());
+    let payload_decoder = parse_payload(&header)?;
+
+    Ok((header, payload_decoder))
+}
+
+/// The length of the URI received so far in the partial request line of `src`, the bytes after the
+/// method and the space following it
+fn partial_uri_len(src: &[u8]) -> usize {
+    src.iter().position(|&b| b == b' ').map_or(0, |method_end| src.len() - method_end - 1)
 }
 
 fn path_index(src: &[u8], path: &str) -> (usize, usize) {
@@ -187,7 +339,7 @@ struct HeaderIndex {
 
 const EMPTY_HEADER_INDEX: HeaderIndex = HeaderIndex { name: (0, 0), value: (0, 0) };
 
-const EMPTY_HEADER_INDEX_ARRAY: [HeaderIndex; MAX_HEADER_NUM] = [EMPTY_HEADER_INDEX; MAX_HEADER_NUM];
+const EMPTY_HEADER_INDEX_ARRAY: [HeaderIndex; STACK_HEADER_NUM] = [EMPTY_HEADER_INDEX; STACK_HEADER_NUM];
 
 impl HeaderIndex {
     /// Records the byte positions of header names and values from the parsed headers.
@@ -337,7 +489,7 @@ mod tests {
 
         assert_eq!(bytes.len(), str.len());
 
-        let mut header_decoder = HeaderDecoder;
+        let mut header_decoder = HeaderDecoder::default();
 
         header_decoder.decode(&mut bytes).unwrap();
 
@@ -357,7 +509,7 @@ mod tests {
 
         let mut buf = BytesMut::from(str);
 
-        let (header, payload_decoder) = HeaderDecoder.decode(&mut buf).unwrap().unwrap();
+        let (header, payload_decoder) = HeaderDecoder::default().decode(&mut buf).unwrap().unwrap();
 
         assert!(payload_decoder.is_empty());
 
@@ -401,7 +553,7 @@ mod tests {
 
         let mut buf = BytesMut::from(str);
 
-        let (header, payload_decoder) = HeaderDecoder.decode(&mut buf).unwrap().unwrap();
+        let (header, payload_decoder) = HeaderDecoder::default().decode(&mut buf).unwrap().unwrap();
 
         assert!(payload_decoder.is_empty());
 
@@ -447,4 +599,83 @@ mod tests {
             Some(&HeaderValue::from_str("zh-CN,zh;q=0.9,en-US;q=0.8,en;q=0.7").unwrap())
         );
     }
+
+    fn request_with_headers(path: &str, header_num: usize, header_value: &str) -> BytesMut {
+        let headers: String = (0..header_num).map(|i| format!("x-header-{i}: {header_value}\r\n")).collect();
+        BytesMut::from(format!("GET {path} HTTP/1.1\r\n{headers}\r\n").as_str())
+    }
+
+    #[test]
+    fn header_num_limit() {
+        let mut buf = request_with_headers("/", 65, "v");
+        assert!(matches!(HeaderDecoder::default().decode(&mut buf), Err(ParseError::TooManyHeaders { .. })));
+
+        let mut buf = request_with_headers("/", 3, "v");
+        let limits = HeaderLimits::default().with_max_header_num(2);
+        assert!(matches!(HeaderDecoder::with_limits(limits).decode(&mut buf), Err(ParseError::TooManyHeaders { .. })));
+    }
+
+    #[test]
+    fn raised_header_num_limit_uses_heap_slots() {
+        let mut buf = request_with_headers("/", 100, "v");
+        let limits = HeaderLimits::default().with_max_header_num(128);
+
+        let (header, _) = HeaderDecoder::with_limits(limits).decode(&mut buf).unwrap().unwrap();
+
+        assert_eq!(header.headers().len(), 100);
+        assert_eq!(header.headers().get("x-header-99"), Some(&HeaderValue::from_static("v")));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn header_bytes_limit() {
+        let cookie = "c".repeat(10 * 1024);
+
+        let mut buf = request_with_headers("/", 1, &cookie);
+        assert!(matches!(HeaderDecoder::default().decode(&mut buf), Err(ParseError::TooLargeHeader { .. })));
+
+        let mut buf = request_with_headers("/", 1, &cookie);
+        let limits = HeaderLimits::default().with_max_header_bytes(16 * 1024);
+        let (header, _) = HeaderDecoder::with_limits(limits).decode(&mut buf).unwrap().unwrap();
+        assert_eq!(header.headers().get("x-header-0").unwrap().len(), cookie.len());
+    }
+
+    #[test]
+    fn uri_len_limit() {
+        let limits = HeaderLimits::default().with_max_uri_len(16);
+        let path = format!("/{}", "a".repeat(20));
+
+        let mut buf = request_with_headers(&path, 1, "v");
+        assert!(matches!(HeaderDecoder::with_limits(limits).decode(&mut buf), Err(ParseError::UriTooLong { .. })));
+
+        // the request line hasn't finished yet but is already too long
+        let mut buf = BytesMut::from(format!("GET {path}").as_str());
+        assert!(matches!(HeaderDecoder::with_limits(limits).decode(&mut buf), Err(ParseError::UriTooLong { .. })));
+
+        let mut buf = request_with_headers("/short", 1, "v");
+        assert!(HeaderDecoder::with_limits(limits).decode(&mut buf).unwrap().is_some());
+    }
+
+    #[test]
+    fn uri_len_limit_excludes_method_while_partial() {
+        let limits = HeaderLimits::default().with_max_uri_len(16);
+        let path = format!("/{}", "a".repeat(15));
+        let request = format!("OPTIONS {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+        // the request arrives one byte at a time, the URI at the limit must never be rejected
+        let mut decoder = HeaderDecoder::with_limits(limits);
+        let mut buf = BytesMut::new();
+        for (i, &byte) in request.as_bytes().iter().enumerate() {
+            buf.extend_from_slice(&[byte]);
+            match decoder.decode(&mut buf).unwrap() {
+                Some((header, _)) => {
+                    assert_eq!(i, request.len() - 1);
+                    assert_eq!(header.uri().path(), path);
+                    return;
+                }
+                None => assert!(i < request.len() - 1),
+            }
+        }
+        panic!("the request was never decoded");
+    }
 }
@@ -8,14 +8,16 @@
 //!
 //! - Efficient header serialization
 //! - Automatic handling of Content-Length and Transfer-Encoding headers
-//! - Support for HTTP/1.1 responses
-//! - Chunked transfer encoding support
+//! - Support for HTTP/1.0 and HTTP/1.1 responses
+//! - Chunked transfer encoding support, falling back to close-delimited bodies for HTTP/1.0
+//! - No framing headers for `1xx`, `204` and `304` responses, which never have a body
 
+use crate::is_bodiless_status;
 use crate::protocol::{PayloadSize, ResponseHead, SendError};
 
 use bytes::{BufMut, BytesMut};
 
-use http::{HeaderValue, Version, header};
+use http::{HeaderValue, StatusCode, Version, header};
 use std::io;
 use std::io::{ErrorKind, Write};
 use tokio_util::codec::Encoder;
@@ -49,24 +51,43 @@ impl Encoder<(ResponseHead, PayloadSize)> for HeaderEncoder {
     /// # Errors
     ///
     /// Returns error if:
-    /// - HTTP version is not supported (only HTTP/1.1 supported)
+    /// - HTTP version is not supported (only HTTP/1.0 and HTTP/1.1 supported)
     /// - Writing to buffer fails
+    ///
+    /// # HTTP/1.0
+    ///
+    /// Chunked transfer encoding doesn't exist in HTTP/1.0, so a [`PayloadSize::Chunked`]
+    /// payload is sent close-delimited instead: no framing header is written and
+    /// `Connection: close` is set, the caller must close the connection after the body.
+    ///
+    /// # Bodiless Statuses
+    ///
+    /// `1xx` and `204` responses are sent without `Content-Length` and `Transfer-Encoding`. A
+    /// `304` response drops `Transfer-Encoding` but keeps a `Content-Length` set by the handler,
+    /// since it describes the representation the `304` stands for.
     fn encode(&mut self, item: (ResponseHead, PayloadSize), dst: &mut BytesMut) -> Result<(), Self::Error> {
         let (mut header, payload_size) = item;
 
         dst.reserve(INIT_HEADER_SIZE);
-        match header.version() {
-            Version::HTTP_11 => {
-                write!(FastWrite(dst), "HTTP/1.1 {} {}\r\n", header.status().as_str(), header.status().canonical_reason().unwrap())?;
-            }
+        let version = match header.version() {
+            Version::HTTP_11 => "HTTP/1.1",
+            Version::HTTP_10 => "HTTP/1.0",
             v => {
                 error!(http_version = ?v, "unsupported http version");
                 return Err(io::Error::from(ErrorKind::Unsupported).into());
             }
-        }
+        };
+        write!(FastWrite(dst), "{} {} {}\r\n", version, header.status().as_str(), header.status().canonical_reason().unwrap())?;
 
         // Set appropriate content length or transfer encoding header
         match payload_size {
+            _ if is_bodiless_status(header.status()) => {
+                header.headers_mut().remove(header::TRANSFER_ENCODING);
+                if header.status() != StatusCode::NOT_MODIFIED {
+                    header.headers_mut().remove(header::CONTENT_LENGTH);
+                }
+            }
+
             PayloadSize::Length(n) => match header.headers_mut().get_mut(header::CONTENT_LENGTH) {
                 Some(value) => *value = n.into(),
                 None => {
@@ -74,6 +95,14 @@ impl Encoder<(ResponseHead, PayloadSize)> for HeaderEncoder {
                 }
             },
 
+            PayloadSize::Chunked if header.version() == Version::HTTP_10 => {
+                const CLOSE: HeaderValue = HeaderValue::from_static("close");
+
+                header.headers_mut().remove(header::CONTENT_LENGTH);
+                header.headers_mut().remove(header::TRANSFER_ENCODING);
+                header.headers_mut().insert(header::CONNECTION, CLOSE);
+            }
+
             PayloadSize::Chunked => {
                 const CHUNKED: HeaderValue = HeaderValue::from_static("chunked");
 
@@ -123,3 +152,45 @@ impl Write for FastWrite<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Response;
+
+    fn encode(version: Version, payload_size: PayloadSize) -> String {
+        let head = Response::builder().version(version).body(()).unwrap();
+        let mut dst = BytesMut::new();
+        HeaderEncoder.encode((head, payload_size), &mut dst).unwrap();
+        String::from_utf8(dst.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn encode_http_11_chunked() {
+        let head = encode(Version::HTTP_11, PayloadSize::Chunked);
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains("transfer-encoding: chunked\r\n"));
+    }
+
+    #[test]
+    fn encode_http_10_length() {
+        let head = encode(Version::HTTP_10, PayloadSize::Length(5));
+        assert!(head.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(head.contains("content-length: 5\r\n"));
+    }
+
+    #[test]
+    fn encode_http_10_chunked_as_close_delimited() {
+        let head = encode(Version::HTTP_10, PayloadSize::Chunked);
+        assert!(head.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(!head.contains("transfer-encoding"));
+        assert!(!head.contains("content-length"));
+        assert!(head.contains("connection: close\r\n"));
+    }
+
+    #[test]
+    fn reject_http_2() {
+        let head = Response::builder().version(Version::HTTP_2).body(()).unwrap();
+        assert!(HeaderEncoder.encode((head, PayloadSize::Empty), &mut BytesMut::new()).is_err());
+    }
+}
@@ -28,5 +28,5 @@
 mod header_decoder;
 mod header_encoder;
 
-pub use header_decoder::HeaderDecoder;
+pub use header_decoder::{HeaderDecoder, HeaderLimits};
 pub use header_encoder::HeaderEncoder;
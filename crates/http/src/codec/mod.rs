@@ -10,6 +10,7 @@
 //!
 //! - Request handling:
 //!   - [`RequestDecoder`]: Decodes incoming HTTP requests
+//!   - [`HeaderLimits`]: Limits enforced while parsing request headers
 //!   - Header parsing via [`header`] module
 //!   - Payload decoding via [`body`] module
 //!
@@ -49,5 +50,6 @@ mod header;
 mod request_decoder;
 mod response_encoder;
 
+pub use header::HeaderLimits;
 pub use request_decoder::RequestDecoder;
 pub use response_encoder::ResponseEncoder;
@@ -23,7 +23,7 @@
 //! ```
 
 use crate::codec::body::PayloadDecoder;
-use crate::codec::header::HeaderDecoder;
+use crate::codec::header::{HeaderDecoder, HeaderLimits};
 use crate::protocol::{Message, ParseError, PayloadItem, PayloadSize, RequestHeader};
 use bytes::BytesMut;
 use tokio_util::codec::Decoder;
@@ -39,10 +39,22 @@ use tokio_util::codec::Decoder;
 /// The decoder maintains its state through the `payload_decoder` field:
 /// - `None`: Currently parsing headers
 /// - `Some(PayloadDecoder)`: Currently parsing payload
+///
+/// # Trailers
+///
+/// Trailer fields of a chunked body are decoded with the header limits and yielded as
+/// [`PayloadItem::Trailers`] right before [`PayloadItem::Eof`].
+///
+/// # Body Size Limit
+///
+/// With a maximum body size set, a request whose `Content-Length` exceeds the limit is rejected
+/// right after its header, and a chunked body fails once its chunks cross the limit. Both
+/// cases yield [`ParseError::PayloadTooLarge`].
 #[derive(Debug)]
 pub struct RequestDecoder {
     header_decoder: HeaderDecoder,
     payload_decoder: Option<PayloadDecoder>,
+    max_body_size: Option<u64>,
 }
 
 impl RequestDecoder {
@@ -50,11 +62,32 @@ impl RequestDecoder {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Creates a new `RequestDecoder` that enforces the given header limits
+    #[must_use]
+    pub fn with_limits(limits: HeaderLimits) -> Self {
+        Self { header_decoder: HeaderDecoder::with_limits(limits), payload_decoder: None, max_body_size: None }
+    }
+
+    /// Sets the maximum size of a request body, `None` means unlimited
+    #[must_use]
+    pub fn with_max_body_size(mut self, max_body_size: Option<u64>) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    fn payload_decoder(&self, payload_size: PayloadSize) -> Result<PayloadDecoder, ParseError> {
+        match (payload_size, self.max_body_size) {
+            (PayloadSize::Length(length), Some(max_size)) if length > max_size => Err(ParseError::payload_too_large(length, max_size)),
+            (PayloadSize::Chunked, max_size) => Ok(PayloadDecoder::chunked_with_limits(self.header_decoder.limits(), max_size)),
+            (payload_size, _) => Ok(payload_size.into()),
+        }
+    }
 }
 
 impl Default for RequestDecoder {
     fn default() -> Self {
-        Self { header_decoder: HeaderDecoder, payload_decoder: None }
+        Self::with_limits(HeaderLimits::default())
     }
 }
 
@@ -74,7 +107,7 @@ impl Decoder for RequestDecoder {
         // parse payload if have payload_decoder
         if let Some(payload_decoder) = &mut self.payload_decoder {
             let message = match payload_decoder.decode(src)? {
-                Some(item @ PayloadItem::Chunk(_)) => Some(Message::Payload(item)),
+                Some(item @ (PayloadItem::Chunk(_) | PayloadItem::Trailers(_))) => Some(Message::Payload(item)),
                 Some(item @ PayloadItem::Eof) => {
                     // no need payload decoder in this request now
                     self.payload_decoder.take();
@@ -89,7 +122,7 @@ impl Decoder for RequestDecoder {
         // parse request
         let message = match self.header_decoder.decode(src)? {
             Some((header, payload_size)) => {
-                self.payload_decoder = Some(payload_size.into());
+                self.payload_decoder = Some(self.payload_decoder(payload_size)?);
                 Some(Message::Header((header, payload_size)))
             }
             None => None,
@@ -98,3 +131,31 @@ impl Decoder for RequestDecoder {
         Ok(message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_content_length_over_max_body_size() {
+        let mut decoder = RequestDecoder::new().with_max_body_size(Some(4));
+
+        let mut buf = BytesMut::from("POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nabcd");
+        assert!(matches!(decoder.decode(&mut buf), Ok(Some(Message::Header(_)))));
+        assert!(matches!(decoder.decode(&mut buf), Ok(Some(Message::Payload(PayloadItem::Chunk(_))))));
+        assert!(matches!(decoder.decode(&mut buf), Ok(Some(Message::Payload(PayloadItem::Eof)))));
+
+        let mut buf = BytesMut::from("POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nabcde");
+        assert!(matches!(decoder.decode(&mut buf), Err(ParseError::PayloadTooLarge { current_size: 5, max_size: 4 })));
+    }
+
+    #[test]
+    fn reject_chunked_body_over_max_body_size() {
+        let mut decoder = RequestDecoder::new().with_max_body_size(Some(4));
+
+        let mut buf = BytesMut::from("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n");
+        assert!(matches!(decoder.decode(&mut buf), Ok(Some(Message::Header(_)))));
+        assert!(matches!(decoder.decode(&mut buf), Ok(Some(Message::Payload(PayloadItem::Chunk(_))))));
+        assert!(matches!(decoder.decode(&mut buf), Err(ParseError::PayloadTooLarge { .. })));
+    }
+}
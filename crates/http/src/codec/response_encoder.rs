@@ -23,8 +23,10 @@
 
 use crate::codec::body::PayloadEncoder;
 use crate::codec::header::HeaderEncoder;
+use crate::is_bodiless_status;
 use crate::protocol::{Message, PayloadSize, ResponseHead, SendError};
 use bytes::{Buf, BytesMut};
+use http::Version;
 use std::io;
 use std::io::ErrorKind;
 use tokio_util::codec::Encoder;
@@ -49,6 +51,14 @@ impl ResponseEncoder {
     pub fn new() -> Self {
         ResponseEncoder::default()
     }
+
+    /// Discards the payload of the response whose header was encoded last
+    ///
+    /// A response to a `HEAD` request announces the body a `GET` would have had, but none
+    /// follows its header, so no payload items are encoded for it.
+    pub fn skip_payload(&mut self) {
+        self.payload_encoder.take();
+    }
 }
 
 impl Default for ResponseEncoder {
@@ -80,8 +90,8 @@ impl<D: Buf> Encoder<Message<(ResponseHead, PayloadSize), D>> for ResponseEncode
                     return Err(io::Error::from(ErrorKind::InvalidInput).into());
                 }
 
-                // Create a payload encoder based on the payload size
-                let payload_encoder = parse_payload_encoder(payload_size);
+                // Create a payload encoder based on the payload size and http version
+                let payload_encoder = parse_payload_encoder(&head, payload_size);
                 self.payload_encoder = Some(payload_encoder);
                 // Encode the response headers
                 self.header_encoder.encode((head, payload_size), dst)
@@ -114,14 +124,19 @@ impl<D: Buf> Encoder<Message<(ResponseHead, PayloadSize), D>> for ResponseEncode
 ///
 /// # Arguments
 ///
+/// * `head` - The head of the response
 /// * `payload_size` - The size specification for the payload
 ///
 /// # Returns
 ///
-/// Returns a [`PayloadEncoder`] configured according to the payload size
-fn parse_payload_encoder(payload_size: PayloadSize) -> PayloadEncoder {
+/// Returns a [`PayloadEncoder`] configured according to the payload size, HTTP/1.0
+/// responses of unknown length are close-delimited since chunked encoding isn't available.
+/// Responses whose status forbids a body get an empty encoder.
+fn parse_payload_encoder(head: &ResponseHead, payload_size: PayloadSize) -> PayloadEncoder {
     match payload_size {
+        _ if is_bodiless_status(head.status()) => PayloadEncoder::empty(),
         PayloadSize::Length(size) => PayloadEncoder::fix_length(size),
+        PayloadSize::Chunked if head.version() == Version::HTTP_10 => PayloadEncoder::close_delimited(),
         PayloadSize::Chunked => PayloadEncoder::chunked(),
         PayloadSize::Empty => PayloadEncoder::empty(),
     }
@@ -0,0 +1,175 @@
+//! Connection level configuration.
+//!
+//! [`ConnectionConfig`] bundles the deadlines that protect a connection from clients that
+//! send too slowly, stop reading, or simply keep an idle socket open. Every deadline can
+//! be disabled by setting it to `None`. It also carries the [`HeaderLimits`] used to
+//! decode request headers, the maximum request body size, and the mapping from request
+//! errors to response status codes.
+
+use crate::codec::HeaderLimits;
+use crate::protocol::ParseError;
+use http::StatusCode;
+use std::time::Duration;
+
+const DEFAULT_HEADER_READ_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(75);
+const DEFAULT_BODY_READ_TIMEOUT: Duration = Duration::from_secs(60);
+const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Configuration for an [`HttpConnection`](crate::connection::HttpConnection).
+///
+/// Each timeout ends with a defined outcome:
+///
+/// | Timeout | Measured | Outcome |
+/// |---------|----------|---------|
+/// | `header_read_timeout` | from the first byte of a request header until the header is complete | `408 Request Timeout`, then close with a timeout error |
+/// | `keep_alive_timeout` | idle time between the end of a response and the first byte of the next request | silent close |
+/// | `body_read_timeout` | time a body read waits for the next chunk from the client | `408 Request Timeout`, then close with a timeout error |
+/// | `write_timeout` | time a response write waits for the client to accept more bytes | close with a timeout error |
+///
+/// A freshly accepted connection that sends nothing is closed silently once
+/// `header_read_timeout` elapses. Timeout errors are told apart from other failures with
+/// [`HttpError::is_timeout`](crate::protocol::HttpError::is_timeout).
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use micro_http::codec::HeaderLimits;
+/// use micro_http::connection::ConnectionConfig;
+///
+/// let config = ConnectionConfig::default()
+///     .with_header_read_timeout(Some(Duration::from_secs(10)))
+///     .with_keep_alive_timeout(None)
+///     .with_header_limits(HeaderLimits::default().with_max_header_bytes(32 * 1024));
+///
+/// assert_eq!(config.header_read_timeout(), Some(Duration::from_secs(10)));
+/// assert_eq!(config.keep_alive_timeout(), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectionConfig {
+    header_read_timeout: Option<Duration>,
+    keep_alive_timeout: Option<Duration>,
+    body_read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    header_limits: HeaderLimits,
+    max_body_size: Option<u64>,
+    error_status: fn(&ParseError) -> Option<StatusCode>,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            header_read_timeout: Some(DEFAULT_HEADER_READ_TIMEOUT),
+            keep_alive_timeout: Some(DEFAULT_KEEP_ALIVE_TIMEOUT),
+            body_read_timeout: Some(DEFAULT_BODY_READ_TIMEOUT),
+            write_timeout: Some(DEFAULT_WRITE_TIMEOUT),
+            header_limits: HeaderLimits::default(),
+            max_body_size: None,
+            error_status: ParseError::status_code,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Sets the deadline for receiving a complete request header, defaults to 30 seconds.
+    #[must_use]
+    pub fn with_header_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.header_read_timeout = timeout;
+        self
+    }
+
+    /// Sets how long an idle persistent connection waits for the next request, defaults to 75 seconds.
+    #[must_use]
+    pub fn with_keep_alive_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a request body read may stall waiting for data, defaults to 60 seconds.
+    #[must_use]
+    pub fn with_body_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.body_read_timeout = timeout;
+        self
+    }
+
+    /// Sets how long writing a response may wait for the client to accept more bytes, defaults to
+    /// 60 seconds.
+    ///
+    /// The deadline restarts whenever the client accepts bytes, so it doesn't limit the time a
+    /// whole response takes to deliver.
+    #[must_use]
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Sets the limits enforced while decoding request headers.
+    #[must_use]
+    pub fn with_header_limits(mut self, header_limits: HeaderLimits) -> Self {
+        self.header_limits = header_limits;
+        self
+    }
+
+    /// Sets the maximum size of a request body, defaults to `None` which means unlimited.
+    ///
+    /// A larger `Content-Length` is rejected before the handler runs, and a chunked body fails
+    /// once it crosses the limit; both are answered with `413 Content Too Large`.
+    #[must_use]
+    pub fn with_max_body_size(mut self, max_body_size: Option<u64>) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sets how errors reading a request map to the status of the response sent before the
+    /// connection is closed, defaults to [`ParseError::status_code`].
+    ///
+    /// Returning `None` closes the connection without a response.
+    #[must_use]
+    pub fn with_error_status(mut self, error_status: fn(&ParseError) -> Option<StatusCode>) -> Self {
+        self.error_status = error_status;
+        self
+    }
+
+    /// Returns the deadline for receiving a complete request header.
+    #[must_use]
+    pub fn header_read_timeout(&self) -> Option<Duration> {
+        self.header_read_timeout
+    }
+
+    /// Returns how long an idle persistent connection waits for the next request.
+    #[must_use]
+    pub fn keep_alive_timeout(&self) -> Option<Duration> {
+        self.keep_alive_timeout
+    }
+
+    /// Returns how long a request body read may stall waiting for data.
+    #[must_use]
+    pub fn body_read_timeout(&self) -> Option<Duration> {
+        self.body_read_timeout
+    }
+
+    /// Returns how long writing a response may wait for the client to accept more bytes.
+    #[must_use]
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Returns the limits enforced while decoding request headers.
+    #[must_use]
+    pub fn header_limits(&self) -> HeaderLimits {
+        self.header_limits
+    }
+
+    /// Returns the maximum size of a request body.
+    #[must_use]
+    pub fn max_body_size(&self) -> Option<u64> {
+        self.max_body_size
+    }
+
+    /// Returns the mapping from request errors to response status codes.
+    #[must_use]
+    pub fn error_status(&self) -> fn(&ParseError) -> Option<StatusCode> {
+        self.error_status
+    }
+}
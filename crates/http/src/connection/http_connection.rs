@@ -1,20 +1,28 @@
 use bytes::Bytes;
 use std::error::Error;
 use std::fmt::{Debug, Display};
+use std::future::{pending, poll_fn};
+use std::pin::{Pin, pin};
+use std::task::Poll;
+use std::time::Duration;
 
-use futures::StreamExt;
-use http::header::EXPECT;
-use http::{Response, StatusCode};
+use futures::future::{Fuse, FusedFuture};
+use futures::{FutureExt, StreamExt};
+use http::header::{CONNECTION, EXPECT};
+use http::{HeaderValue, Method, Response, StatusCode, Version};
 use http_body::Body;
 use http_body_util::{BodyExt, Empty};
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::time::{Sleep, sleep};
 
 use crate::codec::RequestDecoder;
 use crate::handler::Handler;
 use crate::protocol::body::ReqBody;
 use crate::protocol::{HttpError, Message, ParseError, PayloadItem, PayloadSize, RequestHeader, ResponseHead, SendError};
+use crate::{connection_has_token, is_bodiless_status};
 
 use crate::connection::message_writer::MessageWriter;
+use crate::connection::{ConnectionConfig, ConnectionInfo};
 use tokio_util::codec::FramedRead;
 use tracing::{error, info};
 
@@ -25,6 +33,27 @@ use tracing::{error, info};
 /// - Processing request headers and bodies
 /// - Handling expect-continue mechanism
 /// - Streaming responses back to clients
+/// - Deciding whether the connection persists after each response
+///
+/// A connection is kept open for the next request unless the request is HTTP/1.0
+/// without `Connection: keep-alive`, the request carries `Connection: close`, or the
+/// handler sets `Connection: close` on its response. When the connection is not
+/// persistent, the response is sent with `Connection: close` and the writer is shut
+/// down once the response has been flushed.
+///
+/// The timeouts in [`ConnectionConfig`] guard every phase of the connection: waiting
+/// for the next request, receiving its header, reading its body and writing the response.
+///
+/// A request that can't be parsed is answered with a minimal response carrying
+/// `Connection: close`, whose status comes from [`ConnectionConfig::error_status`],
+/// before the connection is torn down.
+///
+/// With [`process_with_shutdown`](Self::process_with_shutdown) the connection can be shut
+/// down gracefully: an idle connection closes right away, while a request in flight is
+/// answered first, with `Connection: close`.
+///
+/// The [`ConnectionInfo`] set with [`with_connection_info`](Self::with_connection_info) is
+/// inserted into the extensions of every request.
 ///
 /// # Type Parameters
 ///
@@ -39,6 +68,8 @@ where
 {
     framed_read: Option<FramedRead<R, RequestDecoder>>,
     message_writer: MessageWriter<W>,
+    config: ConnectionConfig,
+    connection_info: Option<ConnectionInfo>,
 }
 
 impl<R, W> HttpConnection<R, W>
@@ -46,25 +77,108 @@ where
     R: AsyncRead + Unpin + Send + Debug,
     W: AsyncWrite + Unpin + Debug,
 {
+    /// Creates a connection with the default [`ConnectionConfig`].
     pub fn new(reader: R, writer: W) -> Self {
+        Self::with_config(reader, writer, ConnectionConfig::default())
+    }
+
+    /// Creates a connection with the given [`ConnectionConfig`].
+    pub fn with_config(reader: R, writer: W, config: ConnectionConfig) -> Self {
         Self {
-            framed_read: Some(FramedRead::with_capacity(reader, RequestDecoder::new(), 8 * 1024)),
-            message_writer: MessageWriter::with_capacity(writer, 8 * 1024),
+            framed_read: Some(FramedRead::with_capacity(
+                reader,
+                RequestDecoder::with_limits(config.header_limits()).with_max_body_size(config.max_body_size()),
+                8 * 1024,
+            )),
+            message_writer: MessageWriter::with_capacity(writer, 8 * 1024).with_write_timeout(config.write_timeout()),
+            config,
+            connection_info: None,
         }
     }
 
-    pub async fn process<H>(mut self, handler: &H) -> Result<(), HttpError>
+    /// Sets the metadata inserted into the extensions of every request read from this connection.
+    #[must_use]
+    pub fn with_connection_info(mut self, connection_info: ConnectionInfo) -> Self {
+        self.connection_info = Some(connection_info);
+        self
+    }
+
+    /// Processes requests until the connection closes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a request can't be parsed, a response can't be sent, or a timeout of
+    /// the [`ConnectionConfig`] other than the keep-alive timeout elapses, see
+    /// [`HttpError::is_timeout`]. The client is answered first where it still can be.
+    pub async fn process<H>(self, handler: &H) -> Result<(), HttpError>
+    where
+        H: Handler,
+        H::RespBody: Body<Data = Bytes> + Unpin,
+        <H::RespBody as Body>::Error: Display,
+    {
+        self.process_with_shutdown(handler, pending()).await
+    }
+
+    /// Processes requests until the connection closes or `shutdown` completes.
+    ///
+    /// Once `shutdown` completes, the connection closes as soon as no request is in flight.
+    /// A request that is being received or handled is still answered, its response carries
+    /// `Connection: close`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`HttpConnection::process`].
+    pub async fn process_with_shutdown<H, S>(mut self, handler: &H, shutdown: S) -> Result<(), HttpError>
+    where
+        H: Handler,
+        H::RespBody: Body<Data = Bytes> + Unpin,
+        <H::RespBody as Body>::Error: Display,
+        S: Future<Output = ()>,
+    {
+        let shutdown = pin!(shutdown.fuse());
+        self.process_requests(handler, shutdown).await
+    }
+
+    async fn process_requests<H, S>(&mut self, handler: &H, mut shutdown: Pin<&mut Fuse<S>>) -> Result<(), HttpError>
     where
         H: Handler,
         H::RespBody: Body<Data = Bytes> + Unpin,
         <H::RespBody as Body>::Error: Display,
+        S: Future<Output = ()>,
     {
+        // a fresh connection gets the header read timeout to send its first byte
+        let mut idle_timeout = self.config.header_read_timeout();
+
         loop {
-            let framed_read = self.framed_read.as_mut().expect("framed reader must be available while processing requests");
+            let message = match self.read_message(idle_timeout, shutdown.as_mut()).await {
+                Ok(message) => message,
+                Err(ReadInterrupt::Idle) => {
+                    info!("connection idle timeout, shutdown");
+                    return Ok(());
+                }
+                Err(ReadInterrupt::Shutdown) => {
+                    info!("connection is idle while shutting down, shutdown");
+                    self.message_writer.shutdown().await?;
+                    return Ok(());
+                }
+                Err(ReadInterrupt::Header) => {
+                    info!("request header read timeout, shutdown after response");
+                    let e = ParseError::timeout("request header read timed out");
+                    self.reject(&e, Version::HTTP_11).await?;
+                    self.message_writer.shutdown().await?;
+                    return Err(e.into());
+                }
+            };
 
-            match framed_read.next().await {
+            match message {
                 Some(Ok(Message::Header((header, payload_size)))) => {
-                    self.do_process(header, payload_size, handler).await?;
+                    let keep_alive = self.do_process(header, payload_size, handler, shutdown.as_mut()).await?;
+                    if !keep_alive {
+                        info!("connection is not persistent, shutdown after response");
+                        self.message_writer.shutdown().await?;
+                        return Ok(());
+                    }
+                    idle_timeout = self.config.keep_alive_timeout();
                 }
 
                 Some(Ok(Message::Payload(PayloadItem::Eof))) => continue,
@@ -72,7 +186,7 @@ where
                 Some(Ok(Message::Payload(_))) => {
                     error!("error status because chunked has read in do_process");
                     let error_response = build_error_response(StatusCode::BAD_REQUEST);
-                    self.do_send_response(error_response).await?;
+                    self.do_send_response(error_response, ResponseContext::closing(Version::HTTP_11)).await?;
                     return Err(ParseError::invalid_body("need header while receive body").into());
                 }
 
@@ -83,6 +197,8 @@ where
 
                 Some(Err(e)) => {
                     error!("can't receive next request, cause {}", e);
+                    self.reject(&e, Version::HTTP_11).await?;
+                    self.message_writer.shutdown().await?;
                     return Err(e.into());
                 }
 
@@ -94,14 +210,91 @@ where
         }
     }
 
-    async fn do_process<H>(&mut self, header: RequestHeader, payload_size: PayloadSize, handler: &H) -> Result<(), HttpError>
+    /// Waits for the next message from the client.
+    ///
+    /// While nothing of the next request has arrived, `idle_timeout` applies and a completed
+    /// `shutdown` stops the wait. Once the first bytes are buffered, the header read timeout
+    /// applies until the header is decoded.
+    async fn read_message<S>(
+        &mut self,
+        idle_timeout: Option<Duration>,
+        mut shutdown: Pin<&mut Fuse<S>>,
+    ) -> Result<Option<Result<Message<(RequestHeader, PayloadSize)>, ParseError>>, ReadInterrupt>
+    where
+        S: Future<Output = ()>,
+    {
+        let header_read_timeout = self.config.header_read_timeout();
+        let framed_read = self.framed_read.as_mut().expect("framed reader must be available while processing requests");
+
+        let mut idle_sleep = pin!(idle_timeout.map(sleep));
+        let mut header_sleep = pin!(None::<Sleep>);
+
+        poll_fn(|cx| {
+            if let Poll::Ready(message) = framed_read.poll_next_unpin(cx) {
+                return Poll::Ready(Ok(message));
+            }
+
+            // keeps the shutdown signal registered, it's checked again once the request is answered
+            let shutting_down = shutdown.as_mut().poll(cx).is_ready() || shutdown.is_terminated();
+
+            if framed_read.read_buffer().is_empty() {
+                if shutting_down {
+                    return Poll::Ready(Err(ReadInterrupt::Shutdown));
+                }
+                if let Some(idle_sleep) = idle_sleep.as_mut().as_pin_mut()
+                    && idle_sleep.poll(cx).is_ready()
+                {
+                    return Poll::Ready(Err(ReadInterrupt::Idle));
+                }
+            } else if let Some(header_read_timeout) = header_read_timeout {
+                if header_sleep.is_none() {
+                    header_sleep.set(Some(sleep(header_read_timeout)));
+                }
+                if let Some(header_sleep) = header_sleep.as_mut().as_pin_mut()
+                    && header_sleep.poll(cx).is_ready()
+                {
+                    return Poll::Ready(Err(ReadInterrupt::Header));
+                }
+            }
+
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Answers a request that couldn't be read with the status that [`ConnectionConfig::error_status`]
+    /// maps the error to, announcing `Connection: close`. Nothing is written if the error maps to no status.
+    async fn reject(&mut self, error: &ParseError, version: Version) -> Result<(), HttpError> {
+        let Some(status_code) = (self.config.error_status())(error) else {
+            return Ok(());
+        };
+
+        let error_response = build_error_response(status_code);
+        self.do_send_response(error_response, ResponseContext::closing(version)).await?;
+        Ok(())
+    }
+
+    /// Processes a single request and returns whether the connection should be kept alive.
+    ///
+    /// The connection isn't kept alive once `shutdown` has completed.
+    async fn do_process<H, S>(
+        &mut self,
+        header: RequestHeader,
+        payload_size: PayloadSize,
+        handler: &H,
+        mut shutdown: Pin<&mut Fuse<S>>,
+    ) -> Result<bool, HttpError>
     where
         H: Handler,
         H::RespBody: Body<Data = Bytes> + Unpin,
         <H::RespBody as Body>::Error: Display,
+        S: Future<Output = ()>,
     {
-        // Check if the request header contains the "Expect: 100-continue" field.
-        if let Some(value) = header.headers().get(EXPECT) {
+        // Check if the request header contains the "Expect: 100-continue" field,
+        // HTTP/1.0 clients don't understand interim responses so the expectation is ignored for them.
+        if let Some(value) = header.headers().get(EXPECT)
+            && header.version() == Version::HTTP_11
+        {
             let slice = value.as_bytes();
             // Verify if the value of the "Expect" field is "100-continue".
             if slice.len() >= 4 && &slice[0..4] == b"100-" {
@@ -114,56 +307,119 @@ where
             }
         }
 
+        let mut response_context =
+            ResponseContext { version: header.version(), keep_alive: header.is_keep_alive(), head: header.method() == Method::HEAD };
+
         let framed_read = self.framed_read.take().expect("framed reader must exist when creating request body");
-        let (req_body, req_body_state) = ReqBody::create_req_body(framed_read, payload_size);
-        let request = header.body(req_body);
+        let (req_body, req_body_state) = ReqBody::create_req_body(framed_read, payload_size, self.config.body_read_timeout());
+        let mut request = header.body(req_body);
+        if let Some(connection_info) = self.connection_info {
+            request.extensions_mut().insert(connection_info);
+        }
 
         let response_result = handler.call(request).await;
 
-        let framed_read = req_body_state.finish().await?;
+        let framed_read = match req_body_state.finish().await {
+            Ok(framed_read) => framed_read,
+            Err(e) => {
+                if matches!(e, ParseError::Timeout { .. }) {
+                    info!("request body read timeout, shutdown after response");
+                } else {
+                    error!("can't read request body, cause {}", e);
+                }
+                self.reject(&e, response_context.version).await?;
+                self.message_writer.shutdown().await?;
+                return Err(e.into());
+            }
+        };
         self.framed_read = Some(framed_read);
 
-        self.send_response(response_result).await
+        if shutdown.is_terminated() || futures::poll!(shutdown.as_mut()).is_ready() {
+            info!("shutting down, close the connection after this response");
+            response_context.keep_alive = false;
+        }
+
+        self.send_response(response_result, response_context).await
     }
 
-    async fn send_response<T, E>(&mut self, response_result: Result<Response<T>, E>) -> Result<(), HttpError>
+    async fn send_response<T, E>(&mut self, response_result: Result<Response<T>, E>, context: ResponseContext) -> Result<bool, HttpError>
     where
         T: Body + Unpin,
         T::Error: Display,
         E: Into<Box<dyn Error + Send + Sync>>,
     {
         match response_result {
-            Ok(response) => self.do_send_response(response).await,
+            Ok(response) => self.do_send_response(response, context).await,
             Err(e) => {
                 error!("handle response error, cause: {}", e.into());
                 let error_response = build_error_response(StatusCode::INTERNAL_SERVER_ERROR);
-                self.do_send_response(error_response).await
+                self.do_send_response(error_response, context).await
             }
         }
     }
 
-    async fn do_send_response<T>(&mut self, response: Response<T>) -> Result<(), HttpError>
+    /// Sends the response and returns whether the connection should be kept alive.
+    ///
+    /// The response takes the request's HTTP version. The connection persists only if the
+    /// request allows it, the response itself doesn't carry `Connection: close` and the body
+    /// isn't close-delimited (an HTTP/1.0 body of unknown length); otherwise `Connection: close`
+    /// is announced. HTTP/1.0 clients that asked for keep-alive get `Connection: keep-alive` back.
+    ///
+    /// The body is streamed: the encoded bytes are written out whenever enough of them are
+    /// buffered, and the rest once the body ends.
+    ///
+    /// Trailer frames are written after the last chunk of a chunked body, bodies of known length
+    /// and close-delimited bodies can't carry them so they are dropped.
+    ///
+    /// The body isn't sent for a `HEAD` request, whose response keeps the framing headers a `GET`
+    /// would have had, nor for `1xx`, `204` and `304` responses, which get no framing headers.
+    async fn do_send_response<T>(&mut self, response: Response<T>, context: ResponseContext) -> Result<bool, HttpError>
     where
         T: Body + Unpin,
         T::Error: Display,
     {
-        let (header_parts, mut body) = response.into_parts();
+        let (mut header_parts, mut body) = response.into_parts();
+        header_parts.version = context.version;
 
         let payload_size: PayloadSize = body.size_hint().into();
-        
+
+        let bodiless_status = is_bodiless_status(header_parts.status);
+        let close_delimited = context.version == Version::HTTP_10 && payload_size.is_chunked() && !bodiless_status;
+        let keep_alive = context.keep_alive && !close_delimited && !connection_has_token(&header_parts.headers, "close");
+        if !keep_alive {
+            header_parts.headers.insert(CONNECTION, HeaderValue::from_static("close"));
+        } else if context.version == Version::HTTP_10 {
+            header_parts.headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+        }
+
         let header = Message::<_, T::Data>::Header((ResponseHead::from_parts(header_parts, ()), payload_size));
 
         self.message_writer.write(header)?;
 
+        if context.head || bodiless_status {
+            self.message_writer.skip_payload();
+            self.message_writer.flush().await?;
+            return Ok(keep_alive);
+        }
+
         loop {
             match body.frame().await {
                 Some(Ok(frame)) => {
-                    let payload_item =
-                        frame.into_data().map(PayloadItem::Chunk).map_err(|_e| SendError::invalid_body("resolve body response error"))?;
+                    let payload_item = match frame.into_data() {
+                        Ok(data) => PayloadItem::Chunk(data),
+                        Err(frame) => frame
+                            .into_trailers()
+                            .map(PayloadItem::Trailers)
+                            .map_err(|_e| SendError::invalid_body("resolve body response error"))?,
+                    };
 
                     self.message_writer
                         .write(Message::Payload(payload_item))
                         .map_err(|_e| SendError::invalid_body("can't send response"))?;
+
+                    if self.message_writer.should_flush() {
+                        self.message_writer.flush().await?;
+                    }
                 }
                 Some(Err(e)) => return Err(SendError::invalid_body(format!("resolve response body error: {e}")).into()),
                 None => {
@@ -172,14 +428,625 @@ where
                         .write(Message::Payload(PayloadItem::<T::Data>::Eof))
                         .map_err(|e| SendError::invalid_body(format!("can't send eof response: {}", e)))?;
                     self.message_writer.flush().await?;
-                    self.message_writer.clear_buf();
-                    return Ok(());
+                    return Ok(keep_alive);
                 }
             }
         }
     }
 }
 
+/// Why waiting for the next message from the client stopped.
+enum ReadInterrupt {
+    /// No request arrived within the idle timeout
+    Idle,
+    /// A request header started but wasn't completed within the header read timeout
+    Header,
+    /// The connection is shutting down while no request is in flight
+    Shutdown,
+}
+
+/// Per-request facts the connection needs when writing the response.
+#[derive(Debug, Clone, Copy)]
+struct ResponseContext {
+    /// HTTP version of the request, which the response mirrors
+    version: Version,
+    /// Whether the request allows the connection to stay open
+    keep_alive: bool,
+    /// Whether the request is a `HEAD` request, whose response has no body
+    head: bool,
+}
+
+impl ResponseContext {
+    fn closing(version: Version) -> Self {
+        Self { version, keep_alive: false, head: false }
+    }
+}
+
 fn build_error_response(status_code: StatusCode) -> Response<Empty<Bytes>> {
     Response::builder().status(status_code).body(Empty::<Bytes>::new()).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::HeaderLimits;
+    use crate::handler::make_handler;
+    use http::HeaderMap;
+    use http_body::Frame;
+    use http_body_util::StreamBody;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Context;
+    use tokio::io::{AsyncReadExt, DuplexStream, WriteHalf, duplex};
+    use tokio::sync::Notify;
+
+    async fn hello(_request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+        Ok(Response::new("hello".to_string()))
+    }
+
+    async fn streaming(
+        _request: http::Request<ReqBody>,
+    ) -> Result<Response<impl Body<Data = Bytes, Error = Infallible> + Unpin>, Box<dyn Error + Send + Sync>> {
+        let frames = ["hello", " world"].map(|s| Ok(Frame::data(Bytes::from_static(s.as_bytes()))));
+        Ok(Response::new(StreamBody::new(futures::stream::iter(frames))))
+    }
+
+    async fn streaming_with_trailers(
+        _request: http::Request<ReqBody>,
+    ) -> Result<Response<impl Body<Data = Bytes, Error = Infallible> + Unpin>, Box<dyn Error + Send + Sync>> {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", HeaderValue::from_static("5d41"));
+        let frames = [Ok(Frame::data(Bytes::from_static(b"hello"))), Ok(Frame::trailers(trailers))];
+        Ok(Response::new(StreamBody::new(futures::stream::iter(frames))))
+    }
+
+    async fn echo(request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+        let body = request.into_body().collect().await?.to_bytes();
+        Ok(Response::new(String::from_utf8(body.to_vec())?))
+    }
+
+    async fn large(_request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+        Ok(Response::new("x".repeat(64 * 1024)))
+    }
+
+    async fn hello_and_close(_request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+        Ok(Response::builder().header(CONNECTION, "close").body("bye".to_string()).unwrap())
+    }
+
+    /// Writes `request` to a fresh connection, closes the client's write side when `half_close` is set,
+    /// and returns everything the server wrote back.
+    async fn exchange<H>(handler: &H, request: &str, half_close: bool) -> (Result<(), HttpError>, String)
+    where
+        H: Handler,
+        H::RespBody: Body<Data = Bytes> + Unpin,
+        <H::RespBody as Body>::Error: Display,
+    {
+        exchange_with_config(handler, request, half_close, ConnectionConfig::default()).await
+    }
+
+    async fn exchange_with_config<H>(
+        handler: &H,
+        request: &str,
+        half_close: bool,
+        config: ConnectionConfig,
+    ) -> (Result<(), HttpError>, String)
+    where
+        H: Handler,
+        H::RespBody: Body<Data = Bytes> + Unpin,
+        <H::RespBody as Body>::Error: Display,
+    {
+        let (mut client, server): (DuplexStream, DuplexStream) = duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(server);
+
+        client.write_all(request.as_bytes()).await.unwrap();
+        if half_close {
+            client.shutdown().await.unwrap();
+        }
+
+        let result = HttpConnection::with_config(reader, writer, config).process(handler).await;
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        (result, response)
+    }
+
+    #[tokio::test]
+    async fn connection_info_reaches_every_request() {
+        async fn peer(request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+            let info = request.extensions().get::<ConnectionInfo>().ok_or("missing connection info")?;
+            Ok(Response::new(format!("{:?}/{}", info.peer_addr(), info.is_tls())))
+        }
+
+        let (mut client, server) = duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(server);
+        client.write_all(b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n").await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let info = ConnectionInfo::default().with_peer_addr("192.0.2.10:51234".parse().unwrap()).with_tls(true);
+        HttpConnection::new(reader, writer).with_connection_info(info).process(&make_handler(peer)).await.unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response.matches("Some(192.0.2.10:51234)/true").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn http_11_keeps_connection_alive() {
+        let handler = make_handler(hello);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n", true).await;
+
+        result.unwrap();
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(!response.contains("connection: close"));
+    }
+
+    #[tokio::test]
+    async fn http_11_connection_close_ends_connection() {
+        let handler = make_handler(hello);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.1\r\nConnection: close\r\n\r\nGET / HTTP/1.1\r\n\r\n", false).await;
+
+        result.unwrap();
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 1);
+        assert!(response.contains("connection: close\r\n"));
+    }
+
+    #[tokio::test]
+    async fn http_10_closes_by_default() {
+        let handler = make_handler(hello);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.0\r\n\r\n", false).await;
+
+        result.unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+        assert!(response.ends_with("hello"));
+    }
+
+    #[tokio::test]
+    async fn http_10_keep_alive_is_echoed() {
+        let handler = make_handler(hello);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\nGET / HTTP/1.0\r\n\r\n", false).await;
+
+        result.unwrap();
+        assert_eq!(response.matches("HTTP/1.0 200 OK").count(), 2);
+        assert_eq!(response.matches("connection: keep-alive\r\n").count(), 1);
+        assert_eq!(response.matches("connection: close\r\n").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn http_10_streaming_body_is_close_delimited() {
+        let handler = make_handler(streaming);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n", false).await;
+
+        result.unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(!response.contains("transfer-encoding"));
+        assert!(response.contains("connection: close\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello world"));
+    }
+
+    #[tokio::test]
+    async fn response_trailers_follow_last_chunk() {
+        let handler = make_handler(streaming_with_trailers);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.1\r\n\r\n", true).await;
+
+        result.unwrap();
+        assert!(response.contains("transfer-encoding: chunked\r\n"));
+        assert!(response.ends_with("\r\n\r\n5\r\nhello\r\n0\r\nx-checksum: 5d41\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn http_10_drops_response_trailers() {
+        let handler = make_handler(streaming_with_trailers);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.0\r\n\r\n", false).await;
+
+        result.unwrap();
+        assert!(response.ends_with("\r\n\r\nhello"));
+    }
+
+    #[tokio::test]
+    async fn head_response_has_no_body() {
+        let handler = make_handler(hello);
+        let (result, response) = exchange(&handler, "HEAD / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n", true).await;
+
+        result.unwrap();
+        let (head, get) = response.split_at(response.rfind("HTTP/1.1").unwrap());
+        assert!(head.contains("content-length: 5\r\n"));
+        assert!(head.ends_with("\r\n\r\n"));
+        assert!(get.contains("content-length: 5\r\n"));
+        assert!(get.ends_with("\r\n\r\nhello"));
+    }
+
+    #[tokio::test]
+    async fn head_response_keeps_chunked_framing() {
+        let handler = make_handler(streaming);
+        let (result, response) = exchange(&handler, "HEAD / HTTP/1.1\r\n\r\n", true).await;
+
+        result.unwrap();
+        assert!(response.contains("transfer-encoding: chunked\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn bodiless_statuses_have_no_framing() {
+        async fn status(request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+            let status = StatusCode::from_bytes(&request.uri().path().as_bytes()[1..])?;
+            let mut builder = Response::builder().status(status);
+            if status == StatusCode::NOT_MODIFIED {
+                builder = builder.header(http::header::CONTENT_LENGTH, "7");
+            }
+            Ok(builder.body("ignored".to_string())?)
+        }
+
+        let handler = make_handler(status);
+        let (result, response) = exchange(&handler, "GET /204 HTTP/1.1\r\n\r\nGET /304 HTTP/1.1\r\n\r\n", true).await;
+
+        result.unwrap();
+        let (no_content, not_modified) = response.split_at(response.rfind("HTTP/1.1").unwrap());
+        assert!(no_content.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(!no_content.contains("content-length"));
+        assert!(!no_content.contains("transfer-encoding"));
+        assert!(no_content.ends_with("\r\n\r\n"));
+        assert!(not_modified.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+        assert!(not_modified.contains("content-length: 7\r\n"));
+        assert!(not_modified.ends_with("\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_idle_connection() {
+        let handler = make_handler(hello);
+        let (mut client, server) = duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(server);
+
+        let result = HttpConnection::new(reader, writer).process_with_shutdown(&handler, std::future::ready(())).await;
+
+        result.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.is_empty());
+    }
+
+    #[tokio::test]
+    async fn shutdown_answers_request_in_flight_with_close() {
+        let handler = make_handler(hello);
+        let (mut client, server) = duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(server);
+
+        client.write_all(b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let result = HttpConnection::new(reader, writer).process_with_shutdown(&handler, std::future::ready(())).await;
+
+        result.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+        assert_eq!(response.matches("HTTP/1.1").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn handler_connection_close_ends_connection() {
+        let handler = make_handler(hello_and_close);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n", false).await;
+
+        result.unwrap();
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 1);
+        assert!(response.contains("connection: close\r\n"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn silent_connection_is_closed_after_header_read_timeout() {
+        let handler = make_handler(hello);
+        let (result, response) = exchange(&handler, "", false).await;
+
+        result.unwrap();
+        assert!(response.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn incomplete_header_gets_request_timeout() {
+        let handler = make_handler(hello);
+        let (result, response) = exchange(&handler, "GET / HTTP/1.1\r\nHost: localhost\r\n", false).await;
+
+        assert!(result.unwrap_err().is_timeout());
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_keep_alive_connection_is_closed_silently() {
+        let handler = make_handler(hello);
+        let config = ConnectionConfig::default().with_header_read_timeout(None);
+        let (result, response) = exchange_with_config(&handler, "GET / HTTP/1.1\r\n\r\n", false, config).await;
+
+        result.unwrap();
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 1);
+        assert!(!response.contains("connection: close"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalled_body_gets_request_timeout() {
+        let handler = make_handler(echo);
+        let (result, response) = exchange(&handler, "POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nhello", false).await;
+
+        assert!(result.unwrap_err().is_timeout());
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+    }
+
+    #[tokio::test]
+    async fn large_body_is_streamed() {
+        let sent = Arc::new(Notify::new());
+        let handler = make_handler(|_request: http::Request<ReqBody>| {
+            let sent = Arc::clone(&sent);
+            async move {
+                // the last frame is only produced once the client has received the first one
+                let first = futures::stream::once(async { Ok::<_, Infallible>(Frame::data(Bytes::from(vec![b'x'; 128 * 1024]))) });
+                let last = futures::stream::once(async move {
+                    sent.notified().await;
+                    Ok(Frame::data(Bytes::from_static(b"end")))
+                });
+                Ok::<_, Infallible>(Response::new(StreamBody::new(Box::pin(first.chain(last)))))
+            }
+        });
+
+        let (mut client, server) = duplex(16 * 1024);
+        let (reader, writer) = tokio::io::split(server);
+        client.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+
+        let read = async {
+            let mut response = Vec::new();
+            let mut buf = vec![0; 8 * 1024];
+            loop {
+                let n = client.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    return response;
+                }
+                response.extend_from_slice(&buf[..n]);
+                if response.len() >= 128 * 1024 {
+                    sent.notify_one();
+                }
+            }
+        };
+
+        let process = Box::pin(HttpConnection::new(reader, writer).process(&handler));
+        let (result, response) = tokio::time::timeout(Duration::from_secs(5), async { tokio::join!(process, read) }).await.unwrap();
+
+        result.unwrap();
+        assert!(response.ends_with(b"end\r\n0\r\n\r\n"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalled_write_closes_connection() {
+        let handler = make_handler(large);
+        let (mut client_writer, reader) = duplex(1024);
+        // the client never reads, so the response can't be flushed past the first few bytes
+        let (_client_reader, writer) = duplex(16);
+
+        client_writer.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+
+        let e = HttpConnection::new(reader, writer).process(&handler).await.unwrap_err();
+        assert!(e.is_timeout());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_reader_outlasts_write_timeout() {
+        let handler = make_handler(large);
+        let (mut client_writer, reader) = duplex(1024);
+        let (mut client_reader, writer) = duplex(1024);
+        let config = ConnectionConfig::default().with_write_timeout(Some(Duration::from_secs(1)));
+
+        client_writer.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        client_writer.shutdown().await.unwrap();
+
+        // the client keeps reading, but takes far longer than the write timeout for the whole response
+        let read = async {
+            let mut response = Vec::new();
+            let mut chunk = vec![0; 1024];
+            loop {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                match client_reader.read(&mut chunk).await.unwrap() {
+                    0 => break response,
+                    n => response.extend_from_slice(&chunk[..n]),
+                }
+            }
+        };
+
+        let started = tokio::time::Instant::now();
+        let process = Box::pin(HttpConnection::with_config(reader, writer, config).process(&handler));
+        let (result, response) = tokio::join!(process, read);
+
+        result.unwrap();
+        assert!(started.elapsed() > Duration::from_secs(10));
+        assert!(response.ends_with(&[b'x'; 1024]));
+        let body_start = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+        assert_eq!(response.len() - body_start, 64 * 1024);
+    }
+
+    #[tokio::test]
+    async fn too_many_headers_gets_431() {
+        let handler = make_handler(hello);
+        let headers = "x-header: v\r\n".repeat(65);
+        let (result, response) = exchange(&handler, &format!("GET / HTTP/1.1\r\n{headers}\r\n"), false).await;
+
+        assert!(matches!(result, Err(HttpError::RequestError { source: ParseError::TooManyHeaders { .. } })));
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+    }
+
+    #[tokio::test]
+    async fn too_long_uri_gets_414() {
+        let handler = make_handler(hello);
+        let config = ConnectionConfig::default().with_header_limits(HeaderLimits::default().with_max_uri_len(8));
+        let (result, response) = exchange_with_config(&handler, "GET /too/long/path HTTP/1.1\r\n\r\n", false, config).await;
+
+        assert!(matches!(result, Err(HttpError::RequestError { source: ParseError::UriTooLong { .. } })));
+        assert!(response.starts_with("HTTP/1.1 414 URI Too Long\r\n"));
+    }
+
+    #[tokio::test]
+    async fn malformed_request_gets_400() {
+        let handler = make_handler(hello);
+        let (result, response) =
+            exchange(&handler, "POST / HTTP/1.1\r\nContent-Length: 1\r\nTransfer-Encoding: chunked\r\n\r\n", false).await;
+
+        assert!(matches!(result, Err(HttpError::RequestError { source: ParseError::InvalidContentLength { .. } })));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+    }
+
+    #[tokio::test]
+    async fn malformed_body_gets_400() {
+        let handler = make_handler(echo);
+        let (result, response) = exchange(&handler, "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n", false).await;
+
+        assert!(matches!(result, Err(HttpError::RequestError { .. })));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert_eq!(response.matches("HTTP/1.1").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn chunked_trailers_reach_handler() {
+        async fn checksum(request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+            let collected = request.into_body().collect().await?;
+            let checksum = collected.trailers().and_then(|trailers| trailers.get("x-checksum")).cloned();
+            let body = String::from_utf8(collected.to_bytes().to_vec())?;
+            Ok(Response::new(format!("{body} {checksum:?}")))
+        }
+
+        let handler = make_handler(checksum);
+        let request = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\nX-Checksum: 5d41\r\n\r\n";
+        let (result, response) = exchange(&handler, request, true).await;
+
+        result.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("hello Some(\"5d41\")"));
+    }
+
+    #[tokio::test]
+    async fn forbidden_trailer_gets_400() {
+        let handler = make_handler(echo);
+        let request = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\nHost: evil\r\n\r\n";
+        let (result, response) = exchange(&handler, request, false).await;
+
+        assert!(matches!(result, Err(HttpError::RequestError { .. })));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[tokio::test]
+    async fn error_status_can_be_overridden() {
+        fn error_status(error: &ParseError) -> Option<StatusCode> {
+            match error {
+                ParseError::TooManyHeaders { .. } => Some(StatusCode::BAD_REQUEST),
+                _ => None,
+            }
+        }
+
+        let handler = make_handler(hello);
+        let config =
+            ConnectionConfig::default().with_header_limits(HeaderLimits::default().with_max_header_num(1)).with_error_status(error_status);
+        let (_, response) = exchange_with_config(&handler, "GET / HTTP/1.1\r\na: 1\r\nb: 2\r\n\r\n", false, config.clone()).await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        let (_, response) = exchange_with_config(&handler, "POST / HTTP/1.1\r\nContent-Length: x\r\n\r\n", false, config).await;
+        assert!(response.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unreadable_body_is_rejected_then_shut_down() {
+        /// A writer recording whether the connection shut it down
+        #[derive(Debug)]
+        struct ShutdownRecorder {
+            inner: WriteHalf<DuplexStream>,
+            shut_down: Arc<AtomicBool>,
+        }
+
+        impl AsyncWrite for ShutdownRecorder {
+            fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+                Pin::new(&mut self.inner).poll_write(cx, buf)
+            }
+
+            fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+                Pin::new(&mut self.inner).poll_flush(cx)
+            }
+
+            fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+                self.shut_down.store(true, Ordering::Relaxed);
+                Pin::new(&mut self.inner).poll_shutdown(cx)
+            }
+        }
+
+        let handler = make_handler(hello);
+        let requests = [
+            ("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\nHost: evil\r\n\r\n", "HTTP/1.1 400 Bad Request\r\n"),
+            ("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n", "HTTP/1.1 413 Payload Too Large\r\n"),
+        ];
+        for (request, status_line) in requests {
+            let (mut client, server) = duplex(64 * 1024);
+            let (reader, writer) = tokio::io::split(server);
+            let shut_down = Arc::new(AtomicBool::new(false));
+            let writer = ShutdownRecorder { inner: writer, shut_down: Arc::clone(&shut_down) };
+            client.write_all(request.as_bytes()).await.unwrap();
+
+            let config = ConnectionConfig::default().with_max_body_size(Some(4));
+            let result = HttpConnection::with_config(reader, writer, config).process(&handler).await;
+            assert!(matches!(result, Err(HttpError::RequestError { .. })));
+            assert!(shut_down.load(Ordering::Relaxed), "{status_line}");
+
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with(status_line), "{response}");
+        }
+    }
+
+    #[tokio::test]
+    async fn oversized_body_gets_413() {
+        let handler = make_handler(echo);
+        let config = ConnectionConfig::default().with_max_body_size(Some(4));
+
+        let (result, response) =
+            exchange_with_config(&handler, "POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello", false, config.clone()).await;
+        assert!(matches!(result, Err(HttpError::RequestError { source: ParseError::PayloadTooLarge { .. } })));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+
+        let request = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nhel\r\n2\r\nlo\r\n0\r\n\r\n";
+        let (result, response) = exchange_with_config(&handler, request, false, config).await;
+        assert!(matches!(result, Err(HttpError::RequestError { source: ParseError::PayloadTooLarge { .. } })));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert_eq!(response.matches("HTTP/1.1").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn request_body_max_size_is_not_drained() {
+        async fn limited(mut request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+            request.body_mut().set_max_size(4)?;
+            Ok(Response::new("unreachable".to_string()))
+        }
+
+        let handler = make_handler(limited);
+        let (result, response) = exchange(&handler, "POST / HTTP/1.1\r\nContent-Length: 1000\r\n\r\nhello", false).await;
+
+        assert!(matches!(result, Err(HttpError::RequestError { source: ParseError::PayloadTooLarge { .. } })));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+    }
+
+    #[tokio::test]
+    async fn request_body_max_size_outlives_the_request() {
+        static KEPT: std::sync::Mutex<Option<ReqBody>> = std::sync::Mutex::new(None);
+
+        async fn keep(request: http::Request<ReqBody>) -> Result<Response<String>, Box<dyn Error + Send + Sync>> {
+            *KEPT.lock().unwrap() = Some(request.into_body());
+            Ok(Response::new("kept".to_string()))
+        }
+
+        let handler = make_handler(keep);
+        let (result, response) = exchange(&handler, "POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello", true).await;
+        result.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        // the connection is done with the request, the body only holds the limit now
+        let mut body = KEPT.lock().unwrap().take().unwrap();
+        assert!(matches!(body.set_max_size(4), Err(ParseError::PayloadTooLarge { current_size: 5, max_size: 4 })));
+        body.set_max_size(8).unwrap();
+    }
+}
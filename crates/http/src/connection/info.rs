@@ -0,0 +1,71 @@
+//! Connection metadata.
+//!
+//! [`ConnectionInfo`] describes the transport a connection was accepted on. When it is handed
+//! to [`HttpConnection::with_connection_info`](crate::connection::HttpConnection::with_connection_info),
+//! a copy is inserted into the extensions of every request read from that connection, where
+//! handlers can look it up with `request.extensions().get::<ConnectionInfo>()`.
+
+use std::net::SocketAddr;
+
+/// Metadata about the transport of a connection.
+///
+/// # Example
+///
+/// ```
+/// use std::net::SocketAddr;
+/// use micro_http::connection::ConnectionInfo;
+///
+/// let peer_addr: SocketAddr = "192.0.2.10:51234".parse().unwrap();
+/// let info = ConnectionInfo::default().with_peer_addr(peer_addr);
+///
+/// assert_eq!(info.peer_addr(), Some(peer_addr));
+/// assert_eq!(info.local_addr(), None);
+/// assert!(!info.is_tls());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    peer_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    tls: bool,
+}
+
+impl ConnectionInfo {
+    /// Sets the address of the remote end of the connection.
+    #[must_use]
+    pub fn with_peer_addr(mut self, peer_addr: SocketAddr) -> Self {
+        self.peer_addr = Some(peer_addr);
+        self
+    }
+
+    /// Sets the local address the connection was accepted on.
+    #[must_use]
+    pub fn with_local_addr(mut self, local_addr: SocketAddr) -> Self {
+        self.local_addr = Some(local_addr);
+        self
+    }
+
+    /// Sets whether the connection is secured with TLS, defaults to `false`.
+    #[must_use]
+    pub fn with_tls(mut self, tls: bool) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Returns the address of the remote end of the connection.
+    #[must_use]
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Returns the local address the connection was accepted on.
+    #[must_use]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns whether the connection is secured with TLS.
+    #[must_use]
+    pub fn is_tls(&self) -> bool {
+        self.tls
+    }
+}
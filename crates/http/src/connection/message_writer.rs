@@ -1,14 +1,20 @@
 use crate::codec::ResponseEncoder;
 use crate::protocol::{Message, PayloadSize, ResponseHead, SendError};
 use bytes::{Buf, BytesMut};
+use std::io;
+use std::time::Duration;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio_util::codec::Encoder;
 
+/// Buffered bytes of a response body beyond which they are written out before encoding more
+const FLUSH_THRESHOLD: usize = 64 * 1024;
+
 #[derive(Debug)]
 pub struct MessageWriter<W> {
     writer: W,
     buffer: BytesMut,
     encoder: ResponseEncoder,
+    write_timeout: Option<Duration>,
 }
 
 impl<W> MessageWriter<W>
@@ -16,7 +22,13 @@ where
     W: AsyncWrite + Unpin,
 {
     pub fn with_capacity(writer: W, buffer_size: usize) -> Self {
-        Self { writer, buffer: BytesMut::with_capacity(buffer_size), encoder: ResponseEncoder::new() }
+        Self { writer, buffer: BytesMut::with_capacity(buffer_size), encoder: ResponseEncoder::new(), write_timeout: None }
+    }
+
+    /// Limits how long a write may make no progress during a [`flush`](Self::flush).
+    pub fn with_write_timeout(mut self, write_timeout: Option<Duration>) -> Self {
+        self.write_timeout = write_timeout;
+        self
     }
 
     #[inline]
@@ -24,8 +36,17 @@ where
         &mut self.writer
     }
 
-    pub fn clear_buf(&mut self) {
-        self.buffer.clear();
+    /// Ends the current response right after its header, see [`ResponseEncoder::skip_payload`].
+    #[inline]
+    pub fn skip_payload(&mut self) {
+        self.encoder.skip_payload();
+    }
+
+    /// Returns whether enough bytes are buffered to [`flush`](Self::flush) them before encoding
+    /// more, so a large body is streamed instead of being held in memory.
+    #[inline]
+    pub fn should_flush(&self) -> bool {
+        self.buffer.len() >= FLUSH_THRESHOLD
     }
 
     #[inline]
@@ -36,13 +57,40 @@ where
         self.encoder.encode(item, &mut self.buffer)
     }
 
+    /// Writes the buffered bytes to the underlying writer and empties the buffer.
+    ///
+    /// Fails with a [`SendError::Timeout`] error if a write makes no progress for the
+    /// write timeout. The deadline restarts after every successful write, so a slow client that
+    /// keeps reading isn't cut off.
     #[inline]
     pub async fn flush(&mut self) -> Result<(), SendError> {
         if self.buffer.is_empty() {
             return Ok(());
         }
 
-        self.writer.write_all(self.buffer.as_ref()).await?;
-        Ok(self.writer.flush().await?)
+        while !self.buffer.is_empty() {
+            let written = with_timeout(self.write_timeout, self.writer.write_buf(&mut self.buffer)).await?;
+            if written == 0 {
+                return Err(SendError::io(io::ErrorKind::WriteZero));
+            }
+        }
+        with_timeout(self.write_timeout, self.writer.flush()).await
+    }
+
+    /// Shuts down the underlying writer, signalling the peer that no more data follows.
+    #[inline]
+    pub async fn shutdown(&mut self) -> Result<(), SendError> {
+        Ok(self.writer.shutdown().await?)
+    }
+}
+
+/// Runs a single write operation, failing with [`SendError::Timeout`] if it doesn't complete in time
+async fn with_timeout<T>(timeout: Option<Duration>, write: impl Future<Output = io::Result<T>>) -> Result<T, SendError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, write)
+            .await
+            .map_err(|_elapsed| SendError::timeout("response write timed out"))?
+            .map_err(SendError::io),
+        None => write.await.map_err(SendError::io),
     }
 }
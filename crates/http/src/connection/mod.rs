@@ -12,6 +12,8 @@
 //!   - Handles response streaming
 //!   - Supports keep-alive connections
 //!   - Implements expect-continue handling
+//! - [`ConnectionConfig`]: Timeouts applied to a connection
+//! - [`ConnectionInfo`]: Peer and local addresses exposed to every request
 //!
 //! # Features
 //!
@@ -20,9 +22,14 @@
 //! - Keep-alive connection support
 //! - Error handling and recovery
 //! - Expect-continue mechanism
+//! - Header, body, idle and write timeouts
 //! - Efficient memory usage through buffering
 
+mod config;
 mod http_connection;
+mod info;
 mod message_writer;
 
+pub use config::ConnectionConfig;
 pub use http_connection::HttpConnection;
+pub use info::ConnectionInfo;
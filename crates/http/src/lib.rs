@@ -12,6 +12,7 @@
 //! - Chunked transfer encoding
 //! - Keep-alive connections
 //! - Expect-continue mechanism
+//! - Configurable header, body, idle and write timeouts
 //! - Efficient memory usage through zero-copy parsing
 //! - Clean error handling
 //!
@@ -169,4 +170,7 @@ pub mod handler;
 pub mod protocol;
 
 mod utils;
+pub(crate) use utils::connection_has_token;
 pub(crate) use utils::ensure;
+pub(crate) use utils::is_bodiless_status;
+pub(crate) use utils::is_forbidden_trailer;
@@ -1,13 +1,18 @@
 use crate::codec::RequestDecoder;
 use crate::protocol::{Message, ParseError, PayloadItem, PayloadSize};
 use bytes::Bytes;
-use futures::{Stream, StreamExt, ready};
+use futures::{Stream, ready};
 use http_body::{Body, Frame, SizeHint};
 use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::future::poll_fn;
 use std::pin::Pin;
 use std::ptr::NonNull;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::AsyncRead;
+use tokio::time::{Instant, Sleep, sleep_until};
 use tokio_util::codec::FramedRead;
 
 /// The request body provided to handlers.
@@ -17,6 +22,11 @@ use tokio_util::codec::FramedRead;
 /// the previous design. The streaming state is owned by a [`ReqBodyState`],
 /// while `ReqBody` simply provides the consumer view that is attached to the
 /// HTTP request object.
+///
+/// A chunked body that ends with trailer fields yields them as a final [`Frame::trailers`].
+///
+/// When a body read timeout is configured, a read that waits longer than the timeout
+/// for the next chunk fails with [`ParseError::Timeout`].
 #[derive(Debug)]
 pub struct ReqBody {
     inner: ReqBodyRepr,
@@ -33,7 +43,8 @@ enum ReqBodyRepr {
 /// Once the handler has finished processing the request, the connection calls
 /// [`ReqBodyState::finish`] to ensure the body is fully drained and to regain
 /// ownership of the [`FramedRead`] so the next request on the connection can be
-/// parsed.
+/// parsed. If reading the body failed, `finish` fails as well since the connection
+/// can't be reused.
 #[allow(private_interfaces)]
 pub(crate) enum ReqBodyState<R> {
     Streaming(StreamingStateHandle<R>),
@@ -44,11 +55,15 @@ impl<R> ReqBodyState<R>
 where
     R: AsyncRead + Unpin + Send + Debug,
 {
-    pub(crate) fn new(framed_read: FramedRead<R, RequestDecoder>, payload_size: PayloadSize) -> (ReqBody, ReqBodyState<R>) {
+    pub(crate) fn new(
+        framed_read: FramedRead<R, RequestDecoder>,
+        payload_size: PayloadSize,
+        read_timeout: Option<Duration>,
+    ) -> (ReqBody, ReqBodyState<R>) {
         match payload_size {
             PayloadSize::Empty | PayloadSize::Length(0) => (ReqBody::no_body(), ReqBodyState::Empty(Some(framed_read))),
             _ => {
-                let (body, handle) = StreamingStateHandle::new(framed_read, payload_size);
+                let (body, handle) = StreamingStateHandle::new(framed_read, payload_size, read_timeout);
                 (body, ReqBodyState::Streaming(handle))
             }
         }
@@ -66,11 +81,12 @@ impl ReqBody {
     pub(crate) fn create_req_body<R>(
         framed_read: FramedRead<R, RequestDecoder>,
         payload_size: PayloadSize,
+        read_timeout: Option<Duration>,
     ) -> (ReqBody, ReqBodyState<R>)
     where
         R: AsyncRead + Unpin + Send + Debug,
     {
-        ReqBodyState::new(framed_read, payload_size)
+        ReqBodyState::new(framed_read, payload_size, read_timeout)
     }
 
     fn no_body() -> Self {
@@ -80,6 +96,23 @@ impl ReqBody {
     fn streaming(streaming: StreamingReqBody) -> Self {
         Self { inner: ReqBodyRepr::Streaming(streaming) }
     }
+
+    /// Limits this body to `max_size` bytes.
+    ///
+    /// Reading the body fails with [`ParseError::PayloadTooLarge`] once more than `max_size`
+    /// bytes arrived. The body isn't drained after either failure, the connection answers
+    /// with `413 Content Too Large` and closes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParseError::PayloadTooLarge`] right away if the declared `Content-Length`
+    /// already exceeds the limit.
+    pub fn set_max_size(&mut self, max_size: u64) -> Result<(), ParseError> {
+        match &self.inner {
+            ReqBodyRepr::Streaming(streaming) => streaming.set_max_size(max_size),
+            ReqBodyRepr::NoBody => Ok(()),
+        }
+    }
 }
 
 impl Body for ReqBody {
@@ -109,19 +142,23 @@ impl Body for ReqBody {
     }
 }
 
-/// Pointer-sized handle shared between the request body object and the owning
+/// Handle shared between the request body object and the owning
 /// [`StreamingStateHandle`]. The handle stores function pointers that know how
 /// to operate on the concrete streaming state without exposing the generic
 /// [`FramedRead`] type in the public [`ReqBody`] API.
-#[derive(Clone, Copy)]
+///
+/// The size limit isn't set through `raw`: it's shared with the state, so setting it doesn't
+/// depend on the state still being alive.
 struct StreamingReqBody {
     raw: NonNull<()>,
     vtable: &'static ReqBodyVTable,
+    max_size: Arc<MaxSize>,
+    declared_size: Option<u64>,
 }
 
 impl Debug for StreamingReqBody {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        f.debug_struct("StreamingReqBody").field("raw", &self.raw).finish()
+        f.debug_struct("StreamingReqBody").field("raw", &self.raw).field("max_size", &self.max_size.get()).finish_non_exhaustive()
     }
 }
 
@@ -145,6 +182,32 @@ impl StreamingReqBody {
     unsafe fn size_hint(&self) -> SizeHint {
         unsafe { (self.vtable.size_hint)(self.raw) }
     }
+
+    fn set_max_size(&self, max_size: u64) -> Result<(), ParseError> {
+        self.max_size.set(max_size);
+        match self.declared_size {
+            Some(declared_size) if declared_size > max_size => Err(ParseError::payload_too_large(declared_size, max_size)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The size limit of a streaming body, `u64::MAX` when it's unlimited
+#[derive(Debug)]
+struct MaxSize(AtomicU64);
+
+impl MaxSize {
+    fn unlimited() -> Self {
+        Self(AtomicU64::new(u64::MAX))
+    }
+
+    fn get(&self) -> Option<u64> {
+        Some(self.0.load(Ordering::Relaxed)).filter(|&max_size| max_size != u64::MAX)
+    }
+
+    fn set(&self, max_size: u64) {
+        self.0.store(max_size, Ordering::Relaxed);
+    }
 }
 
 struct ReqBodyVTable {
@@ -161,10 +224,19 @@ impl<R> StreamingStateHandle<R>
 where
     R: AsyncRead + Unpin + Send + Debug,
 {
-    fn new(framed_read: FramedRead<R, RequestDecoder>, payload_size: PayloadSize) -> (ReqBody, StreamingStateHandle<R>) {
-        let mut state = Box::new(StreamingState::new(framed_read, payload_size));
+    fn new(
+        framed_read: FramedRead<R, RequestDecoder>,
+        payload_size: PayloadSize,
+        read_timeout: Option<Duration>,
+    ) -> (ReqBody, StreamingStateHandle<R>) {
+        let max_size = Arc::new(MaxSize::unlimited());
+        let mut state = Box::new(StreamingState::new(framed_read, payload_size, read_timeout, Arc::clone(&max_size)));
         let raw = NonNull::from(state.as_mut()).cast::<()>();
-        let streaming = StreamingReqBody { raw, vtable: StreamingState::<R>::vtable() };
+        let declared_size = match payload_size {
+            PayloadSize::Length(length) => Some(length),
+            _ => None,
+        };
+        let streaming = StreamingReqBody { raw, vtable: StreamingState::<R>::vtable(), max_size, declared_size };
         (ReqBody::streaming(streaming), StreamingStateHandle { state: Some(state) })
     }
 
@@ -178,18 +250,45 @@ struct StreamingState<R> {
     reader: Option<FramedRead<R, RequestDecoder>>,
     payload_size: PayloadSize,
     reached_eof: bool,
+    /// Set when reading the body ended with an error
+    failure: Option<BodyFailure>,
+    received_size: u64,
+    max_size: Arc<MaxSize>,
+    read_timeout: Option<Duration>,
+    /// Armed when a read starts waiting and disarmed whenever the reader makes progress
+    deadline: Option<Pin<Box<Sleep>>>,
+    deadline_armed: bool,
 }
 
 impl<R> StreamingState<R>
 where
     R: AsyncRead + Unpin + Send + Debug,
 {
-    fn new(reader: FramedRead<R, RequestDecoder>, payload_size: PayloadSize) -> Self {
-        Self { reader: Some(reader), payload_size, reached_eof: false }
+    fn new(
+        reader: FramedRead<R, RequestDecoder>,
+        payload_size: PayloadSize,
+        read_timeout: Option<Duration>,
+        max_size: Arc<MaxSize>,
+    ) -> Self {
+        Self {
+            reader: Some(reader),
+            payload_size,
+            reached_eof: false,
+            failure: None,
+            received_size: 0,
+            max_size,
+            read_timeout,
+            deadline: None,
+            deadline_armed: false,
+        }
     }
 
     fn vtable() -> &'static ReqBodyVTable {
-        &ReqBodyVTable { poll_frame: Self::poll_frame_dyn, is_end_stream: Self::is_end_stream_dyn, size_hint: Self::size_hint_dyn }
+        &ReqBodyVTable {
+            poll_frame: Self::poll_frame_dyn,
+            is_end_stream: Self::is_end_stream_dyn,
+            size_hint: Self::size_hint_dyn,
+        }
     }
 
     fn reader_pin(&mut self) -> Pin<&mut FramedRead<R, RequestDecoder>> {
@@ -201,27 +300,77 @@ where
             return Poll::Ready(None);
         }
 
-        match ready!(self.reader_pin().poll_next(cx)) {
-            Some(Ok(Message::Payload(PayloadItem::Chunk(bytes)))) => Poll::Ready(Some(Ok(Frame::data(bytes)))),
-            Some(Ok(Message::Payload(PayloadItem::Eof))) => {
-                self.reached_eof = true;
-                Poll::Ready(None)
+        // the limit may have been lowered below what was declared or already received
+        if let Some(max_size) = self.max_size.get() {
+            let size = match self.payload_size {
+                PayloadSize::Length(length) => length,
+                _ => self.received_size,
+            };
+            if size > max_size {
+                return self.fail(ParseError::payload_too_large(size, max_size));
             }
-            Some(Ok(Message::Header(_))) => {
-                self.reached_eof = true;
-                Poll::Ready(Some(Err(ParseError::invalid_body("unexpected header while streaming request body"))))
+        }
+
+        let message = match self.reader_pin().poll_next(cx) {
+            Poll::Ready(message) => {
+                self.deadline_armed = false;
+                message
             }
-            Some(Err(e)) => {
-                self.reached_eof = true;
-                Poll::Ready(Some(Err(e)))
+            Poll::Pending => return self.poll_read_timeout(cx),
+        };
+
+        match message {
+            Some(Ok(Message::Payload(PayloadItem::Chunk(bytes)))) => {
+                self.received_size += bytes.len() as u64;
+                match self.max_size.get() {
+                    Some(max_size) if self.received_size > max_size => {
+                        self.fail(ParseError::payload_too_large(self.received_size, max_size))
+                    }
+                    _ => Poll::Ready(Some(Ok(Frame::data(bytes)))),
+                }
             }
-            None => {
+            Some(Ok(Message::Payload(PayloadItem::Trailers(trailers)))) => Poll::Ready(Some(Ok(Frame::trailers(trailers)))),
+            Some(Ok(Message::Payload(PayloadItem::Eof))) => {
                 self.reached_eof = true;
-                Poll::Ready(Some(Err(ParseError::invalid_body("unexpected EOF while streaming request body"))))
+                Poll::Ready(None)
             }
+            Some(Ok(Message::Header(_))) => self.fail(ParseError::invalid_body("unexpected header while streaming request body")),
+            Some(Err(e)) => self.fail(e),
+            None => self.fail(ParseError::invalid_body("unexpected EOF while streaming request body")),
         }
     }
 
+    /// Called when the reader is waiting for data, fails the body once the read timeout elapses.
+    fn poll_read_timeout(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, ParseError>>> {
+        let Some(read_timeout) = self.read_timeout else {
+            return Poll::Pending;
+        };
+
+        if !self.deadline_armed {
+            let deadline = Instant::now() + read_timeout;
+            match self.deadline.as_mut() {
+                Some(sleep) => sleep.as_mut().reset(deadline),
+                None => self.deadline = Some(Box::pin(sleep_until(deadline))),
+            }
+            self.deadline_armed = true;
+        }
+
+        let sleep = self.deadline.as_mut().expect("read deadline must be armed");
+        ready!(sleep.as_mut().poll(cx));
+
+        self.fail(ParseError::timeout("request body read timed out"))
+    }
+
+    fn fail(&mut self, e: ParseError) -> Poll<Option<Result<Frame<Bytes>, ParseError>>> {
+        self.reached_eof = true;
+        self.failure = Some(match e {
+            ParseError::Timeout { .. } => BodyFailure::Timeout,
+            ParseError::PayloadTooLarge { current_size, max_size } => BodyFailure::TooLarge { current_size, max_size },
+            _ => BodyFailure::Invalid,
+        });
+        Poll::Ready(Some(Err(e)))
+    }
+
     fn is_end_stream(&self) -> bool {
         self.reached_eof
     }
@@ -231,31 +380,17 @@ where
     }
 
     async fn finish(mut self) -> Result<FramedRead<R, RequestDecoder>, ParseError> {
-        if self.reached_eof {
-            return Ok(self.reader.take().expect("streaming state reader missing"));
+        // drain whatever the handler left unread, the read timeout still applies
+        while let Some(frame) = poll_fn(|cx| self.poll_frame(cx)).await {
+            frame?;
         }
 
-        let reader = self.reader.as_mut().expect("streaming state reader missing");
-
-        while let Some(message) = reader.next().await {
-            match message? {
-                Message::Payload(PayloadItem::Chunk(_)) => continue,
-                Message::Payload(PayloadItem::Eof) => {
-                    self.reached_eof = true;
-                    break;
-                }
-                Message::Header(_) => {
-                    self.reached_eof = true;
-                    return Err(ParseError::invalid_body("unexpected header while draining request body"));
-                }
-            }
-        }
-
-        if !self.reached_eof {
-            return Err(ParseError::invalid_body("connection closed before body EOF"));
+        match self.failure {
+            Some(BodyFailure::Timeout) => Err(ParseError::timeout("request body read timed out")),
+            Some(BodyFailure::TooLarge { current_size, max_size }) => Err(ParseError::payload_too_large(current_size, max_size)),
+            Some(BodyFailure::Invalid) => Err(ParseError::invalid_body("request body was not fully read")),
+            None => Ok(self.reader.take().expect("streaming state reader missing")),
         }
-
-        Ok(self.reader.take().expect("streaming state reader missing"))
     }
 
     unsafe fn poll_frame_dyn(raw: NonNull<()>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, ParseError>>> {
@@ -277,6 +412,14 @@ where
     }
 }
 
+/// Why streaming the request body stopped early.
+#[derive(Debug, Clone, Copy)]
+enum BodyFailure {
+    Timeout,
+    TooLarge { current_size: u64, max_size: u64 },
+    Invalid,
+}
+
 impl From<PayloadSize> for SizeHint {
     fn from(payload_size: PayloadSize) -> Self {
         match payload_size {
@@ -12,6 +12,7 @@
 //! The error types form a hierarchy where `HttpError` is the top-level error that can
 //! contain either a `ParseError` or `SendError`. This allows for granular error handling
 //! while still providing a unified error type at the API boundary.
+use http::StatusCode;
 use std::io;
 use thiserror::Error;
 
@@ -36,6 +37,18 @@ pub enum HttpError {
     },
 }
 
+impl HttpError {
+    /// Returns whether the error is a timeout: the client didn't deliver the request in time, or
+    /// didn't accept the response in time.
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            Self::RequestError { source: ParseError::Timeout { .. } } | Self::ResponseError { source: SendError::Timeout { .. } }
+        )
+    }
+}
+
 /// Errors that occur during HTTP request parsing
 ///
 /// This enum represents various error conditions that can occur while parsing
@@ -66,6 +79,10 @@ pub enum ParseError {
     #[error("invalid http uri")]
     InvalidUri,
 
+    /// URI length exceeds the maximum allowed length
+    #[error("uri too long, current: {current_len} exceed the limit {max_len}")]
+    UriTooLong { current_len: usize, max_len: usize },
+
     /// Invalid Content-Length header
     #[error("invalid content-length header: {reason}")]
     InvalidContentLength { reason: String },
@@ -74,6 +91,14 @@ pub enum ParseError {
     #[error("invalid body: {reason}")]
     InvalidBody { reason: String },
 
+    /// Request body size exceeds the maximum allowed size
+    #[error("payload too large, current: {current_size} exceed the limit {max_size}")]
+    PayloadTooLarge { current_size: u64, max_size: u64 },
+
+    /// The client didn't deliver the request in time
+    #[error("timeout: {reason}")]
+    Timeout { reason: String },
+
     /// I/O error during parsing
     #[error("io error: {source}")]
     Io {
@@ -93,6 +118,11 @@ impl ParseError {
         Self::TooManyHeaders { max_num }
     }
 
+    /// Creates a new UriTooLong error
+    pub fn uri_too_long(current_len: usize, max_len: usize) -> Self {
+        Self::UriTooLong { current_len, max_len }
+    }
+
     /// Creates a new InvalidHeader error
     pub fn invalid_header<S: ToString>(str: S) -> Self {
         Self::InvalidHeader { reason: str.to_string() }
@@ -103,15 +133,51 @@ impl ParseError {
         Self::InvalidBody { reason: str.to_string() }
     }
 
+    /// Creates a new `PayloadTooLarge` error
+    #[must_use]
+    pub fn payload_too_large(current_size: u64, max_size: u64) -> Self {
+        Self::PayloadTooLarge { current_size, max_size }
+    }
+
     /// Creates a new InvalidContentLength error
     pub fn invalid_content_length<S: ToString>(str: S) -> Self {
         Self::InvalidContentLength { reason: str.to_string() }
     }
 
+    /// Creates a new Timeout error
+    pub fn timeout<S: ToString>(str: S) -> Self {
+        Self::Timeout { reason: str.to_string() }
+    }
+
     /// Creates a new I/O error
     pub fn io<E: Into<io::Error>>(e: E) -> Self {
         Self::Io { source: e.into() }
     }
+
+    /// Returns the status code a server should answer this error with.
+    ///
+    /// - `431 Request Header Fields Too Large` for header size and header count limits
+    /// - `414 URI Too Long` for the URI length limit
+    /// - `413 Content Too Large` for the body size limit
+    /// - `408 Request Timeout` for timeouts
+    /// - `400 Bad Request` for malformed requests
+    /// - `None` for I/O errors, since the client can't be answered anymore
+    #[must_use]
+    pub fn status_code(&self) -> Option<StatusCode> {
+        match self {
+            Self::TooLargeHeader { .. } | Self::TooManyHeaders { .. } => Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE),
+            Self::UriTooLong { .. } => Some(StatusCode::URI_TOO_LONG),
+            Self::PayloadTooLarge { .. } => Some(StatusCode::PAYLOAD_TOO_LARGE),
+            Self::Timeout { .. } => Some(StatusCode::REQUEST_TIMEOUT),
+            Self::InvalidHeader { .. }
+            | Self::InvalidVersion(_)
+            | Self::InvalidMethod
+            | Self::InvalidUri
+            | Self::InvalidContentLength { .. }
+            | Self::InvalidBody { .. } => Some(StatusCode::BAD_REQUEST),
+            Self::Io { .. } => None,
+        }
+    }
 }
 
 /// Errors that occur during HTTP response generation and sending
@@ -124,6 +190,10 @@ pub enum SendError {
     #[error("invalid body: {reason}")]
     InvalidBody { reason: String },
 
+    /// The client didn't accept the response in time
+    #[error("timeout: {reason}")]
+    Timeout { reason: String },
+
     /// I/O error during sending
     #[error("io error: {source}")]
     Io {
@@ -138,6 +208,11 @@ impl SendError {
         Self::InvalidBody { reason: str.to_string() }
     }
 
+    /// Creates a new Timeout error
+    pub fn timeout<S: ToString>(str: S) -> Self {
+        Self::Timeout { reason: str.to_string() }
+    }
+
     /// Creates a new I/O error
     pub fn io<E: Into<io::Error>>(e: E) -> Self {
         Self::Io { source: e.into() }
@@ -1,4 +1,5 @@
 use bytes::{Buf, Bytes};
+use http::HeaderMap;
 use http_body::SizeHint;
 
 /// Represents a HTTP message that can either be a header or payload.
@@ -16,12 +17,14 @@ pub enum Message<T, Data: Buf = Bytes> {
 
 /// Represents an item in the HTTP message payload stream.
 ///
-/// This enum is used by the payload decoder to produce either data chunks
-/// or signal the end of the payload stream (EOF).
+/// This enum is used by the payload decoder to produce either data chunks, the trailer
+/// fields of a chunked payload, or signal the end of the payload stream (EOF).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PayloadItem<Data: Buf = Bytes> {
     /// A chunk of payload data
     Chunk(Data),
+    /// Trailer fields sent after the last chunk of a chunked payload, followed by `Eof`
+    Trailers(HeaderMap),
     /// Marks the end of the payload stream
     Eof,
 }
@@ -132,36 +135,50 @@ impl<D: Buf> PayloadItem<D> {
     pub fn is_chunk(&self) -> bool {
         matches!(self, PayloadItem::Chunk(_))
     }
+
+    /// Returns true if this item contains trailer fields
+    #[inline]
+    pub fn is_trailers(&self) -> bool {
+        matches!(self, PayloadItem::Trailers(_))
+    }
+
+    /// Consumes the `PayloadItem` and returns the contained trailer fields if this is `Trailers`
+    pub fn into_trailers(self) -> Option<HeaderMap> {
+        match self {
+            PayloadItem::Trailers(trailers) => Some(trailers),
+            PayloadItem::Chunk(_) | PayloadItem::Eof => None,
+        }
+    }
 }
 
 impl PayloadItem {
     /// Returns a reference to the contained bytes if this is a Chunk
     ///
-    /// Returns None if this is an EOF marker
+    /// Returns None if this is trailer fields or an EOF marker
     pub fn as_bytes(&self) -> Option<&Bytes> {
         match self {
             PayloadItem::Chunk(bytes) => Some(bytes),
-            PayloadItem::Eof => None,
+            PayloadItem::Trailers(_) | PayloadItem::Eof => None,
         }
     }
 
     /// Returns a mutable reference to the contained bytes if this is a Chunk
     ///
-    /// Returns None if this is an EOF marker
+    /// Returns None if this is trailer fields or an EOF marker
     pub fn as_mut_bytes(&mut self) -> Option<&mut Bytes> {
         match self {
             PayloadItem::Chunk(bytes) => Some(bytes),
-            PayloadItem::Eof => None,
+            PayloadItem::Trailers(_) | PayloadItem::Eof => None,
         }
     }
 
     /// Consumes the PayloadItem and returns the contained bytes if this is a Chunk
     ///
-    /// Returns None if this is an EOF marker
+    /// Returns None if this is trailer fields or an EOF marker
     pub fn into_bytes(self) -> Option<Bytes> {
         match self {
             PayloadItem::Chunk(bytes) => Some(bytes),
-            PayloadItem::Eof => None,
+            PayloadItem::Trailers(_) | PayloadItem::Eof => None,
         }
     }
 }
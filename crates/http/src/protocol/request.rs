@@ -4,6 +4,7 @@
 //! It wraps the standard `http::Request` type to provide additional functionality
 //! specific to our HTTP server implementation.
 
+use crate::connection_has_token;
 use http::request::Parts;
 use http::{HeaderMap, Method, Request, Uri, Version};
 
@@ -78,6 +79,19 @@ impl RequestHeader {
             &Method::GET | &Method::HEAD | &Method::DELETE | &Method::OPTIONS | &Method::CONNECT | &Method::TRACE | &Method::PATCH
         )
     }
+
+    /// Determines if the client wants the connection kept open after this request.
+    ///
+    /// HTTP/1.1 connections are persistent unless the request carries `Connection: close`,
+    /// while HTTP/1.0 connections close after the response unless the request carries
+    /// `Connection: keep-alive`.
+    pub fn is_keep_alive(&self) -> bool {
+        match self.version() {
+            Version::HTTP_11 => !connection_has_token(self.headers(), "close"),
+            Version::HTTP_10 => connection_has_token(self.headers(), "keep-alive"),
+            _ => false,
+        }
+    }
 }
 
 /// Converts request parts into a RequestHeader.
@@ -246,4 +260,24 @@ mod tests {
             Some(&HeaderValue::from_str("zh-CN,zh;q=0.9,en-US;q=0.8,en;q=0.7").unwrap())
         );
     }
+
+    #[test]
+    fn keep_alive_by_version_and_connection_header() {
+        fn header(version: Version, connection: Option<&str>) -> RequestHeader {
+            let mut builder = Request::builder().version(version);
+            if let Some(connection) = connection {
+                builder = builder.header(http::header::CONNECTION, connection);
+            }
+            builder.body(()).unwrap().into()
+        }
+
+        assert!(header(Version::HTTP_11, None).is_keep_alive());
+        assert!(header(Version::HTTP_11, Some("keep-alive")).is_keep_alive());
+        assert!(!header(Version::HTTP_11, Some("close")).is_keep_alive());
+        assert!(!header(Version::HTTP_11, Some("Upgrade, Close")).is_keep_alive());
+
+        assert!(!header(Version::HTTP_10, None).is_keep_alive());
+        assert!(header(Version::HTTP_10, Some("Keep-Alive")).is_keep_alive());
+        assert!(!header(Version::HTTP_10, Some("close")).is_keep_alive());
+    }
 }
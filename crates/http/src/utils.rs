@@ -24,3 +24,70 @@ macro_rules! ensure {
 }
 
 pub(crate) use ensure;
+
+/// Returns true if any `Connection` header value lists `token` (case-insensitively).
+///
+/// The `Connection` header is a comma separated list of options and may appear
+/// multiple times, so every value and every list member is inspected.
+pub(crate) fn connection_has_token(headers: &http::HeaderMap, token: &str) -> bool {
+    headers
+        .get_all(http::header::CONNECTION)
+        .iter()
+        .flat_map(|value| value.as_bytes().split(|b| *b == b','))
+        .any(|option| option.trim_ascii().eq_ignore_ascii_case(token.as_bytes()))
+}
+
+/// Returns true if a response with this status never has a body.
+///
+/// `1xx`, `204 No Content` and `304 Not Modified` responses end right after their header,
+/// see [RFC 9112 Section 6.3](https://www.rfc-editor.org/rfc/rfc9112#section-6.3).
+pub(crate) fn is_bodiless_status(status: http::StatusCode) -> bool {
+    status.is_informational() || status == http::StatusCode::NO_CONTENT || status == http::StatusCode::NOT_MODIFIED
+}
+
+/// Fields that must not be sent as trailers, see [`is_forbidden_trailer`].
+static FORBIDDEN_TRAILERS: [http::HeaderName; 26] = {
+    use http::header::{
+        AUTHORIZATION, CACHE_CONTROL, CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, COOKIE, EXPECT, HOST,
+        IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, IF_UNMODIFIED_SINCE, MAX_FORWARDS, PRAGMA, PROXY_AUTHENTICATE,
+        PROXY_AUTHORIZATION, RANGE, SET_COOKIE, TE, TRAILER, TRANSFER_ENCODING, UPGRADE, WWW_AUTHENTICATE,
+    };
+
+    [
+        CONTENT_LENGTH,
+        TRANSFER_ENCODING,
+        TRAILER,
+        CONNECTION,
+        UPGRADE,
+        TE,
+        HOST,
+        AUTHORIZATION,
+        PROXY_AUTHORIZATION,
+        WWW_AUTHENTICATE,
+        PROXY_AUTHENTICATE,
+        COOKIE,
+        SET_COOKIE,
+        CACHE_CONTROL,
+        EXPECT,
+        MAX_FORWARDS,
+        PRAGMA,
+        RANGE,
+        IF_MATCH,
+        IF_NONE_MATCH,
+        IF_MODIFIED_SINCE,
+        IF_UNMODIFIED_SINCE,
+        IF_RANGE,
+        CONTENT_ENCODING,
+        CONTENT_TYPE,
+        CONTENT_RANGE,
+    ]
+};
+
+/// Returns true if `name` must not be sent as a trailer field.
+///
+/// Trailers are merged into the message after its body, so fields that control framing,
+/// routing, authentication, request modifiers or how the content is processed are
+/// forbidden, see [RFC 9110 Section 6.5.1](https://www.rfc-editor.org/rfc/rfc9110#section-6.5.1).
+pub(crate) fn is_forbidden_trailer(name: &http::HeaderName) -> bool {
+    FORBIDDEN_TRAILERS.contains(name)
+}
@@ -0,0 +1,292 @@
+//! Client connection information module
+//!
+//! This module resolves what handlers know about the client of a request:
+//! - `ConnectInfo`: The peer and local addresses of the connection and the resolved client IP
+//! - `IpCidr`: An IP network used to configure the trusted proxies
+//!
+//! By default the client IP is the address of the peer. When the peer belongs to a trusted
+//! proxy, registered with `ServerBuilder::trusted_proxy`, the client IP is taken from the
+//! `Forwarded` header, or from `X-Forwarded-For` when there is no `Forwarded` header.
+
+use http::HeaderMap;
+use http::header::FORWARDED;
+use micro_http::connection::ConnectionInfo;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Information about the connection a request arrived on.
+///
+/// Extracted by handlers as `ConnectInfo`, see [`ConnectInfo::client_ip`] for how proxies
+/// are taken into account.
+///
+/// # Example
+/// ```no_run
+/// use micro_web::ConnectInfo;
+///
+/// async fn handler(connect_info: ConnectInfo) -> String {
+///     format!("hello {}", connect_info.client_ip())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectInfo {
+    peer_addr: SocketAddr,
+    local_addr: Option<SocketAddr>,
+    tls: bool,
+    client_ip: IpAddr,
+}
+
+impl ConnectInfo {
+    /// Returns the address of the remote end of the connection, which may be a proxy.
+    #[must_use]
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Returns the local address the connection was accepted on.
+    #[must_use]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns whether the connection is secured with TLS.
+    #[must_use]
+    pub fn is_tls(&self) -> bool {
+        self.tls
+    }
+
+    /// Returns the IP address of the client.
+    ///
+    /// This is the peer's IP unless the peer is a trusted proxy. Then the addresses listed by the
+    /// forwarding headers are walked from the nearest to the farthest hop, and the first one that
+    /// isn't a trusted proxy is the client.
+    #[must_use]
+    pub fn client_ip(&self) -> IpAddr {
+        self.client_ip
+    }
+}
+
+/// An IP network in CIDR notation, such as `10.0.0.0/8` or `fd00::/8`.
+///
+/// A plain IP address parses as a network holding just that address.
+///
+/// # Example
+/// ```
+/// use micro_web::IpCidr;
+///
+/// let cidr: IpCidr = "10.0.0.0/8".parse().unwrap();
+/// assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+/// assert!(!cidr.contains("192.0.2.1".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+/// Error parsing or creating an [`IpCidr`]
+#[derive(Debug, Error)]
+#[error("invalid CIDR `{cidr}`")]
+pub struct InvalidCidr {
+    cidr: String,
+}
+
+impl IpCidr {
+    /// Creates the network of `addr` with the given prefix length.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prefix is longer than the address.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, InvalidCidr> {
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max_len {
+            return Err(InvalidCidr { cidr: format!("{addr}/{prefix_len}") });
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// Returns true if `ip` belongs to this network, IPv4-mapped IPv6 addresses match IPv4 networks.
+    #[must_use]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(net.to_bits().into(), ip.to_bits().into(), 32, self.prefix_len),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => prefix_matches(net.to_bits(), ip.to_bits(), 128, self.prefix_len),
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, bits: u8, prefix_len: u8) -> bool {
+    let shift = bits - prefix_len;
+    shift == bits || net >> shift == ip >> shift
+}
+
+impl FromStr for IpCidr {
+    type Err = InvalidCidr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = match s.split_once('/') {
+            Some((addr, prefix_len)) => addr.parse().ok().zip(prefix_len.parse().ok()),
+            None => s.parse::<IpAddr>().ok().map(|addr| (addr, if addr.is_ipv4() { 32 } else { 128 })),
+        };
+        let (addr, prefix_len) = parsed.ok_or_else(|| InvalidCidr { cidr: s.to_string() })?;
+        Self::new(addr, prefix_len)
+    }
+}
+
+impl Display for IpCidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// The proxies whose forwarding headers are trusted
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrustedProxies {
+    cidrs: Vec<IpCidr>,
+}
+
+impl TrustedProxies {
+    pub(crate) fn push(&mut self, cidr: IpCidr) {
+        self.cidrs.push(cidr);
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        self.cidrs.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Builds the [`ConnectInfo`] of a request, returns `None` if the connection has no peer address
+    pub(crate) fn connect_info(&self, connection_info: &ConnectionInfo, headers: &HeaderMap) -> Option<ConnectInfo> {
+        let peer_addr = connection_info.peer_addr()?;
+        Some(ConnectInfo {
+            peer_addr,
+            local_addr: connection_info.local_addr(),
+            tls: connection_info.is_tls(),
+            client_ip: self.client_ip(peer_addr.ip(), headers),
+        })
+    }
+
+    fn client_ip(&self, peer_ip: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client_ip = peer_ip;
+        if !self.contains(client_ip) {
+            return client_ip;
+        }
+
+        for hop in forwarded_chain(headers).into_iter().rev() {
+            // an obfuscated or unparseable hop, nothing behind it can be trusted
+            let Some(ip) = hop else {
+                break;
+            };
+            client_ip = ip;
+            if !self.contains(ip) {
+                break;
+            }
+        }
+        client_ip
+    }
+}
+
+/// Collects the hops from the `Forwarded` header, or from `X-Forwarded-For` when it's absent,
+/// ordered from the farthest to the nearest
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    if headers.contains_key(FORWARDED) {
+        headers
+            .get_all(FORWARDED)
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node.trim().trim_matches('"')))
+            })
+            .collect()
+    } else {
+        headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+            .map(|node| parse_node(node.trim()))
+            .collect()
+    }
+}
+
+/// Parses a node such as `192.0.2.43`, `192.0.2.43:47011`, `[2001:db8::1]:4711` or `2001:db8::1`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']').and_then(|(ip, _)| ip.parse().ok());
+    }
+    node.parse().ok().or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trusted(cidrs: &[&str]) -> TrustedProxies {
+        let mut proxies = TrustedProxies::default();
+        for cidr in cidrs {
+            proxies.push(cidr.parse().unwrap());
+        }
+        proxies
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs.iter().map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap())).collect()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_cidr() {
+        let cidr: IpCidr = "10.0.0.0/8".parse().unwrap();
+        assert!(cidr.contains(ip("10.255.0.1")));
+        assert!(cidr.contains(ip("::ffff:10.0.0.1")));
+        assert!(!cidr.contains(ip("11.0.0.1")));
+
+        let cidr: IpCidr = "fd00::/8".parse().unwrap();
+        assert!(cidr.contains(ip("fd12::1")));
+        assert!(!cidr.contains(ip("fe80::1")));
+
+        let cidr: IpCidr = "192.0.2.1".parse().unwrap();
+        assert_eq!(cidr.to_string(), "192.0.2.1/32");
+        assert!(!cidr.contains(ip("192.0.2.2")));
+
+        assert!("0.0.0.0/0".parse::<IpCidr>().unwrap().contains(ip("203.0.113.9")));
+        "10.0.0.0/33".parse::<IpCidr>().unwrap_err();
+        "10.0.0/8".parse::<IpCidr>().unwrap_err();
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_forwarding_headers() {
+        let proxies = trusted(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "203.0.113.9")]);
+        assert_eq!(proxies.client_ip(ip("192.0.2.1"), &headers), ip("192.0.2.1"));
+        assert_eq!(TrustedProxies::default().client_ip(ip("10.0.0.1"), &headers), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn trusted_peer_uses_x_forwarded_for() {
+        let proxies = trusted(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "198.51.100.7, 203.0.113.9, 10.0.0.2")]);
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), &headers), ip("203.0.113.9"));
+
+        let headers = self::headers(&[("x-forwarded-for", "10.0.0.3, 10.0.0.2")]);
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), &headers), ip("10.0.0.3"));
+    }
+
+    #[test]
+    fn trusted_peer_prefers_forwarded() {
+        let proxies = trusted(&["10.0.0.0/8"]);
+        let headers =
+            headers(&[("forwarded", r#"for="[2001:db8::1]:4711";proto=https, For=10.0.0.2"#), ("x-forwarded-for", "203.0.113.9")]);
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), &headers), ip("2001:db8::1"));
+
+        let headers = self::headers(&[("forwarded", "for=198.51.100.7, for=_hidden, for=10.0.0.2")]);
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), &headers), ip("10.0.0.2"));
+    }
+}
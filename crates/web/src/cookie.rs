@@ -0,0 +1,385 @@
+//! Cookie handling module
+//!
+//! This module reads the cookies of a request and sets cookies on the response:
+//! - `Cookie`: A cookie with the attributes sent in `Set-Cookie`
+//! - `CookieJar`: The cookies of a request, extracted by handlers, plus the changes to send back
+//! - `SameSite`: The `SameSite` attribute of a cookie
+//!
+//! A handler returns the jar along with its response as `(CookieJar, T)`, every cookie added to
+//! or removed from the jar is then sent as a `Set-Cookie` header.
+//!
+//! With the `cookie-crypto` feature, cookies can also be protected with a `Key` registered as
+//! server state:
+//! - `SignedCookieJar`: Cookies signed with HMAC-SHA256, readable but not forgeable by clients
+//! - `PrivateCookieJar`: Cookies encrypted with ChaCha20-Poly1305, hidden from clients
+//!
+//! # Example
+//! ```no_run
+//! use micro_web::{Cookie, CookieJar, SameSite};
+//! use std::time::Duration;
+//!
+//! async fn login(jar: CookieJar) -> (CookieJar, &'static str) {
+//!     let session = Cookie::new("session", "abc123")
+//!         .with_path("/")
+//!         .with_max_age(Duration::from_secs(3600))
+//!         .with_http_only(true)
+//!         .with_same_site(SameSite::Lax);
+//!     (jar.insert(session), "logged in")
+//! }
+//!
+//! async fn logout(jar: CookieJar) -> (CookieJar, &'static str) {
+//!     (jar.remove(Cookie::new("session", "").with_path("/")), "logged out")
+//! }
+//! ```
+
+#[cfg(feature = "cookie-crypto")]
+mod key;
+#[cfg(feature = "cookie-crypto")]
+mod private;
+#[cfg(feature = "cookie-crypto")]
+mod signed;
+
+#[cfg(feature = "cookie-crypto")]
+pub use key::{Key, KeyTooShort};
+#[cfg(feature = "cookie-crypto")]
+pub use private::PrivateCookieJar;
+#[cfg(feature = "cookie-crypto")]
+pub use signed::SignedCookieJar;
+
+use http::HeaderMap;
+use http::header::COOKIE;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// The `SameSite` attribute of a cookie, restricting when it's sent along cross-site requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    /// Only sent along same-site requests
+    Strict,
+    /// Also sent when navigating to the site from another site
+    Lax,
+    /// Sent along all requests, browsers require the cookie to be `Secure`
+    None,
+}
+
+impl Display for SameSite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SameSite::Strict => f.write_str("Strict"),
+            SameSite::Lax => f.write_str("Lax"),
+            SameSite::None => f.write_str("None"),
+        }
+    }
+}
+
+/// A cookie, with the attributes used when it's set on a response.
+///
+/// Cookies parsed from a request only carry their name and value. Displaying a cookie gives
+/// the value of its `Set-Cookie` header.
+///
+/// # Example
+/// ```
+/// use micro_web::{Cookie, SameSite};
+///
+/// let cookie = Cookie::new("theme", "dark").with_path("/").with_secure(true).with_same_site(SameSite::Strict);
+/// assert_eq!(cookie.to_string(), "theme=dark; Path=/; Secure; SameSite=Strict");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Creates a cookie without attributes.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// Sets the path the cookie is sent for.
+    #[must_use]
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Sets the domain the cookie is sent to, including its subdomains.
+    #[must_use]
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Sets how long the cookie is kept, without it the cookie lasts until the browser closes.
+    #[must_use]
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Sets whether the cookie is only sent over HTTPS.
+    #[must_use]
+    pub fn with_secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Sets whether the cookie is hidden from scripts.
+    #[must_use]
+    pub fn with_http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Sets the `SameSite` attribute of the cookie.
+    #[must_use]
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Returns the name of the cookie.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of the cookie.
+    #[must_use]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns the path the cookie is sent for.
+    #[must_use]
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Returns the domain the cookie is sent to.
+    #[must_use]
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// Returns how long the cookie is kept.
+    #[must_use]
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// Returns whether the cookie is only sent over HTTPS.
+    #[must_use]
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Returns whether the cookie is hidden from scripts.
+    #[must_use]
+    pub fn is_http_only(&self) -> bool {
+        self.http_only
+    }
+
+    /// Returns the `SameSite` attribute of the cookie.
+    #[must_use]
+    pub fn same_site(&self) -> Option<SameSite> {
+        self.same_site
+    }
+
+    /// Returns true if the cookie can be sent in a `Set-Cookie` header without altering it.
+    ///
+    /// The name must be a token and the value made of cookie octets, optionally quoted, as
+    /// defined by RFC 6265. Attributes must not contain `;` or control characters.
+    pub(crate) fn is_valid(&self) -> bool {
+        let value = self.value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(&self.value);
+        let valid_attribute = |attribute: &Option<String>| {
+            attribute.as_deref().is_none_or(|attribute| attribute.bytes().all(|b| b != b';' && !b.is_ascii_control()))
+        };
+
+        !self.name.is_empty()
+            && self.name.bytes().all(is_token_byte)
+            && value.bytes().all(is_cookie_octet)
+            && valid_attribute(&self.path)
+            && valid_attribute(&self.domain)
+    }
+}
+
+impl Display for Cookie {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={same_site}")?;
+        }
+        Ok(())
+    }
+}
+
+fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+fn is_cookie_octet(b: u8) -> bool {
+    matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+/// The cookies of a request, along with the cookies to set on the response.
+///
+/// Extracted by handlers as `CookieJar`. Cookies added or removed are visible through
+/// [`CookieJar::get`] right away, and are sent as `Set-Cookie` headers when the jar is
+/// returned with the response as `(CookieJar, T)`.
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+    delta: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// Creates an empty jar.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the cookies of the `Cookie` headers, skipping malformed pairs.
+    #[must_use]
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let cookies = headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(name, value)| {
+                let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value);
+                Cookie::new(name, value)
+            })
+            .collect();
+        Self { cookies, delta: vec![] }
+    }
+
+    /// Returns the cookie named `name`, taking the cookies added or removed into account.
+    ///
+    /// When a request carries several cookies with the same name, the first one is returned.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Cookie> {
+        match self.delta.iter().rfind(|cookie| cookie.name == name) {
+            Some(cookie) if is_removal(cookie) => None,
+            Some(cookie) => Some(cookie),
+            None => self.cookies.iter().find(|cookie| cookie.name == name),
+        }
+    }
+
+    /// Returns the cookies sent with the request.
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.iter()
+    }
+
+    /// Adds a cookie to set on the response.
+    #[must_use]
+    pub fn insert(mut self, cookie: Cookie) -> Self {
+        self.delta.push(cookie);
+        self
+    }
+
+    /// Removes a cookie from the client by setting it empty and expired.
+    ///
+    /// The path and domain of `cookie` must be the ones the cookie was set with, otherwise
+    /// the client keeps it.
+    #[must_use]
+    pub fn remove(mut self, cookie: Cookie) -> Self {
+        self.delta.push(Cookie { value: String::new(), max_age: Some(Duration::ZERO), ..cookie });
+        self
+    }
+
+    /// Returns the cookies added or removed, in the order they're sent as `Set-Cookie` headers.
+    pub fn delta(&self) -> impl Iterator<Item = &Cookie> {
+        self.delta.iter()
+    }
+}
+
+fn is_removal(cookie: &Cookie) -> bool {
+    cookie.max_age == Some(Duration::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_request_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.append(COOKIE, "session=abc123; theme=\"dark\"; malformed; =empty".parse().unwrap());
+        headers.append(COOKIE, "lang=en; session=shadowed".parse().unwrap());
+
+        let jar = CookieJar::from_headers(&headers);
+        assert_eq!(jar.get("session").map(Cookie::value), Some("abc123"));
+        assert_eq!(jar.get("theme").map(Cookie::value), Some("dark"));
+        assert_eq!(jar.get("lang").map(Cookie::value), Some("en"));
+        assert!(jar.get("malformed").is_none());
+        assert_eq!(jar.iter().count(), 4);
+    }
+
+    #[test]
+    fn delta_overrides_request_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, "session=abc123; theme=dark".parse().unwrap());
+
+        let jar = CookieJar::from_headers(&headers).insert(Cookie::new("theme", "light")).remove(Cookie::new("session", "").with_path("/"));
+        assert_eq!(jar.get("theme").map(Cookie::value), Some("light"));
+        assert!(jar.get("session").is_none());
+
+        let delta = jar.delta().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(delta, ["theme=light", "session=; Path=/; Max-Age=0"]);
+    }
+
+    #[test]
+    fn format_set_cookie() {
+        let cookie = Cookie::new("session", "abc123")
+            .with_path("/")
+            .with_domain("example.com")
+            .with_max_age(Duration::from_secs(3600))
+            .with_secure(true)
+            .with_http_only(true)
+            .with_same_site(SameSite::None);
+        assert_eq!(cookie.to_string(), "session=abc123; Path=/; Domain=example.com; Max-Age=3600; Secure; HttpOnly; SameSite=None");
+        assert!(cookie.is_valid());
+
+        assert!(Cookie::new("quoted", "\"value\"").is_valid());
+        assert!(!Cookie::new("", "value").is_valid());
+        assert!(!Cookie::new("bad name", "value").is_valid());
+        assert!(!Cookie::new("name", "a;b").is_valid());
+        assert!(!Cookie::new("name", "value").with_path("/; Domain=evil.com").is_valid());
+    }
+}
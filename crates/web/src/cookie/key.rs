@@ -0,0 +1,118 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt::{Debug, Formatter};
+use thiserror::Error;
+
+/// The least bytes a master key must have
+const MIN_MASTER_KEY_LEN: usize = 32;
+
+/// The length of the keys derived from the master key
+const KEY_LEN: usize = 32;
+
+/// The secret signing and encrypting cookies, read by [`SignedCookieJar`](crate::SignedCookieJar)
+/// and [`PrivateCookieJar`](crate::PrivateCookieJar) from the server state.
+///
+/// A key is derived from a master key of at least 32 random bytes. Cookies set with a key can
+/// only be read back with a key derived from the same master key, so keep the master key stable
+/// across restarts and shared by every instance of the server, and rotate it to invalidate
+/// every signed and private cookie at once.
+///
+/// # Example
+/// ```no_run
+/// use micro_web::router::Router;
+/// use micro_web::{Key, Server};
+///
+/// let master = std::env::var("COOKIE_KEY").expect("COOKIE_KEY must be set");
+/// let key = Key::new(master.as_bytes()).expect("COOKIE_KEY is too short");
+///
+/// let router = Router::builder().build();
+/// let server = Server::builder().router(router).state(key).bind("127.0.0.1:3000").build();
+/// ```
+#[derive(Clone)]
+pub struct Key {
+    signing: [u8; KEY_LEN],
+    encryption: [u8; KEY_LEN],
+}
+
+impl Key {
+    /// Derives a key from `master`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `master` is shorter than 32 bytes.
+    pub fn new(master: &[u8]) -> Result<Self, KeyTooShort> {
+        if master.len() < MIN_MASTER_KEY_LEN {
+            return Err(KeyTooShort { len: master.len() });
+        }
+
+        // independent keys for each use, so a signature can never pass as an encrypted value
+        Ok(Self { signing: derive(master, b"micro-web signed cookies"), encryption: derive(master, b"micro-web private cookies") })
+    }
+
+    /// Generates a key from a random master key.
+    ///
+    /// Cookies set with a generated key can't be read after a restart, or by another instance
+    /// of the server.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system can't provide random bytes.
+    #[must_use]
+    pub fn generate() -> Self {
+        let mut master = [0; MIN_MASTER_KEY_LEN];
+        getrandom::fill(&mut master).expect("failed to generate a random cookie key");
+        Self::new(&master).unwrap()
+    }
+
+    /// Returns the HMAC-SHA256 key of signed cookies
+    pub(crate) fn signing(&self) -> &[u8; KEY_LEN] {
+        &self.signing
+    }
+
+    /// Returns the ChaCha20-Poly1305 key of private cookies
+    pub(crate) fn encryption(&self) -> &[u8; KEY_LEN] {
+        &self.encryption
+    }
+}
+
+/// Derives the key used for `purpose` from `master`, as the HMAC-SHA256 of `purpose` keyed with `master`
+fn derive(master: &[u8], purpose: &[u8]) -> [u8; KEY_LEN] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(master).expect("HMAC accepts keys of any length");
+    mac.update(purpose);
+    mac.finalize().into_bytes().into()
+}
+
+/// Hides the key material from logs
+impl Debug for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key").finish_non_exhaustive()
+    }
+}
+
+/// Error creating a [`Key`] from a master key shorter than 32 bytes
+#[derive(Debug, Error)]
+#[error("a cookie master key must have at least {MIN_MASTER_KEY_LEN} bytes, got {len}")]
+pub struct KeyTooShort {
+    len: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derived_keys_are_independent() {
+        let key = Key::new(&[7; 32]).unwrap();
+        assert_ne!(key.signing(), key.encryption());
+        assert_eq!(Key::new(&[7; 32]).unwrap().signing(), key.signing());
+
+        assert_ne!(Key::generate().signing(), Key::generate().signing());
+        assert_eq!(format!("{key:?}"), "Key { .. }");
+    }
+
+    #[test]
+    fn short_master_key_is_error() {
+        let e = Key::new(b"too short").unwrap_err();
+        assert_eq!(e.to_string(), "a cookie master key must have at least 32 bytes, got 9");
+    }
+}
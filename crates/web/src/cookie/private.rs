@@ -0,0 +1,155 @@
+use crate::cookie::{Cookie, CookieJar, Key};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+/// The length of a ChaCha20-Poly1305 nonce
+const NONCE_LEN: usize = 12;
+
+/// A [`CookieJar`] whose cookies are encrypted, so clients can neither read nor tamper with them.
+///
+/// Extracted by handlers as `PrivateCookieJar`, with the [`Key`] registered as server state.
+/// The value of a private cookie is encrypted with ChaCha20-Poly1305 under a random nonce, with
+/// the cookie name as associated data so it can't be moved to another cookie; cookies that fail
+/// to decrypt are ignored. Return the jar with the response as `(PrivateCookieJar, T)` to send the cookies
+/// added or removed.
+///
+/// Values are encoded after encryption, so they may hold any text.
+///
+/// # Example
+/// ```no_run
+/// use micro_web::{Cookie, PrivateCookieJar};
+///
+/// async fn remember(jar: PrivateCookieJar) -> (PrivateCookieJar, &'static str) {
+///     (jar.insert(Cookie::new("email", "alice@example.com; verified").with_http_only(true)), "remembered")
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PrivateCookieJar {
+    jar: CookieJar,
+    key: Key,
+}
+
+impl PrivateCookieJar {
+    /// Wraps `jar`, encrypting and decrypting its cookies with `key`.
+    #[must_use]
+    pub fn new(jar: CookieJar, key: Key) -> Self {
+        Self { jar, key }
+    }
+
+    /// Returns the cookie named `name` if it decrypts, with its value decrypted.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<Cookie> {
+        self.jar.get(name).and_then(|cookie| self.decrypt(cookie))
+    }
+
+    /// Returns the cookies sent with the request that decrypt.
+    pub fn iter(&self) -> impl Iterator<Item = Cookie> + '_ {
+        self.jar.iter().filter_map(|cookie| self.decrypt(cookie))
+    }
+
+    /// Encrypts a cookie and adds it to set on the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system can't provide random bytes for the nonce.
+    #[must_use]
+    pub fn insert(self, cookie: Cookie) -> Self {
+        let mut nonce = [0; NONCE_LEN];
+        getrandom::fill(&mut nonce).expect("failed to generate a random cookie nonce");
+
+        let payload = Payload { msg: cookie.value.as_bytes(), aad: cookie.name.as_bytes() };
+        let ciphertext =
+            self.cipher().encrypt(Nonce::from_slice(&nonce), payload).expect("cookie values are far below the ChaCha20-Poly1305 limit");
+
+        let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+
+        let value = URL_SAFE_NO_PAD.encode(sealed);
+        Self { jar: self.jar.insert(Cookie { value, ..cookie }), key: self.key }
+    }
+
+    /// Removes a cookie from the client, see [`CookieJar::remove`].
+    #[must_use]
+    pub fn remove(self, cookie: Cookie) -> Self {
+        Self { jar: self.jar.remove(cookie), key: self.key }
+    }
+
+    /// Returns the underlying jar, holding the encrypted values.
+    #[must_use]
+    pub fn into_inner(self) -> CookieJar {
+        self.jar
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(self.key.encryption().into())
+    }
+
+    fn decrypt(&self, cookie: &Cookie) -> Option<Cookie> {
+        let sealed = URL_SAFE_NO_PAD.decode(&cookie.value).ok()?;
+        let (nonce, ciphertext) = sealed.split_at_checked(NONCE_LEN)?;
+
+        let payload = Payload { msg: ciphertext, aad: cookie.name.as_bytes() };
+        let value = self.cipher().decrypt(Nonce::from_slice(nonce), payload).ok()?;
+        let value = String::from_utf8(value).ok()?;
+        Some(Cookie { value, ..cookie.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderMap;
+    use http::header::COOKIE;
+
+    fn key() -> Key {
+        Key::new(&[7; 32]).unwrap()
+    }
+
+    fn request_jar(cookies: &str) -> PrivateCookieJar {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, cookies.parse().unwrap());
+        PrivateCookieJar::new(CookieJar::from_headers(&headers), key())
+    }
+
+    fn encrypt(name: &str, value: &str) -> String {
+        let jar = PrivateCookieJar::new(CookieJar::new(), key()).insert(Cookie::new(name, value));
+        jar.into_inner().delta().next().unwrap().value().to_string()
+    }
+
+    #[test]
+    fn private_cookies_round_trip() {
+        let jar = PrivateCookieJar::new(CookieJar::new(), key()).insert(Cookie::new("email", "alice@example.com; verified").with_path("/"));
+        assert_eq!(jar.get("email").unwrap().value(), "alice@example.com; verified");
+
+        let encrypted = jar.into_inner().delta().next().unwrap().clone();
+        assert!(!encrypted.value().contains("alice"));
+        assert_eq!(encrypted.path(), Some("/"));
+        assert!(encrypted.is_valid());
+
+        let jar = request_jar(&format!("email={}; plain=1", encrypted.value()));
+        assert_eq!(jar.get("email").unwrap().value(), "alice@example.com; verified");
+        assert!(jar.get("plain").is_none());
+        assert_eq!(jar.iter().count(), 1);
+
+        // a random nonce makes every encryption of a value different
+        assert_ne!(encrypt("email", "alice"), encrypt("email", "alice"));
+    }
+
+    #[test]
+    fn tampered_cookies_are_ignored() {
+        let encrypted = encrypt("role", "user");
+
+        let mut flipped = URL_SAFE_NO_PAD.decode(&encrypted).unwrap();
+        flipped[NONCE_LEN] ^= 1;
+        assert!(request_jar(&format!("role={}", URL_SAFE_NO_PAD.encode(&flipped))).get("role").is_none());
+        assert!(request_jar(&format!("admin_role={encrypted}")).get("admin_role").is_none());
+        assert!(request_jar("role=dXNlcg").get("role").is_none());
+
+        let jar = PrivateCookieJar::new(CookieJar::new(), Key::new(&[8; 32]).unwrap()).insert(Cookie::new("role", "admin"));
+        let forged = jar.into_inner().delta().next().unwrap().value().to_string();
+        assert!(request_jar(&format!("role={forged}")).get("role").is_none());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::cookie::{Cookie, CookieJar, Key};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// The length of an HMAC-SHA256 tag
+const TAG_LEN: usize = 32;
+
+/// The length of a base64 encoded signature
+const SIGNATURE_LEN: usize = (TAG_LEN * 8).div_ceil(6);
+
+/// A [`CookieJar`] whose cookies are signed, so clients can read but not tamper with them.
+///
+/// Extracted by handlers as `SignedCookieJar`, with the [`Key`] registered as server state.
+/// The value of a signed cookie is prefixed with an HMAC-SHA256 signature of its name and value,
+/// cookies with a missing or wrong signature are ignored. Return the jar with the response as
+/// `(SignedCookieJar, T)` to send the cookies added or removed.
+///
+/// # Example
+/// ```no_run
+/// use micro_web::{Cookie, SignedCookieJar};
+///
+/// async fn login(jar: SignedCookieJar) -> (SignedCookieJar, &'static str) {
+///     (jar.insert(Cookie::new("user_id", "42").with_http_only(true)), "logged in")
+/// }
+///
+/// async fn whoami(jar: SignedCookieJar) -> String {
+///     jar.get("user_id").map_or("anonymous".to_string(), |cookie| cookie.value().to_string())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SignedCookieJar {
+    jar: CookieJar,
+    key: Key,
+}
+
+impl SignedCookieJar {
+    /// Wraps `jar`, signing and verifying its cookies with `key`.
+    #[must_use]
+    pub fn new(jar: CookieJar, key: Key) -> Self {
+        Self { jar, key }
+    }
+
+    /// Returns the cookie named `name` if its signature is valid, with the signature stripped
+    /// from its value.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<Cookie> {
+        self.jar.get(name).and_then(|cookie| self.verify(cookie))
+    }
+
+    /// Returns the cookies sent with the request whose signature is valid.
+    pub fn iter(&self) -> impl Iterator<Item = Cookie> + '_ {
+        self.jar.iter().filter_map(|cookie| self.verify(cookie))
+    }
+
+    /// Signs a cookie and adds it to set on the response.
+    #[must_use]
+    pub fn insert(self, cookie: Cookie) -> Self {
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&cookie.name, &cookie.value).finalize().into_bytes());
+        let value = signature + &cookie.value;
+        Self { jar: self.jar.insert(Cookie { value, ..cookie }), key: self.key }
+    }
+
+    /// Removes a cookie from the client, see [`CookieJar::remove`].
+    #[must_use]
+    pub fn remove(self, cookie: Cookie) -> Self {
+        Self { jar: self.jar.remove(cookie), key: self.key }
+    }
+
+    /// Returns the underlying jar, holding the signed values.
+    #[must_use]
+    pub fn into_inner(self) -> CookieJar {
+        self.jar
+    }
+
+    /// Returns the HMAC of a cookie, covering its name so a signature can't be moved to another cookie
+    fn mac(&self, name: &str, value: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.key.signing()).expect("HMAC accepts keys of any length");
+        mac.update(name.as_bytes());
+        mac.update(b"=");
+        mac.update(value.as_bytes());
+        mac
+    }
+
+    fn verify(&self, cookie: &Cookie) -> Option<Cookie> {
+        let (signature, value) = cookie.value.split_at_checked(SIGNATURE_LEN)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        // compares in constant time
+        self.mac(&cookie.name, value).verify_slice(&signature).ok()?;
+        Some(Cookie { value: value.to_string(), ..cookie.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderMap;
+    use http::header::COOKIE;
+
+    fn key() -> Key {
+        Key::new(&[7; 32]).unwrap()
+    }
+
+    fn request_jar(cookies: &str) -> SignedCookieJar {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, cookies.parse().unwrap());
+        SignedCookieJar::new(CookieJar::from_headers(&headers), key())
+    }
+
+    #[test]
+    fn signed_cookies_round_trip() {
+        let jar = SignedCookieJar::new(CookieJar::new(), key()).insert(Cookie::new("user_id", "42").with_path("/"));
+        assert_eq!(jar.get("user_id").map(|cookie| cookie.value().to_string()).as_deref(), Some("42"));
+
+        let signed = jar.into_inner().delta().next().unwrap().clone();
+        assert_eq!(signed.value().len(), SIGNATURE_LEN + 2);
+        assert!(signed.value().ends_with("42"));
+        assert_eq!(signed.path(), Some("/"));
+        assert!(signed.is_valid());
+
+        let jar = request_jar(&format!("user_id={}; plain=1", signed.value()));
+        assert_eq!(jar.get("user_id").unwrap().value(), "42");
+        assert!(jar.get("plain").is_none());
+        assert_eq!(jar.iter().count(), 1);
+    }
+
+    #[test]
+    fn tampered_cookies_are_ignored() {
+        let jar = SignedCookieJar::new(CookieJar::new(), key()).insert(Cookie::new("user_id", "42"));
+        let signed = jar.into_inner().delta().next().unwrap().value().to_string();
+
+        assert!(request_jar(&format!("user_id={}43", &signed[..SIGNATURE_LEN])).get("user_id").is_none());
+        // the signature covers the name, so it can't be moved to another cookie
+        assert!(request_jar(&format!("admin_id={signed}")).get("admin_id").is_none());
+
+        let other_key = SignedCookieJar::new(CookieJar::new(), Key::new(&[8; 32]).unwrap()).insert(Cookie::new("user_id", "42"));
+        let forged = other_key.into_inner().delta().next().unwrap().value().to_string();
+        assert!(request_jar(&format!("user_id={forged}")).get("user_id").is_none());
+    }
+}
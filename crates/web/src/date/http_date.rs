@@ -0,0 +1,130 @@
+//! Formatting and parsing of HTTP dates.
+//!
+//! Dates are written in the IMF-fixdate format of RFC 9110 Section 5.6.7, e.g.
+//! `Sun, 06 Nov 1994 08:49:37 GMT`, which is also the only format accepted when parsing. Times
+//! before the Unix epoch are not supported.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Formats `time` as an IMF-fixdate, truncated to whole seconds.
+///
+/// Times before the Unix epoch are formatted as the epoch.
+pub(crate) fn format_http_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let days = secs / SECONDS_PER_DAY;
+    let secs_of_day = secs % SECONDS_PER_DAY;
+    let (year, month, day) = civil_from_days(days);
+
+    // the epoch was a thursday, the first entry of `WEEKDAYS`
+    let weekday = WEEKDAYS[usize::try_from(days % 7).unwrap()];
+    let month = MONTHS[usize::try_from(month - 1).unwrap()];
+
+    format!("{weekday}, {day:02} {month} {year:04} {:02}:{:02}:{:02} GMT", secs_of_day / 3600, secs_of_day % 3600 / 60, secs_of_day % 60)
+}
+
+/// Parses an IMF-fixdate, returns `None` for any other input.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let value = value.as_bytes();
+    if value.len() != 29 || &value[3..5] != b", " || &value[25..] != b" GMT" {
+        return None;
+    }
+    if !WEEKDAYS.iter().any(|weekday| weekday.as_bytes() == &value[..3]) {
+        return None;
+    }
+
+    let number = |range: std::ops::Range<usize>| -> Option<u64> {
+        let digits = &value[range];
+        digits.iter().all(u8::is_ascii_digit).then(|| digits.iter().fold(0, |n, digit| n * 10 + u64::from(digit - b'0')))
+    };
+
+    let day = number(5..7)?;
+    let month = MONTHS.iter().position(|month| month.as_bytes() == &value[8..11])? as u64 + 1;
+    let year = number(12..16)?;
+    let (hour, minute, second) = (number(17..19)?, number(20..22)?, number(23..25)?);
+    if value[7] != b' ' || value[11] != b' ' || value[16] != b' ' || value[19] != b':' || value[22] != b':' {
+        return None;
+    }
+    if year < 1970 || day == 0 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn is_leap_year(year: u64) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts days since the Unix epoch to a `(year, month, day)` date in the proleptic Gregorian
+/// calendar, the month and the day start at 1.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // shift the epoch to 0000-03-01, so the leap day is the last day of a year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The inverse of [`civil_from_days`], `year` must not be before 1970.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month_from_march = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_http_date() {
+        assert_eq!(format_http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(format_http_date(UNIX_EPOCH + Duration::from_secs(784_111_777)), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(format_http_date(UNIX_EPOCH + Duration::from_millis(951_782_400_999)), "Tue, 29 Feb 2000 00:00:00 GMT");
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(UNIX_EPOCH + Duration::from_secs(784_111_777)));
+        assert_eq!(parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT"), Some(UNIX_EPOCH + Duration::from_secs(951_782_400)));
+
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), None);
+        assert_eq!(parse_http_date("Mon, 29 Feb 2100 00:00:00 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 24:49:37 GMT"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for secs in [0, 59, 86_399, 86_400, 1_700_000_000, 4_102_444_800] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(parse_http_date(&format_http_date(time)), Some(time));
+        }
+    }
+}
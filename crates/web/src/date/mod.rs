@@ -12,8 +12,10 @@ use std::sync::Arc;
 use std::time::Duration;
 
 mod date_service_decorator;
+mod http_date;
 
 pub use date_service_decorator::DateServiceDecorator;
+pub(crate) use http_date::{format_http_date, parse_http_date};
 
 /// A service that maintains and periodically updates the current HTTP date string.
 ///
@@ -7,7 +7,7 @@ use async_trait::async_trait;
 use bytes::{Buf, Bytes};
 use flate2::Compression;
 use flate2::write::{GzEncoder, ZlibEncoder};
-use http::{Response, StatusCode};
+use http::{HeaderMap, HeaderValue, Response, StatusCode};
 use http_body::{Body, Frame};
 use http_body_util::combinators::UnsyncBoxBody;
 use micro_http::protocol::{HttpError, SendError};
@@ -17,7 +17,7 @@ use std::io;
 use std::io::Write;
 use std::pin::Pin;
 use std::task::{Context, Poll, ready};
-use tracing::{error, trace};
+use tracing::trace;
 use zstd::stream::write::Encoder as ZstdEncoder;
 // (almost thanks and) copy from actix-http: https://github.com/actix/actix-web/blob/master/actix-http/src/encoding/encoder.rs
 
@@ -160,18 +160,32 @@ impl Encoder {
 
 pin_project! {
     /// A wrapper around a `Body` that encodes the data.
+    ///
+    /// Trailers end the inner body, so the encoder is finished when they arrive and they are
+    /// passed on after the remaining encoded data.
     struct EncodedBody<B: Body> {
         #[pin]
         inner: B,
         encoder: Option<Encoder>,
         state: Option<bool>,
+        trailers: Option<HeaderMap>,
     }
 }
 
 impl<B: Body> EncodedBody<B> {
     /// Creates a new `EncodedBody`.
     fn new(b: B, encoder: Encoder) -> Self {
-        Self { inner: b, encoder: Some(encoder), state: Some(true) }
+        Self { inner: b, encoder: Some(encoder), state: Some(true), trailers: None }
+    }
+}
+
+/// Finishes the encoder and returns the remaining encoded data, or the trailers if no data is left.
+fn finish_frame(encoder: &mut Option<Encoder>, trailers: &mut Option<HeaderMap>) -> Option<Result<Frame<Bytes>, HttpError>> {
+    // unwrap here is safe, because we only take once
+    match encoder.take().unwrap().finish() {
+        Ok(bytes) if !bytes.is_empty() => Some(Ok(Frame::data(bytes))),
+        Ok(_) => trailers.take().map(|trailers| Ok(Frame::trailers(trailers))),
+        Err(e) => Some(Err(SendError::from(e).into())),
     }
 }
 
@@ -188,7 +202,7 @@ where
         let mut this = self.project();
 
         if this.state.is_none() {
-            return Poll::Ready(None);
+            return Poll::Ready(this.trailers.take().map(|trailers| Ok(Frame::trailers(trailers))));
         }
 
         loop {
@@ -196,12 +210,11 @@ where
                 Some(Ok(frame)) => {
                     let data = match frame.into_data() {
                         Ok(data) => data,
-                        Err(mut frame) => {
-                            let debug_info = frame.trailers_mut();
-                            error!("want to data from body, but receive trailer header: {:?}", debug_info);
-                            return Poll::Ready(Some(
-                                Err(SendError::invalid_body(format!("invalid body frame : {:?}", debug_info)).into()),
-                            ));
+                        Err(frame) => {
+                            // will only run below code once, the body ends with its trailers
+                            this.state.take();
+                            *this.trailers = frame.into_trailers().ok();
+                            return Poll::Ready(finish_frame(this.encoder, this.trailers));
                         }
                     };
 
@@ -220,21 +233,9 @@ where
                 }
                 Some(Err(e)) => Poll::Ready(Some(Err(SendError::invalid_body(e.to_string()).into()))),
                 None => {
-                    if this.state.is_some() {
-                        // will only run below  code once
-                        this.state.take();
-
-                        // unwrap here is safe, because we only take once
-                        let bytes = match this.encoder.take().unwrap().finish() {
-                            Ok(bytes) => bytes,
-                            Err(e) => {
-                                return Poll::Ready(Some(Err(SendError::from(e).into())));
-                            }
-                        };
-                        if !bytes.is_empty() { Poll::Ready(Some(Ok(Frame::data(bytes)))) } else { Poll::Ready(None) }
-                    } else {
-                        Poll::Ready(None)
-                    }
+                    // will only run below code once
+                    this.state.take();
+                    Poll::Ready(finish_frame(this.encoder, this.trailers))
                 }
             };
         }
@@ -289,12 +290,13 @@ impl<H: RequestHandler> RequestHandler for EncodeRequestHandler<H> {
 /// Encodes the response body based on the `Accept-Encoding` header.
 fn encode(req: &RequestContext, resp: &mut Response<ResponseBody>) {
     let status_code = resp.status();
-    if status_code == StatusCode::NO_CONTENT || status_code == StatusCode::SWITCHING_PROTOCOLS {
+    // a partial content is a range of the unencoded representation
+    if matches!(status_code, StatusCode::NO_CONTENT | StatusCode::SWITCHING_PROTOCOLS | StatusCode::PARTIAL_CONTENT) {
         return;
     }
 
     // response has already encoded
-    if req.headers().contains_key(http::header::CONTENT_ENCODING) {
+    if resp.headers().contains_key(http::header::CONTENT_ENCODING) {
         return;
     }
 
@@ -337,6 +339,94 @@ fn encode(req: &RequestContext, resp: &mut Response<ResponseBody>) {
     let encoded_body = EncodedBody::new(body.take(), encoder);
     body.replace(ResponseBody::stream(UnsyncBoxBody::new(encoded_body)));
 
-    resp.headers_mut().remove(http::header::CONTENT_LENGTH);
-    resp.headers_mut().append(http::header::CONTENT_ENCODING, encoder_name.parse().unwrap());
+    let headers = resp.headers_mut();
+    headers.remove(http::header::CONTENT_LENGTH);
+    headers.append(http::header::CONTENT_ENCODING, encoder_name.parse().unwrap());
+    mark_as_encoded(headers);
+}
+
+/// Updates the headers of a response whose body has just been encoded.
+///
+/// The encoded body is another representation: ranges of it can't be requested since a partial
+/// response is never encoded, and a strong entity tag of the unencoded body doesn't validate it,
+/// it's made weak so conditional requests still match but `If-Range` doesn't.
+fn mark_as_encoded(headers: &mut HeaderMap) {
+    headers.remove(http::header::ACCEPT_RANGES);
+
+    if let Some(etag) = headers.get(http::header::ETAG)
+        && etag.as_bytes().starts_with(b"\"")
+    {
+        let weak = [b"W/", etag.as_bytes()].concat();
+        headers.insert(http::header::ETAG, HeaderValue::from_bytes(&weak).unwrap());
+    }
+
+    let varies = headers.get_all(http::header::VARY).iter().filter_map(|vary| vary.to_str().ok()).flat_map(|vary| vary.split(','));
+    if !varies.map(str::trim).any(|vary| vary == "*" || vary.eq_ignore_ascii_case("accept-encoding")) {
+        headers.append(http::header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathParams;
+    use http_body_util::{BodyExt, StreamBody};
+    use micro_http::protocol::RequestHeader;
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn trailers_follow_encoded_data() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", "5d41".parse().unwrap());
+        let frames: [Result<_, Infallible>; 2] = [Ok(Frame::data(Bytes::from_static(b"hello"))), Ok(Frame::trailers(trailers))];
+
+        let body = EncodedBody::new(StreamBody::new(futures::stream::iter(frames)), Encoder::gzip());
+        let collected = body.collect().await.unwrap();
+
+        assert_eq!(collected.trailers().unwrap().get("x-checksum").unwrap(), "5d41");
+        let bytes = collected.to_bytes();
+        let mut text = String::new();
+        io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&*bytes), &mut text).unwrap();
+        assert_eq!(text, "hello");
+    }
+
+    #[test]
+    fn encoded_response_is_not_encoded_again() {
+        let header = RequestHeader::from(http::Request::builder().header(http::header::ACCEPT_ENCODING, "gzip").body(()).unwrap());
+        let params = PathParams::empty();
+        let req = RequestContext::new(&header, &params);
+
+        let mut resp = Response::new(ResponseBody::once(Bytes::from(vec![b'a'; 4096])));
+        resp.headers_mut().insert(http::header::CONTENT_ENCODING, "br".parse().unwrap());
+        encode(&req, &mut resp);
+
+        assert_eq!(resp.headers().get_all(http::header::CONTENT_ENCODING).iter().collect::<Vec<_>>(), ["br"]);
+        assert_eq!(resp.body().size_hint().exact(), Some(4096));
+    }
+
+    #[test]
+    fn encoded_response_is_another_representation() {
+        let header = RequestHeader::from(http::Request::builder().header(http::header::ACCEPT_ENCODING, "gzip").body(()).unwrap());
+        let params = PathParams::empty();
+        let req = RequestContext::new(&header, &params);
+
+        let mut resp = Response::new(ResponseBody::once(Bytes::from(vec![b'a'; 4096])));
+        resp.headers_mut().insert(http::header::ETAG, "\"abc\"".parse().unwrap());
+        resp.headers_mut().insert(http::header::ACCEPT_RANGES, "bytes".parse().unwrap());
+        resp.headers_mut().insert(http::header::VARY, "origin".parse().unwrap());
+        encode(&req, &mut resp);
+
+        assert_eq!(resp.headers().get(http::header::CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(resp.headers().get(http::header::ETAG).unwrap(), "W/\"abc\"");
+        assert!(!resp.headers().contains_key(http::header::ACCEPT_RANGES));
+        assert_eq!(resp.headers().get_all(http::header::VARY).iter().collect::<Vec<_>>(), ["origin", "accept-encoding"]);
+
+        let mut resp = Response::new(ResponseBody::once(Bytes::from(vec![b'a'; 4096])));
+        resp.headers_mut().insert(http::header::ETAG, "W/\"abc\"".parse().unwrap());
+        resp.headers_mut().insert(http::header::VARY, "Accept-Encoding".parse().unwrap());
+        encode(&req, &mut resp);
+
+        assert_eq!(resp.headers().get(http::header::ETAG).unwrap(), "W/\"abc\"");
+        assert_eq!(resp.headers().get_all(http::header::VARY).iter().collect::<Vec<_>>(), ["Accept-Encoding"]);
+    }
 }
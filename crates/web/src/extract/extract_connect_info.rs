@@ -0,0 +1,43 @@
+//! Connection information extraction
+//!
+//! This module provides extractors for the connection a request arrived on: [`ConnectInfo`]
+//! with the resolved client IP, and [`SocketAddr`] with the address of the peer.
+//!
+//! # Example
+//! ```no_run
+//! use micro_web::ConnectInfo;
+//! use std::net::SocketAddr;
+//!
+//! async fn handler(peer_addr: SocketAddr, connect_info: ConnectInfo) -> String {
+//!     format!("client {} connected through {}", connect_info.client_ip(), peer_addr)
+//! }
+//! ```
+
+use crate::body::OptionReqBody;
+use crate::extract::MissingExtension;
+use crate::extract::from_request::FromRequest;
+use crate::{ConnectInfo, RequestContext};
+use std::net::SocketAddr;
+
+/// Extracts the information about the connection
+///
+/// Requests served by the `Server` always carry it, it's only missing when the request was
+/// handed over without a peer address.
+impl FromRequest for ConnectInfo {
+    type Output<'any> = ConnectInfo;
+    type Error = MissingExtension;
+
+    async fn from_request(req: &RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'static>, Self::Error> {
+        req.extensions().get::<ConnectInfo>().copied().ok_or_else(MissingExtension::new::<ConnectInfo>)
+    }
+}
+
+/// Extracts the address of the peer, which is a proxy when the request was forwarded
+impl FromRequest for SocketAddr {
+    type Output<'any> = SocketAddr;
+    type Error = MissingExtension;
+
+    async fn from_request(req: &RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'static>, Self::Error> {
+        req.extensions().get::<ConnectInfo>().map(ConnectInfo::peer_addr).ok_or_else(MissingExtension::new::<ConnectInfo>)
+    }
+}
@@ -0,0 +1,61 @@
+//! Cookie extraction
+//!
+//! This module provides the [`FromRequest`] implementation parsing the `Cookie` headers of a
+//! request into a [`CookieJar`]. With the `cookie-crypto` feature, the `SignedCookieJar` and
+//! `PrivateCookieJar` extractors wrap the jar with the `Key` registered as server state, and
+//! fail with [`MissingState`](crate::extract::MissingState) when no key is registered.
+//!
+//! # Example
+//! ```no_run
+//! use micro_web::CookieJar;
+//!
+//! async fn handler(jar: CookieJar) -> String {
+//!     match jar.get("theme") {
+//!         Some(theme) => format!("theme is {}", theme.value()),
+//!         None => "no theme".to_string(),
+//!     }
+//! }
+//! ```
+
+use crate::body::OptionReqBody;
+#[cfg(feature = "cookie-crypto")]
+use crate::extract::MissingState;
+use crate::extract::from_request::FromRequest;
+use crate::{CookieJar, RequestContext};
+#[cfg(feature = "cookie-crypto")]
+use crate::{Key, PrivateCookieJar, SignedCookieJar};
+use std::convert::Infallible;
+
+/// Extracts the cookies of the request, malformed cookies are skipped
+impl FromRequest for CookieJar {
+    type Output<'any> = CookieJar;
+    type Error = Infallible;
+
+    async fn from_request(req: &RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'static>, Self::Error> {
+        Ok(CookieJar::from_headers(req.headers()))
+    }
+}
+
+/// Extracts the cookies of the request, verified with the registered [`Key`]
+#[cfg(feature = "cookie-crypto")]
+impl FromRequest for SignedCookieJar {
+    type Output<'any> = SignedCookieJar;
+    type Error = MissingState;
+
+    async fn from_request(req: &RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'static>, Self::Error> {
+        let key = req.state_map().get::<Key>().ok_or_else(MissingState::new::<Key>)?;
+        Ok(SignedCookieJar::new(CookieJar::from_headers(req.headers()), key.clone()))
+    }
+}
+
+/// Extracts the cookies of the request, decrypted with the registered [`Key`]
+#[cfg(feature = "cookie-crypto")]
+impl FromRequest for PrivateCookieJar {
+    type Output<'any> = PrivateCookieJar;
+    type Error = MissingState;
+
+    async fn from_request(req: &RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'static>, Self::Error> {
+        let key = req.state_map().get::<Key>().ok_or_else(MissingState::new::<Key>)?;
+        Ok(PrivateCookieJar::new(CookieJar::from_headers(req.headers()), key.clone()))
+    }
+}
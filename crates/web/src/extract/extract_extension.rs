@@ -0,0 +1,67 @@
+//! Request extension extraction
+//!
+//! This module provides the [`FromRequest`] implementation resolving [`Extension<T>`] from the
+//! extensions of the [`RequestContext`], which carry the values inserted by decorators as well as
+//! the extensions of the underlying `http::Request`.
+//!
+//! # Example
+//! ```no_run
+//! # use micro_web::extract::Extension;
+//! #[derive(Clone)]
+//! struct RequestId(u64);
+//!
+//! async fn handler(Extension(id): Extension<RequestId>) -> String {
+//!     format!("request {}", id.0)
+//! }
+//! ```
+
+use crate::body::OptionReqBody;
+use crate::extract::Extension;
+use crate::extract::from_request::FromRequest;
+use crate::responder::Responder;
+use crate::{RequestContext, ResponseBody};
+use http::{Response, StatusCode};
+use std::any::type_name;
+use thiserror::Error;
+use tracing::error;
+
+/// Error extracting an [`Extension<T>`] that no one inserted into the request
+///
+/// Responds with `500 Internal Server Error`, since the request itself is fine.
+#[derive(Debug, Error)]
+#[error("extension of type `{type_name}` is missing, it should be inserted before the handler runs")]
+pub struct MissingExtension {
+    type_name: &'static str,
+}
+
+impl MissingExtension {
+    pub(crate) fn new<T>() -> Self {
+        Self { type_name: type_name::<T>() }
+    }
+
+    /// Returns the name of the missing extension type
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl Responder for MissingExtension {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        error!(cause = %self, "can't extract extension");
+        (StatusCode::INTERNAL_SERVER_ERROR, "missing request extension").response_to(req)
+    }
+}
+
+/// Extracts a clone of the request extension
+impl<T> FromRequest for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Output<'any> = Extension<T>;
+    type Error = MissingExtension;
+
+    async fn from_request(req: &RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'static>, Self::Error> {
+        req.extensions().get::<T>().cloned().map(Extension).ok_or_else(MissingExtension::new::<T>)
+    }
+}
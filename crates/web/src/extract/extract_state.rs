@@ -0,0 +1,84 @@
+//! Shared application state extraction
+//!
+//! This module provides the [`FromRequest`] implementations resolving [`State<T>`] from the
+//! state registered with `ServerBuilder::state`, either by cloning it or by reference.
+//!
+//! # Example
+//! ```no_run
+//! # use micro_web::extract::State;
+//! # use micro_web::router::{Router, get};
+//! # use micro_web::Server;
+//! #[derive(Clone)]
+//! struct AppName(&'static str);
+//!
+//! async fn handler(State(name): State<AppName>) -> String {
+//!     format!("welcome to {}", name.0)
+//! }
+//!
+//! let router = Router::builder().route("/", get(handler)).build();
+//! let server = Server::builder().router(router).state(AppName("micro")).bind("127.0.0.1:3000").build();
+//! ```
+
+use crate::body::OptionReqBody;
+use crate::extract::State;
+use crate::extract::from_request::FromRequest;
+use crate::responder::Responder;
+use crate::{RequestContext, ResponseBody};
+use http::{Response, StatusCode};
+use std::any::type_name;
+use thiserror::Error;
+use tracing::error;
+
+/// Error extracting a [`State<T>`] whose type was never registered with the server
+///
+/// Responds with `500 Internal Server Error`, since the request itself is fine.
+#[derive(Debug, Error)]
+#[error("state of type `{type_name}` is not registered, add it with `ServerBuilder::state`")]
+pub struct MissingState {
+    type_name: &'static str,
+}
+
+impl MissingState {
+    pub(crate) fn new<T>() -> Self {
+        Self { type_name: type_name::<T>() }
+    }
+
+    /// Returns the name of the missing state type
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl Responder for MissingState {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        error!(cause = %self, "can't extract state");
+        (StatusCode::INTERNAL_SERVER_ERROR, "missing application state").response_to(req)
+    }
+}
+
+/// Extracts a clone of the registered state
+impl<T> FromRequest for State<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Output<'any> = State<T>;
+    type Error = MissingState;
+
+    async fn from_request(req: &RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'static>, Self::Error> {
+        req.state_map().get_state::<T>().cloned().ok_or_else(MissingState::new::<T>)
+    }
+}
+
+/// Extracts a reference to the registered state
+impl<T> FromRequest for &State<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Output<'r> = &'r State<T>;
+    type Error = MissingState;
+
+    async fn from_request<'r>(req: &'r RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'r>, Self::Error> {
+        req.state_map().get_state::<T>().ok_or_else(MissingState::new::<T>)
+    }
+}
@@ -2,7 +2,8 @@
 //!
 //! This module provides implementation for extracting typed data from URL query strings.
 //! It allows handlers to receive strongly-typed query parameters by implementing the
-//! `FromRequest` trait for the `Query<T>` type.
+//! `FromRequest` trait for the `Query<T>` type, and strongly-typed path parameters
+//! through the `Path<T>` type.
 //!
 //! # Example
 //! ```no_run
@@ -20,8 +21,9 @@
 //! }
 //! ```
 
-use crate::extract::Query;
 use crate::extract::from_request::FromRequest;
+use crate::extract::path_de::PathDeserializer;
+use crate::extract::{Path, PathError, Query};
 use crate::{OptionReqBody, PathParams, RequestContext};
 use micro_http::protocol::ParseError;
 use serde::Deserialize;
@@ -43,6 +45,23 @@ where
     }
 }
 
+/// Implements typed path parameter extraction for any type that implements Deserialize
+///
+/// The parameters matched by the route are deserialized by name into structs and maps,
+/// by position into tuples, or as the only parameter into primitives.
+impl<T> FromRequest for Path<T>
+where
+    T: for<'de> Deserialize<'de> + Send,
+{
+    type Output<'r> = Path<T>;
+    type Error = PathError;
+
+    async fn from_request<'r>(req: &'r RequestContext<'_, '_>, _body: OptionReqBody) -> Result<Self::Output<'r>, Self::Error> {
+        let params: Vec<(&str, &str)> = req.path_params().iter().collect();
+        T::deserialize(PathDeserializer::new(&params)).map(Path)
+    }
+}
+
 /// Implements path parameter extraction for referenced PathParams
 ///
 /// This implementation is similar to the owned version but works with references
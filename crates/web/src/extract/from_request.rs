@@ -62,8 +62,11 @@ impl Responder for ParseError {
             ParseError::InvalidVersion(_) => (StatusCode::BAD_REQUEST, "invalid version").response_to(req),
             ParseError::InvalidMethod => (StatusCode::BAD_REQUEST, "invalid method").response_to(req),
             ParseError::InvalidUri => (StatusCode::BAD_REQUEST, "invalid uri").response_to(req),
+            ParseError::UriTooLong { .. } => (StatusCode::URI_TOO_LONG, "uri too long").response_to(req),
             ParseError::InvalidContentLength { .. } => (StatusCode::BAD_REQUEST, "invalid content length").response_to(req),
             ParseError::InvalidBody { .. } => (StatusCode::BAD_REQUEST, "invalid body").response_to(req),
+            ParseError::PayloadTooLarge { .. } => (StatusCode::PAYLOAD_TOO_LARGE, "payload too large").response_to(req),
+            ParseError::Timeout { .. } => (StatusCode::REQUEST_TIMEOUT, "request timeout").response_to(req),
             ParseError::Io { .. } => (StatusCode::BAD_REQUEST, "connection error").response_to(req),
         }
     }
@@ -6,6 +6,12 @@
 //! - Form data (`Form<T>`) - For `application/x-www-form-urlencoded` request bodies
 //! - JSON data (`Json<T>`) - For `application/json` request bodies  
 //! - Query parameters (`Query<T>`) - For URL query strings
+//! - Path parameters (`Path<T>`) - For parameters matched by the route
+//! - Shared application state (`State<T>`) - For values registered with the server
+//! - Request extensions (`Extension<T>`) - For values inserted by decorators
+//! - Connection information (`ConnectInfo`, `SocketAddr`) - For the client and peer addresses
+//! - Cookies (`CookieJar`) - For the cookies sent with the request
+//! - Multipart form data (`Multipart`) - For file uploads, streamed part by part
 //! - Headers and other request metadata
 //! - Raw request body as bytes or string
 //!
@@ -62,6 +68,36 @@
 //! }
 //! ```
 //!
+//! ## Path Parameters
+//! ```no_run
+//! # use serde::Deserialize;
+//! # use micro_web::extract::Path;
+//! #[derive(Deserialize)]
+//! struct PostPath {
+//!     user: String,
+//!     post: u32,
+//! }
+//!
+//! // routed as "/users/{user}/posts/{post}"
+//! async fn show_post(Path(path): Path<PostPath>) {
+//!     println!("Showing post {} of {}", path.post, path.user);
+//! }
+//! ```
+//!
+//! ## Shared State
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use micro_web::extract::State;
+//! struct Config {
+//!     greeting: String,
+//! }
+//!
+//! // register with `Server::builder().state(Arc::new(Config { .. }))`
+//! async fn greet(State(config): State<Arc<Config>>) -> String {
+//!     config.greeting.clone()
+//! }
+//! ```
+//!
 //! # Optional Extraction
 //!
 //! All extractors can be made optional by wrapping them in `Option<T>`:
@@ -110,19 +146,33 @@
 //! ```
 
 mod extract_body;
+mod extract_connect_info;
+mod extract_cookie;
+mod extract_extension;
 mod extract_header;
+mod extract_state;
 mod extract_tuple;
 mod extract_url;
 mod from_request;
+mod multipart;
+mod path_de;
 
+pub use extract_extension::MissingExtension;
+pub use extract_state::MissingState;
 pub use from_request::FromRequest;
+pub use multipart::{Field, Multipart, MultipartError, MultipartLimits};
+pub use path_de::PathError;
 use serde::Deserialize;
+use std::ops::Deref;
 
 /// Represented as form data
 ///
 /// when `post` as a `application/x-www-form-urlencoded`, we can use this struct to extract the form data,
 /// note: the struct must impl [`serde::Deserialize`] and [`Send`]
 ///
+/// returned from a handler, a struct that impl [`serde::Serialize`] is sent as form data,
+/// a value that can't be encoded responds with `500 Internal Server Error`
+///
 /// # Example
 /// ```
 /// # use serde::Deserialize;
@@ -139,15 +189,16 @@ use serde::Deserialize;
 /// }
 /// ```
 #[derive(Debug)]
-pub struct Form<T>(pub T)
-where
-    T: for<'de> Deserialize<'de> + Send;
+pub struct Form<T>(pub T);
 
 /// Represented as JSON data
 ///
 /// when `post` as a `application/json`, we can use this struct to extract data,
 /// note: the struct must impl [`serde::Deserialize`] and [`Send`]
 ///
+/// returned from a handler, a struct that impl [`serde::Serialize`] is sent as `application/json`,
+/// see [`responder::json`](crate::responder::json) for pretty printed and streamed JSON
+///
 /// # Example
 /// ```
 /// # use serde::Deserialize;
@@ -164,9 +215,7 @@ where
 /// }
 /// ```
 #[derive(Debug)]
-pub struct Json<T>(pub T)
-where
-    T: for<'de> Deserialize<'de> + Send;
+pub struct Json<T>(pub T);
 
 /// Represented as url query data
 ///
@@ -192,3 +241,101 @@ where
 pub struct Query<T>(pub T)
 where
     T: for<'de> Deserialize<'de> + Send;
+
+/// Represented as typed path parameters
+///
+/// Deserializes the parameters matched by the route, note: the type must impl [`serde::Deserialize`] and [`Send`]
+///
+/// - a struct or map takes the parameters by name
+/// - a tuple takes the parameter values in path order
+/// - a single primitive or string takes the only parameter
+///
+/// Values are percent-decoded first. A value that can't be parsed responds with `400 Bad Request`,
+/// while parameters that don't fit the type at all respond with `500 Internal Server Error`, see [`PathError`].
+///
+/// # Example
+/// ```
+/// # use micro_web::extract::Path;
+/// // routed as "/users/{id}"
+/// pub async fn handle(Path(id): Path<u64>) -> String {
+///     format!("user {id}")
+/// }
+///
+/// // routed as "/users/{user}/posts/{post}"
+/// pub async fn handle_post(Path((user, post)): Path<(String, u32)>) -> String {
+///     format!("post {post} of {user}")
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Path<T>(pub T)
+where
+    T: for<'de> Deserialize<'de> + Send;
+
+/// Represented as shared application state
+///
+/// Values registered with `ServerBuilder::state` are resolved by their type. `State<T>` clones the
+/// registered value, so wrap expensive values in an [`Arc`](std::sync::Arc); `&State<T>` borrows it
+/// instead and needs no [`Clone`].
+///
+/// Handlers are type erased once they are routed, so a value that was never registered can't be
+/// detected when the server is built. Extracting it responds with `500 Internal Server Error`
+/// instead, see [`MissingState`].
+///
+/// # Example
+/// ```
+/// # use micro_web::extract::State;
+/// # #[allow(dead_code)]
+/// #[derive(Clone)]
+/// struct Counter {
+///     start: u64,
+/// }
+///
+/// pub async fn handle(State(counter): State<Counter>) -> String {
+///     format!("counting from {}", counter.start)
+/// }
+///
+/// pub async fn handle_ref(counter: &State<Counter>) -> String {
+///     format!("counting from {}", counter.start)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct State<T>(pub T);
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Represented as a request extension
+///
+/// Clones the value of type `T` from the request extensions, which decorators populate through
+/// [`RequestContext::extensions_mut`](crate::RequestContext::extensions_mut) before invoking the
+/// inner handler. A missing value responds with `500 Internal Server Error`, see [`MissingExtension`];
+/// use `Option<Extension<T>>` when the value is optional.
+///
+/// # Example
+/// ```
+/// # use micro_web::extract::Extension;
+/// # #[allow(dead_code)]
+/// #[derive(Clone)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// pub async fn handle(Extension(user): Extension<User>) -> String {
+///     format!("hello {}", user.name)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Extension<T>(pub T);
+
+impl<T> Deref for Extension<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
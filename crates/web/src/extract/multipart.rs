@@ -0,0 +1,679 @@
+//! Multipart form data extraction
+//!
+//! This module provides the [`Multipart`] extractor for `multipart/form-data` request bodies,
+//! such as file uploads. Parts are streamed out of the request body one at a time, so an upload
+//! is never buffered as a whole:
+//!
+//! - [`Multipart::next_field`] returns the next [`Field`] with its headers, name and file name
+//! - A [`Field`] streams its body in chunks, or collects it with [`Field::bytes`] or [`Field::text`]
+//! - [`Multipart::text_fields`] deserializes the text fields into a struct, skipping the files
+//!
+//! The size of a part and of the whole body are bounded by [`MultipartLimits`], taken from the
+//! state registered with `ServerBuilder::state` or set with [`Multipart::with_limits`]. Request
+//! bodies are unlimited by default, so set these limits for public uploads; the server's request
+//! body limit applies as well when one is set.
+//!
+//! # Example
+//! ```no_run
+//! use micro_web::extract::{Multipart, MultipartError};
+//!
+//! async fn upload(mut multipart: Multipart) -> Result<String, MultipartError> {
+//!     let mut uploaded = vec![];
+//!     while let Some(mut field) = multipart.next_field().await? {
+//!         let name = field.file_name().unwrap_or("unnamed").to_string();
+//!         let mut size = 0;
+//!         while let Some(chunk) = field.chunk().await? {
+//!             size += chunk.len();
+//!         }
+//!         uploaded.push(format!("{name}: {size} bytes"));
+//!     }
+//!     Ok(uploaded.join("\n"))
+//! }
+//! ```
+
+use crate::body::OptionReqBody;
+use crate::extract::from_request::FromRequest;
+use crate::responder::Responder;
+use crate::{RequestContext, ResponseBody};
+use bytes::{Buf, Bytes, BytesMut};
+use futures::Stream;
+use http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use http::{HeaderMap, HeaderName, HeaderValue, Response, StatusCode};
+use http_body::Body;
+use micro_http::protocol::ParseError;
+use micro_http::protocol::body::ReqBody;
+use serde::de::DeserializeOwned;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use thiserror::Error;
+
+/// The most bytes the header block of a part may take
+const MAX_PART_HEADER_SIZE: usize = 8 * 1024;
+
+/// The most headers a part may have
+const MAX_PART_HEADERS: usize = 16;
+
+/// Size limits applied while reading a multipart body, both unlimited by default.
+///
+/// Register them as state to apply them to every [`Multipart`] extractor:
+///
+/// ```no_run
+/// use micro_web::extract::MultipartLimits;
+/// # use micro_web::Server;
+/// # let router = micro_web::router::Router::builder().build();
+///
+/// let limits = MultipartLimits::default().with_max_part_size(Some(10 * 1024 * 1024)).with_max_total_size(Some(50 * 1024 * 1024));
+/// let server = Server::builder().router(router).state(limits).bind("127.0.0.1:3000").build();
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MultipartLimits {
+    max_part_size: Option<u64>,
+    max_total_size: Option<u64>,
+}
+
+impl MultipartLimits {
+    /// Sets the maximum size of the body of a single part.
+    #[must_use]
+    pub fn with_max_part_size(mut self, max_part_size: Option<u64>) -> Self {
+        self.max_part_size = max_part_size;
+        self
+    }
+
+    /// Sets the maximum size of the whole multipart body, including boundaries and part headers.
+    #[must_use]
+    pub fn with_max_total_size(mut self, max_total_size: Option<u64>) -> Self {
+        self.max_total_size = max_total_size;
+        self
+    }
+
+    /// Returns the maximum size of the body of a single part.
+    #[must_use]
+    pub fn max_part_size(&self) -> Option<u64> {
+        self.max_part_size
+    }
+
+    /// Returns the maximum size of the whole multipart body.
+    #[must_use]
+    pub fn max_total_size(&self) -> Option<u64> {
+        self.max_total_size
+    }
+}
+
+/// Error extracting or reading a multipart body
+#[derive(Debug, Error)]
+pub enum MultipartError {
+    /// The request isn't `multipart/form-data`, responds with `415 Unsupported Media Type`
+    #[error("content type is not multipart/form-data")]
+    UnsupportedContentType,
+
+    /// The content type has no valid boundary, responds with `400 Bad Request`
+    #[error("multipart boundary is missing or invalid")]
+    InvalidBoundary,
+
+    /// The body doesn't follow the multipart format, responds with `400 Bad Request`
+    #[error("malformed multipart body: {reason}")]
+    Malformed { reason: &'static str },
+
+    /// A part is larger than [`MultipartLimits::max_part_size`], responds with `413 Content Too Large`
+    #[error("multipart part exceeds {limit} bytes")]
+    PartTooLarge { limit: u64 },
+
+    /// The body is larger than [`MultipartLimits::max_total_size`], responds with `413 Content Too Large`
+    #[error("multipart body exceeds {limit} bytes")]
+    BodyTooLarge { limit: u64 },
+
+    /// A text field isn't valid UTF-8, responds with `400 Bad Request`
+    #[error("field `{name}` is not valid utf-8")]
+    InvalidText { name: String },
+
+    /// The text fields can't be deserialized, responds with `400 Bad Request`
+    #[error("can't deserialize text fields: {reason}")]
+    Deserialize { reason: String },
+
+    /// Reading the request body failed, responds like the [`ParseError`]
+    #[error(transparent)]
+    Body(#[from] ParseError),
+}
+
+impl Responder for MultipartError {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        match self {
+            MultipartError::UnsupportedContentType => (StatusCode::UNSUPPORTED_MEDIA_TYPE, "expected multipart/form-data").response_to(req),
+            MultipartError::PartTooLarge { .. } | MultipartError::BodyTooLarge { .. } => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "payload too large").response_to(req)
+            }
+            MultipartError::Body(e) => e.response_to(req),
+            e @ (MultipartError::InvalidBoundary
+            | MultipartError::Malformed { .. }
+            | MultipartError::InvalidText { .. }
+            | MultipartError::Deserialize { .. }) => (StatusCode::BAD_REQUEST, e.to_string()).response_to(req),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Skipping the preamble before the first boundary
+    Preamble,
+    /// Right after a boundary, either the next part or the end follows
+    Boundary,
+    /// Reading the header block of a part
+    Headers,
+    /// Reading the body of a part
+    Body,
+    /// The final boundary was read, or reading failed
+    Done,
+}
+
+/// A `multipart/form-data` request body, read part by part.
+///
+/// See the [module documentation](self) for an example.
+#[derive(Debug)]
+pub struct Multipart {
+    body: ReqBody,
+    body_finished: bool,
+    /// `--boundary`, opening the first part
+    dash_boundary: Bytes,
+    /// `\r\n--boundary`, ending the body of a part
+    delimiter: Bytes,
+    buffer: BytesMut,
+    state: State,
+    limits: MultipartLimits,
+    part_size: u64,
+    total_size: u64,
+}
+
+impl Multipart {
+    fn new(body: ReqBody, boundary: &str, limits: MultipartLimits) -> Self {
+        let dash_boundary = Bytes::from(format!("--{boundary}"));
+        let delimiter = Bytes::from(format!("\r\n--{boundary}"));
+        Self {
+            body,
+            body_finished: false,
+            dash_boundary,
+            delimiter,
+            buffer: BytesMut::new(),
+            state: State::Preamble,
+            limits,
+            part_size: 0,
+            total_size: 0,
+        }
+    }
+
+    /// Replaces the size limits, which default to the [`MultipartLimits`] registered as state.
+    #[must_use]
+    pub fn with_limits(mut self, limits: MultipartLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the next field, skipping what's left of the previous one, or `None` after the last field.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body is malformed, exceeds a limit, or can't be read.
+    pub async fn next_field(&mut self) -> Result<Option<Field<'_>>, MultipartError> {
+        let Some(headers) = poll_fn(|cx| self.poll_next_headers(cx)).await? else {
+            return Ok(None);
+        };
+
+        let disposition = headers.get(CONTENT_DISPOSITION).and_then(|value| value.to_str().ok()).map(ContentDisposition::parse);
+        let Some(disposition) = disposition.filter(|disposition| disposition.form_data) else {
+            self.state = State::Done;
+            return Err(MultipartError::Malformed { reason: "part without form-data content disposition" });
+        };
+
+        self.part_size = 0;
+        Ok(Some(Field { multipart: self, headers, name: disposition.name, file_name: disposition.file_name }))
+    }
+
+    /// Deserializes the text fields into `T`, skipping the fields that carry a file name.
+    ///
+    /// Fields are decoded like an `application/x-www-form-urlencoded` body, so `T` can hold
+    /// numbers, booleans and optional values as well as strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body can't be read, a text field isn't UTF-8, or `T` can't be
+    /// deserialized from the fields.
+    pub async fn text_fields<T: DeserializeOwned>(mut self) -> Result<T, MultipartError> {
+        let mut fields = vec![];
+        while let Some(field) = self.next_field().await? {
+            if field.file_name().is_some() {
+                continue;
+            }
+            let name = field.name().unwrap_or_default().to_string();
+            fields.push((name, field.text().await?));
+        }
+
+        let encoded = serde_urlencoded::to_string(&fields).map_err(|e| MultipartError::Deserialize { reason: e.to_string() })?;
+        serde_urlencoded::from_str(&encoded).map_err(|e| MultipartError::Deserialize { reason: e.to_string() })
+    }
+
+    fn poll_next_headers(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<HeaderMap>, MultipartError>> {
+        let result = ready!(self.poll_next_headers_inner(cx));
+        if result.is_err() {
+            self.state = State::Done;
+        }
+        Poll::Ready(result)
+    }
+
+    fn poll_next_headers_inner(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<HeaderMap>, MultipartError>> {
+        loop {
+            match self.state {
+                State::Preamble => {
+                    if let Some(index) = find(&self.buffer, &self.dash_boundary) {
+                        self.buffer.advance(index + self.dash_boundary.len());
+                        self.state = State::Boundary;
+                        continue;
+                    }
+                    // keep what may be the start of the boundary
+                    let skipped = self.buffer.len().saturating_sub(self.dash_boundary.len() - 1);
+                    self.buffer.advance(skipped);
+                }
+                State::Body => {
+                    // skip what the handler didn't read of the previous field
+                    ready!(self.poll_chunk_inner(cx))?;
+                    continue;
+                }
+                State::Boundary if self.buffer.len() >= 2 => {
+                    if self.buffer.starts_with(b"--") {
+                        self.state = State::Done;
+                        self.buffer.clear();
+                    } else if self.buffer.starts_with(b"\r\n") {
+                        self.buffer.advance(2);
+                        self.state = State::Headers;
+                    } else {
+                        return Poll::Ready(Err(MultipartError::Malformed { reason: "invalid boundary" }));
+                    }
+                    continue;
+                }
+                State::Boundary => {}
+                State::Headers => {
+                    if let Some(headers) = self.parse_headers()? {
+                        self.state = State::Body;
+                        return Poll::Ready(Ok(Some(headers)));
+                    }
+                    if self.buffer.len() > MAX_PART_HEADER_SIZE {
+                        return Poll::Ready(Err(MultipartError::Malformed { reason: "part headers too large" }));
+                    }
+                }
+                State::Done => return Poll::Ready(Ok(None)),
+            }
+
+            if !ready!(self.poll_fill(cx))? {
+                return Poll::Ready(Err(MultipartError::Malformed { reason: "unexpected end of body" }));
+            }
+        }
+    }
+
+    /// Parses the header block of a part once it's complete in the buffer
+    fn parse_headers(&mut self) -> Result<Option<HeaderMap>, MultipartError> {
+        if self.buffer.starts_with(b"\r\n") {
+            self.buffer.advance(2);
+            return Ok(Some(HeaderMap::new()));
+        }
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_PART_HEADERS];
+        let (len, headers) = match httparse::parse_headers(&self.buffer, &mut headers) {
+            Ok(httparse::Status::Complete((len, headers))) => (len, headers),
+            Ok(httparse::Status::Partial) => return Ok(None),
+            Err(_) => return Err(MultipartError::Malformed { reason: "invalid part headers" }),
+        };
+
+        let mut header_map = HeaderMap::with_capacity(headers.len());
+        for header in headers {
+            let name = HeaderName::from_bytes(header.name.as_bytes());
+            let value = HeaderValue::from_bytes(header.value);
+            let (Ok(name), Ok(value)) = (name, value) else {
+                return Err(MultipartError::Malformed { reason: "invalid part headers" });
+            };
+            header_map.append(name, value);
+        }
+        self.buffer.advance(len);
+        Ok(Some(header_map))
+    }
+
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Bytes>, MultipartError>> {
+        let result = ready!(self.poll_chunk_inner(cx));
+        if result.is_err() {
+            self.state = State::Done;
+        }
+        Poll::Ready(result)
+    }
+
+    /// Returns the next chunk of the current part's body, or `None` at its end
+    fn poll_chunk_inner(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Bytes>, MultipartError>> {
+        while self.state == State::Body {
+            let chunk = if let Some(index) = find(&self.buffer, &self.delimiter) {
+                let chunk = self.buffer.split_to(index).freeze();
+                self.buffer.advance(self.delimiter.len());
+                self.state = State::Boundary;
+                chunk
+            } else {
+                // keep what may be the start of the delimiter
+                let len = self.buffer.len().saturating_sub(self.delimiter.len() - 1);
+                self.buffer.split_to(len).freeze()
+            };
+
+            if !chunk.is_empty() {
+                self.part_size += chunk.len() as u64;
+                if let Some(limit) = self.limits.max_part_size.filter(|limit| self.part_size > *limit) {
+                    return Poll::Ready(Err(MultipartError::PartTooLarge { limit }));
+                }
+                return Poll::Ready(Ok(Some(chunk)));
+            }
+
+            if self.state == State::Body && !ready!(self.poll_fill(cx))? {
+                return Poll::Ready(Err(MultipartError::Malformed { reason: "unexpected end of body" }));
+            }
+        }
+        Poll::Ready(Ok(None))
+    }
+
+    /// Reads the next frame of the request body into the buffer, returns `false` at the end of the body
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool, MultipartError>> {
+        loop {
+            if self.body_finished {
+                return Poll::Ready(Ok(false));
+            }
+
+            let Some(frame) = ready!(Pin::new(&mut self.body).poll_frame(cx)) else {
+                self.body_finished = true;
+                return Poll::Ready(Ok(false));
+            };
+
+            // trailers carry no data, keep reading
+            let Ok(data) = frame?.into_data() else {
+                continue;
+            };
+            self.total_size += data.len() as u64;
+            if let Some(limit) = self.limits.max_total_size.filter(|limit| self.total_size > *limit) {
+                return Poll::Ready(Err(MultipartError::BodyTooLarge { limit }));
+            }
+            self.buffer.extend_from_slice(&data);
+            return Poll::Ready(Ok(true));
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// A field of a multipart body, whose body is streamed from the request.
+///
+/// Reading the field borrows the [`Multipart`], so fields are read one after the other.
+#[derive(Debug)]
+pub struct Field<'m> {
+    multipart: &'m mut Multipart,
+    headers: HeaderMap,
+    name: Option<String>,
+    file_name: Option<String>,
+}
+
+impl Field<'_> {
+    /// Returns the headers of the part.
+    #[must_use]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the name of the field from its `Content-Disposition`.
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the file name from its `Content-Disposition`, present when the field is a file.
+    #[must_use]
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Returns the content type of the part.
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok())
+    }
+
+    /// Returns the next chunk of the body, or `None` at its end.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body is malformed, exceeds a limit, or can't be read.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        poll_fn(|cx| self.multipart.poll_chunk(cx)).await
+    }
+
+    /// Collects the whole body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body is malformed, exceeds a limit, or can't be read.
+    pub async fn bytes(mut self) -> Result<Bytes, MultipartError> {
+        let mut bytes = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes.freeze())
+    }
+
+    /// Collects the whole body as text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body can't be read or isn't valid UTF-8.
+    pub async fn text(self) -> Result<String, MultipartError> {
+        let name = self.name.clone().unwrap_or_default();
+        let bytes = self.bytes().await?;
+        String::from_utf8(bytes.into()).map_err(|_utf8_error| MultipartError::InvalidText { name })
+    }
+}
+
+impl Stream for Field<'_> {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().multipart.poll_chunk(cx).map(Result::transpose)
+    }
+}
+
+/// The parameters of a `Content-Disposition` header
+#[derive(Debug, Default)]
+struct ContentDisposition {
+    form_data: bool,
+    name: Option<String>,
+    file_name: Option<String>,
+}
+
+impl ContentDisposition {
+    /// Parses a value such as `form-data; name="file"; filename="a.txt"`
+    fn parse(value: &str) -> Self {
+        let (disposition_type, mut params) = value.split_once(';').unwrap_or((value, ""));
+        let mut disposition = Self { form_data: disposition_type.trim().eq_ignore_ascii_case("form-data"), ..Self::default() };
+
+        while let Some((name, rest)) = params.split_once('=') {
+            let (value, rest) = parse_param_value(rest.trim_start());
+            match name.trim().to_ascii_lowercase().as_str() {
+                "name" => disposition.name = Some(value),
+                "filename" => disposition.file_name = Some(value),
+                _ => {}
+            }
+            params = rest.split_once(';').map_or("", |(_, rest)| rest);
+        }
+        disposition
+    }
+}
+
+/// Parses a token or quoted string, returning it with what follows it
+fn parse_param_value(s: &str) -> (String, &str) {
+    let Some(quoted) = s.strip_prefix('"') else {
+        let end = s.find(';').unwrap_or(s.len());
+        return (s[..end].trim().to_string(), &s[end..]);
+    };
+
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return (value, &quoted[index + 1..]),
+            '\\' => value.extend(chars.next().map(|(_, escaped)| escaped)),
+            c => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+/// Extracts the multipart body, the body isn't read until fields are requested
+impl FromRequest for Multipart {
+    type Output<'any> = Multipart;
+    type Error = MultipartError;
+
+    async fn from_request(req: &RequestContext<'_, '_>, body: OptionReqBody) -> Result<Self::Output<'static>, Self::Error> {
+        let mime = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<mime::Mime>().ok())
+            .filter(|mime| mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA)
+            .ok_or(MultipartError::UnsupportedContentType)?;
+
+        let boundary = mime.get_param(mime::BOUNDARY).map(|boundary| boundary.as_str()).unwrap_or_default();
+        if boundary.is_empty() || boundary.len() > 70 {
+            return Err(MultipartError::InvalidBoundary);
+        }
+
+        let limits = req.state_map().get::<MultipartLimits>().copied().unwrap_or_default();
+        let body = body.apply(|body| async { Ok(body) }).await?;
+        Ok(Multipart::new(body, boundary, limits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Server;
+    use crate::router::{Router, post};
+    use crate::testing::TestClient;
+    use serde::Deserialize;
+
+    const MULTIPART_CONTENT_TYPE: &str = "multipart/form-data; boundary=XyZ";
+
+    const BODY: &str = "preamble\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        holiday\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"photo\"; filename=\"beach \\\"1\\\".jpg\"\r\n\
+        Content-Type: image/jpeg\r\n\
+        \r\n\
+        \x00\x01\r\n--Xy\x02\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"count\"\r\n\
+        \r\n\
+        3\r\n\
+        --XyZ--\r\n\
+        epilogue";
+
+    async fn describe(mut multipart: Multipart) -> Result<String, MultipartError> {
+        let mut described = vec![];
+        while let Some(field) = multipart.next_field().await? {
+            let name = field.name().unwrap_or_default().to_string();
+            let file_name = field.file_name().map(str::to_string);
+            let content_type = field.content_type().map(str::to_string);
+            let body = field.bytes().await?;
+            described.push(format!("{name} {file_name:?} {content_type:?} {body:?}"));
+        }
+        Ok(described.join("\n"))
+    }
+
+    #[derive(Deserialize)]
+    struct Album {
+        title: String,
+        count: u32,
+    }
+
+    async fn album(multipart: Multipart) -> Result<String, MultipartError> {
+        let album = multipart.text_fields::<Album>().await?;
+        Ok(format!("{} x{}", album.title, album.count))
+    }
+
+    async fn first_field(mut multipart: Multipart) -> Result<String, MultipartError> {
+        let field = multipart.next_field().await?.unwrap();
+        field.text().await
+    }
+
+    fn client(limits: MultipartLimits) -> TestClient {
+        let router =
+            Router::builder().route("/describe", post(describe)).route("/album", post(album)).route("/first", post(first_field)).build();
+        let server = Server::builder().router(router).state(limits).bind("127.0.0.1:0").build().unwrap();
+        TestClient::new(server)
+    }
+
+    #[test]
+    fn parse_content_disposition() {
+        let disposition = ContentDisposition::parse(r#"form-data; name="file"; filename="a;b \"c\".txt""#);
+        assert!(disposition.form_data);
+        assert_eq!(disposition.name.as_deref(), Some("file"));
+        assert_eq!(disposition.file_name.as_deref(), Some(r#"a;b "c".txt"#));
+
+        let disposition = ContentDisposition::parse("attachment; NAME=plain");
+        assert!(!disposition.form_data);
+        assert_eq!(disposition.name.as_deref(), Some("plain"));
+    }
+
+    #[tokio::test]
+    async fn stream_fields() {
+        let response =
+            client(MultipartLimits::default()).post("/describe").header("content-type", MULTIPART_CONTENT_TYPE).body(BODY).send().await;
+
+        response.assert_status(StatusCode::OK).assert_body(
+            "title None None b\"holiday\"\n\
+            photo Some(\"beach \\\"1\\\".jpg\") Some(\"image/jpeg\") b\"\\0\\x01\\r\\n--Xy\\x02\"\n\
+            count None None b\"3\"",
+        );
+    }
+
+    #[tokio::test]
+    async fn deserialize_text_fields() {
+        let response =
+            client(MultipartLimits::default()).post("/album").header("content-type", MULTIPART_CONTENT_TYPE).body(BODY).send().await;
+        response.assert_status(StatusCode::OK).assert_body("holiday x3");
+    }
+
+    #[tokio::test]
+    async fn unread_fields_are_skipped() {
+        let response =
+            client(MultipartLimits::default()).post("/first").header("content-type", MULTIPART_CONTENT_TYPE).body(BODY).send().await;
+        response.assert_status(StatusCode::OK).assert_body("holiday");
+    }
+
+    #[tokio::test]
+    async fn limits_are_enforced() {
+        let client = client(MultipartLimits::default().with_max_part_size(Some(4)));
+        let response = client.post("/describe").header("content-type", MULTIPART_CONTENT_TYPE).body(BODY).send().await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        let client = self::client(MultipartLimits::default().with_max_total_size(Some(64)));
+        let response = client.post("/describe").header("content-type", MULTIPART_CONTENT_TYPE).body(BODY).send().await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn invalid_requests() {
+        let client = client(MultipartLimits::default());
+
+        let response = client.post("/describe").header("content-type", "text/plain").body(BODY).send().await;
+        response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let response = client.post("/describe").header("content-type", "multipart/form-data").body(BODY).send().await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let truncated = &BODY[..BODY.find("--XyZ--").unwrap()];
+        let response = client.post("/describe").header("content-type", MULTIPART_CONTENT_TYPE).body(truncated).send().await;
+        response.assert_status(StatusCode::BAD_REQUEST).assert_body("malformed multipart body: unexpected end of body");
+    }
+}
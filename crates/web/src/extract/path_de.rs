@@ -0,0 +1,362 @@
+//! Serde deserializer for path parameters
+//!
+//! [`PathDeserializer`] turns the `(name, value)` pairs matched by the router into the type
+//! requested by [`Path<T>`](crate::extract::Path):
+//!
+//! - structs and maps take the parameters by name
+//! - tuples and sequences take the parameter values in path order
+//! - a single primitive or string takes the only parameter
+//!
+//! Values are percent-decoded before they are parsed.
+
+use crate::RequestContext;
+use crate::body::ResponseBody;
+use crate::responder::Responder;
+use http::{Response, StatusCode};
+use percent_encoding::percent_decode_str;
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use std::borrow::Cow;
+use std::fmt::Display;
+use thiserror::Error;
+use tracing::error;
+
+/// Error extracting a [`Path<T>`](crate::extract::Path)
+#[derive(Debug, Error)]
+pub enum PathError {
+    /// A parameter value can't be parsed into the requested type, answered with `400 Bad Request`
+    #[error("invalid path parameter `{name}`: {reason}")]
+    InvalidParam { name: String, reason: String },
+
+    /// The route's parameters don't fit the requested type, answered with `500 Internal Server Error`
+    #[error("path parameters don't match: {reason}")]
+    Mismatch { reason: String },
+}
+
+impl PathError {
+    fn invalid_param<R: Display>(name: &str, reason: R) -> Self {
+        Self::InvalidParam { name: name.to_string(), reason: reason.to_string() }
+    }
+
+    /// Reports an error raised while deserializing a parameter value against that parameter
+    fn for_param(self, name: &str) -> Self {
+        match self {
+            PathError::Mismatch { reason } => PathError::invalid_param(name, reason),
+            e @ PathError::InvalidParam { .. } => e,
+        }
+    }
+}
+
+impl de::Error for PathError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Mismatch { reason: msg.to_string() }
+    }
+}
+
+impl Responder for PathError {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        match self {
+            PathError::InvalidParam { .. } => (StatusCode::BAD_REQUEST, self.to_string()).response_to(req),
+            PathError::Mismatch { .. } => {
+                error!(cause = %self, path = req.uri().path(), "can't extract path parameters");
+                (StatusCode::INTERNAL_SERVER_ERROR, "path parameters don't match the handler").response_to(req)
+            }
+        }
+    }
+}
+
+/// Deserializes the whole set of path parameters
+pub(crate) struct PathDeserializer<'de> {
+    params: &'de [(&'de str, &'de str)],
+}
+
+impl<'de> PathDeserializer<'de> {
+    pub(crate) fn new(params: &'de [(&'de str, &'de str)]) -> Self {
+        Self { params }
+    }
+
+    fn single_value(&self) -> Result<ValueDeserializer<'de>, PathError> {
+        match self.params {
+            [(name, value)] => ValueDeserializer::new(name, value),
+            _ => Err(PathError::Mismatch { reason: format!("expected 1 parameter, the route has {}", self.params.len()) }),
+        }
+    }
+}
+
+macro_rules! forward_to_single_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                let value = self.single_value()?;
+                let name = value.name;
+                value.$method(visitor).map_err(|e| e.for_param(name))
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for PathDeserializer<'de> {
+    type Error = PathError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    forward_to_single_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(ParamsAccess { params: self.params, value: None })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        if len != self.params.len() {
+            return Err(PathError::Mismatch { reason: format!("expected {len} parameters, the route has {}", self.params.len()) });
+        }
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(ParamsAccess { params: self.params, value: None })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = self.single_value()?;
+        let param = value.name;
+        value.deserialize_enum(name, variants, visitor).map_err(|e| e.for_param(param))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// Walks the parameters, by name for maps and by value for sequences
+struct ParamsAccess<'de> {
+    params: &'de [(&'de str, &'de str)],
+    value: Option<(&'de str, &'de str)>,
+}
+
+impl<'de> MapAccess<'de> for ParamsAccess<'de> {
+    type Error = PathError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        let Some((param, rest)) = self.params.split_first() else {
+            return Ok(None);
+        };
+        self.params = rest;
+        self.value = Some(*param);
+        seed.deserialize(BorrowedStrDeserializer::new(param.0)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        let (name, value) = self.value.take().ok_or_else(|| de::Error::custom("value is missing"))?;
+        ValueDeserializer::new(name, value)?.deserialize_with(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.params.len())
+    }
+}
+
+impl<'de> SeqAccess<'de> for ParamsAccess<'de> {
+    type Error = PathError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error> {
+        let Some(((name, value), rest)) = self.params.split_first() else {
+            return Ok(None);
+        };
+        self.params = rest;
+        ValueDeserializer::new(name, value)?.deserialize_with(seed).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.params.len())
+    }
+}
+
+/// Deserializes the value of a single parameter
+struct ValueDeserializer<'de> {
+    name: &'de str,
+    value: Cow<'de, str>,
+}
+
+impl<'de> ValueDeserializer<'de> {
+    fn new(name: &'de str, value: &'de str) -> Result<Self, PathError> {
+        let value = percent_decode_str(value).decode_utf8().map_err(|e| PathError::invalid_param(name, e))?;
+        Ok(Self { name, value })
+    }
+
+    fn deserialize_with<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, PathError> {
+        let name = self.name;
+        seed.deserialize(self).map_err(|e| e.for_param(name))
+    }
+
+    fn parse<T>(&self) -> Result<T, PathError>
+    where
+        T: std::str::FromStr,
+        T::Err: Display,
+    {
+        self.value.parse().map_err(|e| PathError::invalid_param(self.name, e))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = PathError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+            Cow::Owned(value) => visitor.visit_string(value),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Cow::Borrowed(value) => BorrowedStrDeserializer::new(value).deserialize_enum(name, variants, visitor),
+            Cow::Owned(value) => value.into_deserializer().deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    fn from_params<T: for<'de> Deserialize<'de>>(params: &[(&str, &str)]) -> Result<T, PathError> {
+        T::deserialize(PathDeserializer::new(params))
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct UserPost {
+        user: String,
+        post: u32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        Draft,
+        Published,
+    }
+
+    #[test]
+    fn struct_by_name() {
+        let post: UserPost = from_params(&[("post", "7"), ("user", "alice")]).unwrap();
+        assert_eq!(post, UserPost { user: "alice".to_string(), post: 7 });
+    }
+
+    #[test]
+    fn tuple_by_position() {
+        let (user, post): (String, u32) = from_params(&[("user", "alice"), ("post", "7")]).unwrap();
+        assert_eq!(user, "alice");
+        assert_eq!(post, 7);
+    }
+
+    #[test]
+    fn single_primitive() {
+        assert_eq!(from_params::<u64>(&[("id", "42")]).unwrap(), 42);
+        assert_eq!(from_params::<String>(&[("name", "hello%20world")]).unwrap(), "hello world");
+        assert_eq!(from_params::<Kind>(&[("kind", "draft")]).unwrap(), Kind::Draft);
+    }
+
+    #[test]
+    fn invalid_value() {
+        let err = from_params::<UserPost>(&[("user", "alice"), ("post", "seven")]).unwrap_err();
+        assert!(matches!(err, PathError::InvalidParam { ref name, .. } if name == "post"));
+
+        let err = from_params::<Kind>(&[("kind", "deleted")]).unwrap_err();
+        assert!(matches!(err, PathError::InvalidParam { ref name, .. } if name == "kind"));
+    }
+
+    #[test]
+    fn mismatched_params() {
+        assert!(matches!(from_params::<u64>(&[("a", "1"), ("b", "2")]).unwrap_err(), PathError::Mismatch { .. }));
+        assert!(matches!(from_params::<(u64, u64)>(&[("a", "1")]).unwrap_err(), PathError::Mismatch { .. }));
+        assert!(matches!(from_params::<UserPost>(&[("user", "alice")]).unwrap_err(), PathError::Mismatch { .. }));
+    }
+}
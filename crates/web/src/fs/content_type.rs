@@ -0,0 +1,64 @@
+//! Guesses the `Content-Type` of a file from its extension.
+
+use mime::Mime;
+use std::path::Path;
+
+/// Returns the media type for the extension of `path`, `application/octet-stream` when unknown.
+///
+/// Text types carry a `utf-8` charset.
+pub(crate) fn guess(path: &Path) -> Mime {
+    let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+        return mime::APPLICATION_OCTET_STREAM;
+    };
+
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => mime::TEXT_HTML_UTF_8,
+        "css" => mime::TEXT_CSS_UTF_8,
+        "js" | "mjs" => mime::TEXT_JAVASCRIPT,
+        "json" | "map" => mime::APPLICATION_JSON,
+        "txt" => mime::TEXT_PLAIN_UTF_8,
+        "csv" => mime::TEXT_CSV_UTF_8,
+        "xml" => mime::TEXT_XML,
+        "pdf" => mime::APPLICATION_PDF,
+        "png" => mime::IMAGE_PNG,
+        "jpg" | "jpeg" => mime::IMAGE_JPEG,
+        "gif" => mime::IMAGE_GIF,
+        "bmp" => mime::IMAGE_BMP,
+        "svg" => mime::IMAGE_SVG,
+        "woff" => mime::FONT_WOFF,
+        "woff2" => mime::FONT_WOFF2,
+        "md" => parse("text/markdown; charset=utf-8"),
+        "webp" => parse("image/webp"),
+        "avif" => parse("image/avif"),
+        "ico" => parse("image/x-icon"),
+        "ttf" => parse("font/ttf"),
+        "otf" => parse("font/otf"),
+        "wasm" => parse("application/wasm"),
+        "webmanifest" => parse("application/manifest+json"),
+        "zip" => parse("application/zip"),
+        "mp3" => parse("audio/mpeg"),
+        "ogg" => parse("audio/ogg"),
+        "wav" => parse("audio/wav"),
+        "mp4" => parse("video/mp4"),
+        "webm" => parse("video/webm"),
+        _ => mime::APPLICATION_OCTET_STREAM,
+    }
+}
+
+fn parse(media_type: &'static str) -> Mime {
+    media_type.parse().expect("static media types are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess() {
+        assert_eq!(guess(Path::new("index.html")), mime::TEXT_HTML_UTF_8);
+        assert_eq!(guess(Path::new("assets/app.JS")), mime::TEXT_JAVASCRIPT);
+        assert_eq!(guess(Path::new("image.webp")).essence_str(), "image/webp");
+        assert_eq!(guess(Path::new("archive.tar.unknown")), mime::APPLICATION_OCTET_STREAM);
+        assert_eq!(guess(Path::new("Makefile")), mime::APPLICATION_OCTET_STREAM);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::range::Part;
+use bytes::{Bytes, BytesMut};
+use http_body::{Body, Frame, SizeHint};
+use micro_http::protocol::{HttpError, SendError};
+use std::collections::VecDeque;
+use std::io;
+use std::io::SeekFrom;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeek, Take};
+use tokio_util::io::poll_read_buf;
+
+/// A body streaming `len` bytes of a file from its current position.
+///
+/// The exact size is known upfront so the response is sent with a `Content-Length`, a file
+/// that shrinks while it's sent fails the body instead of sending fewer bytes.
+#[derive(Debug)]
+pub(crate) struct FileBody {
+    reader: Take<File>,
+    buf: BytesMut,
+    remaining: u64,
+}
+
+/// The size of the chunks read from the file
+const CHUNK_SIZE: usize = 4096;
+
+impl FileBody {
+    pub(crate) fn new(file: File, len: u64) -> Self {
+        Self { reader: file.take(len), buf: BytesMut::new(), remaining: len }
+    }
+
+    /// Returns the file, positioned after the bytes read so far
+    fn into_file(self) -> File {
+        self.reader.into_inner()
+    }
+}
+
+impl Body for FileBody {
+    type Data = Bytes;
+    type Error = HttpError;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        if this.buf.capacity() == 0 {
+            this.buf.reserve(CHUNK_SIZE);
+        }
+        match ready!(poll_read_buf(Pin::new(&mut this.reader), cx, &mut this.buf)) {
+            Ok(0) if this.remaining > 0 => {
+                let e = io::Error::new(io::ErrorKind::UnexpectedEof, "file is shorter than its announced length");
+                this.remaining = 0;
+                Poll::Ready(Some(Err(SendError::io(e).into())))
+            }
+            Ok(0) => Poll::Ready(None),
+            Ok(_) => {
+                let bytes = this.buf.split().freeze();
+                this.remaining = this.remaining.saturating_sub(bytes.len() as u64);
+                Poll::Ready(Some(Ok(Frame::data(bytes))))
+            }
+            Err(e) => Poll::Ready(Some(Err(SendError::io(e).into()))),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+/// A body sending ranges of a file, each after its part header, and then a trailer.
+///
+/// The file is seeked to the start of every range, so only the bytes of the ranges are read
+/// however far apart they are.
+#[derive(Debug)]
+pub(crate) struct FileRangesBody {
+    /// The file between two ranges, `None` while a range is read through `range`
+    file: Option<File>,
+    /// The range being read
+    range: Option<FileBody>,
+    /// Whether the seek to the start of the next range has been started
+    seeking: bool,
+    parts: VecDeque<Part>,
+    trailer: Bytes,
+    remaining: u64,
+}
+
+impl FileRangesBody {
+    pub(crate) fn new(file: File, parts: Vec<Part>, trailer: Bytes) -> Self {
+        let remaining = parts.iter().map(|part| part.header.len() as u64 + part.range.len()).sum::<u64>() + trailer.len() as u64;
+        Self { file: Some(file), range: None, seeking: false, parts: parts.into(), trailer, remaining }
+    }
+
+    fn frame(&mut self, bytes: Bytes) -> Poll<Option<Result<Frame<Bytes>, HttpError>>> {
+        self.remaining = self.remaining.saturating_sub(bytes.len() as u64);
+        Poll::Ready(Some(Ok(Frame::data(bytes))))
+    }
+
+    /// Ends the body with `e`
+    fn fail(&mut self, e: HttpError) -> Poll<Option<Result<Frame<Bytes>, HttpError>>> {
+        self.range = None;
+        self.parts.clear();
+        self.trailer.clear();
+        self.remaining = 0;
+        Poll::Ready(Some(Err(e)))
+    }
+}
+
+impl Body for FileRangesBody {
+    type Data = Bytes;
+    type Error = HttpError;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        loop {
+            if let Some(range) = &mut this.range {
+                match ready!(Pin::new(range).poll_frame(cx)) {
+                    Some(Ok(frame)) => {
+                        let Ok(data) = frame.into_data() else { continue };
+                        return this.frame(data);
+                    }
+                    Some(Err(e)) => return this.fail(e),
+                    None => {
+                        this.file = this.range.take().map(FileBody::into_file);
+                        this.parts.pop_front();
+                        continue;
+                    }
+                }
+            }
+
+            let Some(part) = this.parts.front_mut() else {
+                let trailer = std::mem::take(&mut this.trailer);
+                return if trailer.is_empty() { Poll::Ready(None) } else { this.frame(trailer) };
+            };
+            if !part.header.is_empty() {
+                let header = std::mem::take(&mut part.header);
+                return this.frame(header);
+            }
+
+            let (start, len) = (part.range.start, part.range.len());
+            let file = this.file.as_mut().expect("the file is only taken while a range is read");
+            if !this.seeking {
+                if let Err(e) = Pin::new(&mut *file).start_seek(SeekFrom::Start(start)) {
+                    return this.fail(SendError::io(e).into());
+                }
+                this.seeking = true;
+            }
+            let seeked = ready!(Pin::new(&mut *file).poll_complete(cx));
+            this.seeking = false;
+            if let Err(e) = seeked {
+                return this.fail(SendError::io(e).into());
+            }
+
+            this.range = this.file.take().map(|file| FileBody::new(file, len));
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
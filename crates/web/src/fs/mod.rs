@@ -0,0 +1,272 @@
+//! Static file serving
+//!
+//! This module provides handlers serving files from the file system:
+//!
+//! - [`ServeDir`]: Serves the files of a directory, mounted on a wildcard route such as `/{*path}`
+//! - [`ServeFile`]: Serves a single file, whatever the request path is
+//!
+//! Files are streamed with an exact `Content-Length` and a `Content-Type` guessed from their
+//! extension. Responses carry `ETag` and `Last-Modified` validators, so conditional requests with
+//! `If-None-Match` or `If-Modified-Since` are answered with `304 Not Modified`. Range requests are
+//! answered with `206 Partial Content`, as described in the [`range`](crate::range) module.
+//!
+//! When enabled, a precompressed `.br` or `.gz` sibling of the file is served instead, with the
+//! matching `Content-Encoding`, if the client accepts that encoding.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use micro_web::fs::{ServeDir, ServeFile};
+//! use micro_web::router::{inner_get, Router};
+//!
+//! let router = Router::builder()
+//!     .route("/favicon.ico", inner_get(ServeFile::new("public/favicon.ico")))
+//!     .route("/assets/{*path}", inner_get(ServeDir::new("public/assets").with_precompressed_gzip()))
+//!     // a single page application, unknown paths are answered with its index
+//!     .route("/", inner_get(ServeDir::new("dist").with_fallback("dist/index.html")))
+//!     .route("/{*path}", inner_get(ServeDir::new("dist").with_fallback("dist/index.html")))
+//!     .build();
+//! ```
+
+mod content_type;
+mod file_body;
+mod serve_dir;
+mod serve_file;
+
+pub use serve_dir::ServeDir;
+pub use serve_file::ServeFile;
+
+use crate::date::{format_http_date, parse_http_date};
+use crate::range::{self, RangeOutcome};
+use crate::responder::{NotFound, Responder};
+use crate::{RequestContext, ResponseBody};
+use file_body::{FileBody, FileRangesBody};
+use http::header::{
+    ACCEPT_ENCODING, ACCEPT_RANGES, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    VARY,
+};
+use http::{HeaderValue, Response, StatusCode};
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tracing::error;
+
+/// The precompressed siblings a file may be served from
+#[derive(Debug, Clone, Copy, Default)]
+struct Precompressed {
+    br: bool,
+    gzip: bool,
+}
+
+impl Precompressed {
+    fn is_enabled(self) -> bool {
+        self.br || self.gzip
+    }
+
+    /// Returns the `(encoding, extension)` of the enabled siblings accepted by the client, in
+    /// order of preference
+    fn accepted_by(self, req: &RequestContext) -> Vec<(&'static str, &'static str)> {
+        let Some(accept_encoding) = req.headers().get(ACCEPT_ENCODING).and_then(|value| value.to_str().ok()) else {
+            return Vec::new();
+        };
+
+        [(self.br, "br", ".br"), (self.gzip, "gzip", ".gz")]
+            .into_iter()
+            .filter(|&(enabled, encoding, _)| enabled && accepts_encoding(accept_encoding, encoding))
+            .map(|(_, encoding, extension)| (encoding, extension))
+            .collect()
+    }
+}
+
+/// Checks whether an `Accept-Encoding` value accepts `encoding` with a non-zero quality
+fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    let mut wildcard = false;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let accepted = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .is_none_or(|quality| quality.trim().parse::<f32>().is_ok_and(|quality| quality > 0.0));
+
+        if name.eq_ignore_ascii_case(encoding) {
+            return accepted;
+        }
+        if name == "*" {
+            wildcard = accepted;
+        }
+    }
+    wildcard
+}
+
+/// Responds with the file at `path`, or fails with `NotFound` if it isn't a regular file.
+async fn serve_file(req: &RequestContext<'_, '_>, path: &Path, precompressed: Precompressed) -> io::Result<Response<ResponseBody>> {
+    let (mut file, mut metadata) = open_file(path).await?;
+
+    let mut content_encoding = None;
+    for (encoding, extension) in precompressed.accepted_by(req) {
+        let mut sibling = path.as_os_str().to_owned();
+        sibling.push(extension);
+        if let Ok(opened) = open_file(Path::new(&sibling)).await {
+            (file, metadata) = opened;
+            content_encoding = Some(encoding);
+            break;
+        }
+    }
+
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = modified.map(|modified| entity_tag(len, modified, content_encoding));
+    let modified = modified.map(truncate_to_secs);
+
+    let mut builder = Response::builder();
+    let headers = builder.headers_mut().unwrap();
+    headers.reserve(16);
+    if let Some(etag) = &etag {
+        headers.insert(ETAG, HeaderValue::from_str(etag).unwrap());
+    }
+    if let Some(modified) = modified {
+        headers.insert(LAST_MODIFIED, HeaderValue::from_str(&format_http_date(modified)).unwrap());
+    }
+    if precompressed.is_enabled() {
+        headers.insert(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+
+    if is_not_modified(req, etag.as_deref(), modified) {
+        return Ok(builder.status(StatusCode::NOT_MODIFIED).body(ResponseBody::empty()).unwrap());
+    }
+
+    let headers = builder.headers_mut().unwrap();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type::guess(path).as_ref()).unwrap());
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+    headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Some(encoding) = content_encoding {
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    }
+
+    match range::evaluate(req, builder.headers_ref().unwrap(), len) {
+        RangeOutcome::Full => Ok(builder.status(StatusCode::OK).body(ResponseBody::stream(FileBody::new(file, len))).unwrap()),
+        RangeOutcome::Partial(ranges) => {
+            // every range is read on its own, the bytes between them are never read
+            let response = builder.status(StatusCode::OK).body(ResponseBody::empty()).unwrap();
+            Ok(range::partial_content(response, &ranges, len, |_, parts, trailer| FileRangesBody::new(file, parts, trailer)))
+        }
+        RangeOutcome::Unsatisfiable => Ok(range::range_not_satisfiable(len)),
+    }
+}
+
+/// Opens `path` if it's a regular file
+async fn open_file(path: &Path) -> io::Result<(File, Metadata)> {
+    let file = File::open(path).await?;
+    let metadata = file.metadata().await?;
+    if !metadata.is_file() {
+        return Err(io::ErrorKind::NotFound.into());
+    }
+    Ok((file, metadata))
+}
+
+/// Answers the error of serving a file, a missing file is a 404
+fn error_response(req: &RequestContext, path: &Path, e: &io::Error) -> Response<ResponseBody> {
+    match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::NotADirectory => NotFound.response_to(req),
+        io::ErrorKind::PermissionDenied => (StatusCode::FORBIDDEN, "403 Forbidden").response_to(req),
+        _ => {
+            error!(cause = %e, path = %path.display(), "can't serve file");
+            (StatusCode::INTERNAL_SERVER_ERROR, "failed to read file").response_to(req)
+        }
+    }
+}
+
+/// HTTP dates have a resolution of one second
+fn truncate_to_secs(time: SystemTime) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())
+}
+
+/// A strong entity tag derived from the length and the modification time of a file, a
+/// precompressed sibling is a different representation and gets a different tag.
+fn entity_tag(len: u64, modified: SystemTime, content_encoding: Option<&str>) -> String {
+    let nanos = modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    match content_encoding {
+        Some(encoding) => format!("\"{len:x}-{nanos:x}-{encoding}\""),
+        None => format!("\"{len:x}-{nanos:x}\""),
+    }
+}
+
+/// Evaluates `If-None-Match`, or `If-Modified-Since` when it's absent, as in RFC 9110 Section 13.2.2
+fn is_not_modified(req: &RequestContext, etag: Option<&str>, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = req.headers().get(IF_NONE_MATCH) {
+        let Ok(if_none_match) = if_none_match.to_str() else {
+            return false;
+        };
+        if if_none_match.trim() == "*" {
+            return true;
+        }
+        return etag.is_some_and(|etag| if_none_match.split(',').any(|tag| weak_eq(tag.trim(), etag)));
+    }
+
+    let if_modified_since = req.headers().get(IF_MODIFIED_SINCE).and_then(|value| value.to_str().ok()).and_then(parse_http_date);
+    match (if_modified_since, modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+/// The weak comparison of entity tags, ignoring the `W/` prefix
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A directory below the system temporary directory, removed when dropped
+    #[derive(Debug)]
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let name = format!("micro-web-fs-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed));
+            let path = std::env::temp_dir().join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+
+        pub(crate) fn write(&self, name: &str, contents: impl AsRef<[u8]>) {
+            let path = self.0.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_accepts_encoding() {
+        assert!(accepts_encoding("gzip, deflate, br", "br"));
+        assert!(accepts_encoding("GZIP;q=0.5", "gzip"));
+        assert!(!accepts_encoding("gzip;q=0, br", "gzip"));
+        assert!(!accepts_encoding("deflate", "gzip"));
+        assert!(accepts_encoding("*", "br"));
+        assert!(!accepts_encoding("*, br;q=0", "br"));
+    }
+
+    #[test]
+    fn test_weak_eq() {
+        assert!(weak_eq("W/\"1-2\"", "\"1-2\""));
+        assert!(weak_eq("\"1-2\"", "\"1-2\""));
+        assert!(!weak_eq("\"1-2\"", "\"1-3\""));
+    }
+}
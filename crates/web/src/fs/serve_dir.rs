@@ -0,0 +1,279 @@
+use crate::fs::{Precompressed, error_response, serve_file};
+use crate::handler::RequestHandler;
+use crate::responder::{NotFound, Responder};
+use crate::{OptionReqBody, RequestContext, ResponseBody};
+use async_trait::async_trait;
+use http::header::LOCATION;
+use http::{HeaderValue, Response, StatusCode};
+use percent_encoding::percent_decode_str;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// A handler serving the files of a directory.
+///
+/// The file path is read from the wildcard path parameter named `path`, e.g. `/static/{*path}`,
+/// which can be changed with [`ServeDir::with_path_param`]. A request without that parameter
+/// serves the directory itself, so the same handler can also be mounted on the route of the
+/// directory, e.g. `/static/`.
+///
+/// - Paths escaping the directory, with `..` segments or absolute components, are answered with
+///   `404 Not Found`
+/// - A directory is served through its index file, `index.html` by default, and a request for it
+///   without a trailing slash is redirected to the path with the slash, so relative links resolve
+/// - Missing files are answered with `404 Not Found`, or with the fallback file when set, which
+///   lets a single page application route unknown paths on the client
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    path_param: String,
+    index_file: Option<String>,
+    fallback: Option<PathBuf>,
+    precompressed: Precompressed,
+}
+
+impl ServeDir {
+    /// Creates a handler serving the files below `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            path_param: "path".to_string(),
+            index_file: Some("index.html".to_string()),
+            fallback: None,
+            precompressed: Precompressed::default(),
+        }
+    }
+
+    /// Reads the file path from the wildcard path parameter named `name`, `path` by default
+    #[must_use]
+    pub fn with_path_param(mut self, name: impl Into<String>) -> Self {
+        self.path_param = name.into();
+        self
+    }
+
+    /// Serves directories through the file `name` they contain, `index.html` by default
+    #[must_use]
+    pub fn with_index_file(mut self, name: impl Into<String>) -> Self {
+        self.index_file = Some(name.into());
+        self
+    }
+
+    /// Answers requests for directories with `404 Not Found`
+    #[must_use]
+    pub fn without_index_file(mut self) -> Self {
+        self.index_file = None;
+        self
+    }
+
+    /// Serves the file at `path` with `200 OK` instead of answering `404 Not Found`
+    ///
+    /// The path isn't relative to the served directory, e.g. `dist/index.html` for a single page
+    /// application in `dist`.
+    #[must_use]
+    pub fn with_fallback(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fallback = Some(path.into());
+        self
+    }
+
+    /// Serves the `.gz` sibling of a file, if it exists, to clients accepting `gzip`
+    #[must_use]
+    pub fn with_precompressed_gzip(mut self) -> Self {
+        self.precompressed.gzip = true;
+        self
+    }
+
+    /// Serves the `.br` sibling of a file, if it exists, to clients accepting `br`
+    ///
+    /// It's preferred over the `.gz` sibling when both are enabled.
+    #[must_use]
+    pub fn with_precompressed_br(mut self) -> Self {
+        self.precompressed.br = true;
+        self
+    }
+
+    async fn serve(&self, req: &RequestContext<'_, '_>) -> Response<ResponseBody> {
+        let param = req.path_params().get(&self.path_param).unwrap_or_default();
+        let Some(relative) = decode_path(param) else {
+            return NotFound.response_to(req);
+        };
+
+        let mut path = self.root.join(relative);
+        if tokio::fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_dir()) {
+            match &self.index_file {
+                Some(_) if !req.uri().path().ends_with('/') => return redirect_to_dir(req),
+                Some(index_file) => path.push(index_file),
+                None => return self.serve_fallback(req, &path, &io::ErrorKind::NotFound.into()).await,
+            }
+        }
+
+        match serve_file(req, &path, self.precompressed).await {
+            Ok(response) => response,
+            Err(e) => self.serve_fallback(req, &path, &e).await,
+        }
+    }
+
+    /// Answers a file that couldn't be served
+    async fn serve_fallback(&self, req: &RequestContext<'_, '_>, path: &Path, e: &io::Error) -> Response<ResponseBody> {
+        let is_missing = matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory);
+        let Some(fallback) = self.fallback.as_deref().filter(|_| is_missing) else {
+            return error_response(req, path, e);
+        };
+
+        match serve_file(req, fallback, self.precompressed).await {
+            Ok(response) => response,
+            Err(e) => error_response(req, fallback, &e),
+        }
+    }
+}
+
+#[async_trait]
+impl RequestHandler for ServeDir {
+    async fn invoke<'server, 'req>(&self, req: &mut RequestContext<'server, 'req>, _req_body: OptionReqBody) -> Response<ResponseBody> {
+        self.serve(req).await
+    }
+}
+
+/// Decodes a request path into a path relative to the served directory, `None` if it would
+/// escape it.
+fn decode_path(path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode_str(path).decode_utf8().ok()?;
+
+    let mut relative = PathBuf::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            // a segment must be a single plain component on every platform, e.g. not `a\..` or `C:`
+            _ if segment.contains(['\\', '\0']) => return None,
+            _ => {
+                let mut components = Path::new(segment).components();
+                let (Some(Component::Normal(component)), None) = (components.next(), components.next()) else {
+                    return None;
+                };
+                relative.push(component);
+            }
+        }
+    }
+    Some(relative)
+}
+
+/// Redirects a request for a directory to the same path with a trailing slash
+fn redirect_to_dir(req: &RequestContext) -> Response<ResponseBody> {
+    let location = match req.uri().query() {
+        Some(query) => format!("{}/?{query}", req.uri().path()),
+        None => format!("{}/", req.uri().path()),
+    };
+
+    let mut response = Response::new(ResponseBody::empty());
+    *response.status_mut() = StatusCode::TEMPORARY_REDIRECT;
+    response.headers_mut().insert(LOCATION, HeaderValue::try_from(location).unwrap());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::TempDir;
+    use crate::router::{Router, inner_get};
+    use crate::testing::TestClient;
+
+    fn client(serve_dir: ServeDir) -> TestClient {
+        let router =
+            Router::builder().route("/static/", inner_get(serve_dir.clone())).route("/static/{*path}", inner_get(serve_dir)).build();
+        TestClient::new(router)
+    }
+
+    #[test]
+    fn test_decode_path() {
+        assert_eq!(decode_path("css/app.css"), Some(PathBuf::from("css/app.css")));
+        assert_eq!(decode_path("./a//b%20c.txt"), Some(PathBuf::from("a/b c.txt")));
+        assert_eq!(decode_path(""), Some(PathBuf::new()));
+
+        assert_eq!(decode_path("../secret"), None);
+        assert_eq!(decode_path("a/%2e%2e/%2e%2e/secret"), None);
+        assert_eq!(decode_path("%2Fetc/passwd"), Some(PathBuf::from("etc/passwd")));
+        assert_eq!(decode_path("a%5C..%5Csecret"), None);
+        assert_eq!(decode_path("a%00"), None);
+    }
+
+    #[tokio::test]
+    async fn serves_files_and_index() {
+        let dir = TempDir::new();
+        dir.write("index.html", "<h1>home</h1>");
+        dir.write("css/app.css", "body {}");
+        dir.write("docs/index.html", "<h1>docs</h1>");
+        let client = client(ServeDir::new(dir.path()));
+
+        let response = client.get("/static/css/app.css").send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-type", "text/css; charset=utf-8").assert_body("body {}");
+        response.assert_header("content-length", "7");
+
+        client.get("/static/").send().await.assert_status(StatusCode::OK).assert_body("<h1>home</h1>");
+        client.get("/static/docs/").send().await.assert_status(StatusCode::OK).assert_body("<h1>docs</h1>");
+        client
+            .get("/static/docs?v=1")
+            .send()
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT)
+            .assert_header("location", "/static/docs/?v=1");
+
+        client.get("/static/missing.css").send().await.assert_status(StatusCode::NOT_FOUND);
+        client.get("/static/css/app.css/x").send().await.assert_status(StatusCode::NOT_FOUND);
+        client.get("/static/css").send().await.assert_status(StatusCode::TEMPORARY_REDIRECT);
+
+        let client = self::client(ServeDir::new(dir.path()).without_index_file());
+        client.get("/static/docs/").send().await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn streams_large_files() {
+        // larger than the buffer of the in-memory stream of the test client
+        let contents: Vec<u8> = (0..1024 * 1024).map(|i: u32| (i % 251) as u8).collect();
+        let dir = TempDir::new();
+        dir.write("large.bin", &contents);
+        let client = client(ServeDir::new(dir.path()));
+
+        let response = client.get("/static/large.bin").send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-length", contents.len().to_string());
+        assert!(response.body() == contents.as_slice());
+
+        let response = client.get("/static/large.bin").header("range", "bytes=100000-899999").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT);
+        assert!(response.body() == &contents[100_000..900_000]);
+
+        // far apart ranges, each one larger than a read chunk
+        let response = client.get("/static/large.bin").header("range", "bytes=10-9999,-10000").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT);
+        let content_length: usize = response.header("content-length").unwrap().to_str().unwrap().parse().unwrap();
+        assert_eq!(content_length, response.body().len());
+        let first = response.body().windows(9990).position(|part| part == &contents[10..10_000]);
+        let last = response.body().windows(10_000).position(|part| part == &contents[contents.len() - 10_000..]);
+        assert!(first.is_some_and(|first| last.is_some_and(|last| first < last)));
+        assert!(response.body().len() < 30_000);
+    }
+
+    #[tokio::test]
+    async fn rejects_path_traversal() {
+        let dir = TempDir::new();
+        dir.write("secret.txt", "secret");
+        dir.write("public/file.txt", "public");
+        let client = client(ServeDir::new(dir.path().join("public")));
+
+        client.get("/static/file.txt").send().await.assert_status(StatusCode::OK);
+        client.get("/static/../secret.txt").send().await.assert_status(StatusCode::NOT_FOUND);
+        client.get("/static/%2e%2e/secret.txt").send().await.assert_status(StatusCode::NOT_FOUND);
+        client.get("/static/%2E%2E%2Fsecret.txt").send().await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn falls_back_for_single_page_applications() {
+        let dir = TempDir::new();
+        dir.write("index.html", "app");
+        dir.write("app.js", "js");
+        let client = client(ServeDir::new(dir.path()).with_fallback(dir.path().join("index.html")));
+
+        client.get("/static/app.js").send().await.assert_status(StatusCode::OK).assert_body("js");
+        let response = client.get("/static/users/42").send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-type", "text/html; charset=utf-8").assert_body("app");
+        client.get("/static/../app.js").send().await.assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::fs::{Precompressed, error_response, serve_file};
+use crate::handler::RequestHandler;
+use crate::{OptionReqBody, RequestContext, ResponseBody};
+use async_trait::async_trait;
+use http::Response;
+use std::path::PathBuf;
+
+/// A handler serving a single file, whatever the request path is.
+///
+/// A missing file is answered with `404 Not Found`.
+#[derive(Debug, Clone)]
+pub struct ServeFile {
+    path: PathBuf,
+    precompressed: Precompressed,
+}
+
+impl ServeFile {
+    /// Creates a handler serving the file at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), precompressed: Precompressed::default() }
+    }
+
+    /// Serves the `.gz` sibling of the file, if it exists, to clients accepting `gzip`
+    #[must_use]
+    pub fn with_precompressed_gzip(mut self) -> Self {
+        self.precompressed.gzip = true;
+        self
+    }
+
+    /// Serves the `.br` sibling of the file, if it exists, to clients accepting `br`
+    ///
+    /// It's preferred over the `.gz` sibling when both are enabled.
+    #[must_use]
+    pub fn with_precompressed_br(mut self) -> Self {
+        self.precompressed.br = true;
+        self
+    }
+}
+
+#[async_trait]
+impl RequestHandler for ServeFile {
+    async fn invoke<'server, 'req>(&self, req: &mut RequestContext<'server, 'req>, _req_body: OptionReqBody) -> Response<ResponseBody> {
+        match serve_file(req, &self.path, self.precompressed).await {
+            Ok(response) => response,
+            Err(e) => error_response(req, &self.path, &e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::encoder::EncodeDecorator;
+    use crate::fs::tests::TempDir;
+    use crate::router::{Router, inner_get};
+    use crate::testing::TestClient;
+    use http::StatusCode;
+
+    fn client(serve_file: ServeFile) -> TestClient {
+        TestClient::new(Router::builder().route("/file", inner_get(serve_file)).build())
+    }
+
+    #[tokio::test]
+    async fn serves_file_with_validators() {
+        let dir = TempDir::new();
+        dir.write("data.json", r#"{"a":1}"#);
+        let client = client(ServeFile::new(dir.path().join("data.json")));
+
+        let response = client.get("/file").send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-type", "application/json").assert_body(r#"{"a":1}"#);
+        assert!(!response.headers().contains_key("vary"));
+        let etag = response.header("etag").unwrap().to_str().unwrap().to_string();
+        let last_modified = response.header("last-modified").unwrap().to_str().unwrap().to_string();
+
+        let response = client.head("/file").send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-length", "7").assert_header("etag", &etag).assert_body("");
+
+        let response = client.get("/file").header("if-none-match", format!("\"other\", W/{etag}")).send().await;
+        response.assert_status(StatusCode::NOT_MODIFIED).assert_header("etag", &etag).assert_body("");
+        client.get("/file").header("if-none-match", "\"other\"").send().await.assert_status(StatusCode::OK);
+
+        let response = client.get("/file").header("if-modified-since", &last_modified).send().await;
+        response.assert_status(StatusCode::NOT_MODIFIED);
+        let response = client.get("/file").header("if-modified-since", "Thu, 01 Jan 1970 00:00:00 GMT").send().await;
+        response.assert_status(StatusCode::OK);
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let response = client.get("/file").header("if-none-match", "\"other\"").header("if-modified-since", &last_modified).send().await;
+        response.assert_status(StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn serves_precompressed_siblings() {
+        let dir = TempDir::new();
+        dir.write("app.js", "plain");
+        dir.write("app.js.gz", "gzipped");
+        dir.write("app.js.br", "brotli");
+        let client = client(ServeFile::new(dir.path().join("app.js")).with_precompressed_gzip().with_precompressed_br());
+
+        let response = client.get("/file").header("accept-encoding", "gzip, br").send().await;
+        response.assert_header("content-encoding", "br").assert_header("content-type", "text/javascript").assert_body("brotli");
+        response.assert_header("vary", "accept-encoding");
+        let br_etag = response.header("etag").unwrap().clone();
+
+        let response = client.get("/file").header("accept-encoding", "gzip, br;q=0").send().await;
+        response.assert_header("content-encoding", "gzip").assert_body("gzipped");
+        assert_ne!(response.header("etag"), Some(&br_etag));
+
+        let response = client.get("/file").send().await;
+        response.assert_header("vary", "accept-encoding").assert_body("plain");
+        assert!(!response.headers().contains_key("content-encoding"));
+    }
+
+    #[tokio::test]
+    async fn answers_range_requests() {
+        let dir = TempDir::new();
+        dir.write("video.mp4", "0123456789");
+        let client = client(ServeFile::new(dir.path().join("video.mp4")));
+
+        let response = client.get("/file").send().await;
+        response.assert_status(StatusCode::OK).assert_header("accept-ranges", "bytes");
+        let etag = response.header("etag").unwrap().to_str().unwrap().to_string();
+
+        let response = client.get("/file").header("range", "bytes=4-").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT).assert_header("content-range", "bytes 4-9/10").assert_body("456789");
+        response.assert_header("content-type", "video/mp4").assert_header("etag", &etag);
+
+        let response = client.get("/file").header("range", "bytes=8-,2-3").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT);
+        let body = response.text();
+        assert!(body.contains("Content-Type: video/mp4\r\nContent-Range: bytes 2-3/10\r\n\r\n23\r\n"), "{body}");
+        assert!(body.contains("Content-Range: bytes 8-9/10\r\n\r\n89\r\n"), "{body}");
+
+        client.get("/file").header("range", "bytes=10-").send().await.assert_status(StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let response = client.get("/file").header("range", "bytes=0-1").header("if-range", &etag).send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT).assert_body("01");
+        let response = client.get("/file").header("range", "bytes=0-1").header("if-range", "\"stale\"").send().await;
+        response.assert_status(StatusCode::OK).assert_body("0123456789");
+    }
+
+    #[tokio::test]
+    async fn encoded_file_is_not_resumed_with_ranges() {
+        let contents = "0123456789".repeat(400);
+        let dir = TempDir::new();
+        dir.write("app.js", &contents);
+        let router =
+            Router::builder().route("/file", inner_get(ServeFile::new(dir.path().join("app.js")))).with_global_decorator(EncodeDecorator);
+        let client = TestClient::new(router.build());
+
+        let response = client.get("/file").header("accept-encoding", "gzip").send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-encoding", "gzip").assert_header("vary", "accept-encoding");
+        assert!(!response.headers().contains_key("accept-ranges"));
+        let etag = response.header("etag").unwrap().to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/"), "{etag}");
+
+        // resuming the gzipped download gets the whole encoded body again, not identity bytes
+        let response =
+            client.get("/file").header("accept-encoding", "gzip").header("range", "bytes=100-").header("if-range", &etag).send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-encoding", "gzip");
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&**response.body()), &mut text).unwrap();
+        assert_eq!(text, contents);
+
+        // the weak entity tag still validates the cached encoded body
+        let response = client.get("/file").header("accept-encoding", "gzip").header("if-none-match", &etag).send().await;
+        response.assert_status(StatusCode::NOT_MODIFIED);
+
+        let response = client.get("/file").header("range", "bytes=100-").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT).assert_header("content-length", "3900").assert_body(&contents[100..]);
+    }
+
+    #[tokio::test]
+    async fn missing_file_is_not_found() {
+        let dir = TempDir::new();
+        dir.write("app.js.gz", "gzipped");
+
+        let client = client(ServeFile::new(dir.path().join("app.js")).with_precompressed_gzip());
+        client.get("/file").header("accept-encoding", "gzip").send().await.assert_status(StatusCode::NOT_FOUND);
+
+        let client = self::client(ServeFile::new(dir.path()));
+        client.get("/file").send().await.assert_status(StatusCode::NOT_FOUND);
+    }
+}
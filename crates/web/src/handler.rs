@@ -20,6 +20,7 @@ use std::marker::PhantomData;
 
 pub mod handler_decorator;
 pub mod handler_decorator_factory;
+pub mod middleware;
 
 /// Trait for types that can handle HTTP requests.
 ///
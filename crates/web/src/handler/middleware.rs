@@ -0,0 +1,195 @@
+//! Middleware written as closures.
+//!
+//! [`middleware_fn`] turns a closure into a decorator that runs around the handlers it decorates.
+//! The closure receives the request context, the request body and a [`Next`] to call the decorated
+//! handler, so it can:
+//! - Inspect or modify the request before calling `next`
+//! - Short-circuit by returning any [`Responder`] without calling `next`
+//! - Post-process the `Response<ResponseBody>` returned by `next`
+//!
+//! The closure must return its future boxed, wrapping the `async` block in `Box::pin`, since the
+//! future borrows the request context. See [`middleware_fn`] for why a plain `async` block isn't
+//! accepted.
+//!
+//! # Example
+//!
+//! ```
+//! use http::StatusCode;
+//! use micro_web::router::{get, Router};
+//! use micro_web::middleware_fn;
+//!
+//! async fn hello() -> &'static str {
+//!     "hello"
+//! }
+//!
+//! let auth = middleware_fn(|req, body, next| {
+//!     Box::pin(async move {
+//!         if req.headers().get("x-api-key").is_none_or(|key| key != "secret") {
+//!             return Err((StatusCode::UNAUTHORIZED, "unauthorized"));
+//!         }
+//!         let mut response = next.run(req, body).await;
+//!         response.headers_mut().insert("x-authenticated", "true".parse().unwrap());
+//!         Ok(response)
+//!     })
+//! });
+//!
+//! let router = Router::builder()
+//!     .route("/", get(hello))
+//!     .route("/admin", get(hello).decorate(auth.clone()))
+//!     .with_global_decorator(auth)
+//!     .build();
+//! ```
+
+use crate::handler::RequestHandler;
+use crate::handler::handler_decorator::HandlerDecorator;
+use crate::handler::handler_decorator_factory::HandlerDecoratorFactory;
+use crate::responder::Responder;
+use crate::{OptionReqBody, RequestContext, ResponseBody};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use http::Response;
+use std::any::type_name;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// The rest of the handler chain, called by a middleware to continue handling the request
+pub struct Next<'a> {
+    handler: &'a dyn RequestHandler,
+}
+
+impl Next<'_> {
+    /// Calls the decorated handler and returns its response
+    pub async fn run(self, req: &mut RequestContext<'_, '_>, req_body: OptionReqBody) -> Response<ResponseBody> {
+        self.handler.invoke(req, req_body).await
+    }
+}
+
+impl Debug for Next<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Next").finish_non_exhaustive()
+    }
+}
+
+/// A decorator running a closure around the decorated handlers, created by [`middleware_fn`]
+///
+/// It works both as a [`HandlerDecorator`] for a single route and as a [`HandlerDecoratorFactory`]
+/// for global decorators, cloning it only clones a reference to the closure.
+pub struct MiddlewareFn<F, R> {
+    f: Arc<F>,
+    _phantom: PhantomData<fn() -> R>,
+}
+
+impl<F, R> Clone for MiddlewareFn<F, R> {
+    fn clone(&self) -> Self {
+        Self { f: Arc::clone(&self.f), _phantom: PhantomData }
+    }
+}
+
+impl<F, R> Debug for MiddlewareFn<F, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "middleware_fn: {}", type_name::<F>())
+    }
+}
+
+/// Creates a middleware from a closure, see the [module documentation](self) for an example.
+///
+/// The closure is called with the request context, the request body and the [`Next`] handler,
+/// and must return `Box::pin(async move { .. })`, a [`BoxFuture`] resolving to any [`Responder`]:
+///
+/// ```
+/// use micro_web::middleware_fn;
+///
+/// let trace = middleware_fn(|req, body, next| {
+///     Box::pin(async move {
+///         let path = req.uri().path().to_string();
+///         let response = next.run(req, body).await;
+///         tracing::info!(path, status = %response.status(), "request handled");
+///         response
+///     })
+/// });
+/// ```
+///
+/// # Why `Box::pin`
+///
+/// The future borrows the request context for `'a`, the lifetime of the closure's arguments, and a
+/// generic future type can't depend on that lifetime. The closure is therefore bound to return a
+/// `BoxFuture<'a, R>`, and a closure returning a plain `async` block fails to compile:
+///
+/// ```compile_fail
+/// use micro_web::middleware_fn;
+///
+/// let trace = middleware_fn(|req, body, next| async move {
+///     next.run(req, body).await
+/// });
+/// ```
+///
+/// Async closures, `async |req, body, next| { .. }`, don't work either: their futures can't be
+/// required to be `Send` on stable Rust, which every handler future must be.
+pub fn middleware_fn<F, R>(f: F) -> MiddlewareFn<F, R>
+where
+    F: for<'a, 'server, 'req> Fn(&'a mut RequestContext<'server, 'req>, OptionReqBody, Next<'a>) -> BoxFuture<'a, R> + Send + Sync,
+    R: Responder,
+{
+    MiddlewareFn { f: Arc::new(f), _phantom: PhantomData }
+}
+
+/// A handler decorated by a [`MiddlewareFn`]
+pub struct MiddlewareHandler<H, F, R> {
+    handler: H,
+    middleware: MiddlewareFn<F, R>,
+}
+
+impl<H: Debug, F, R> Debug for MiddlewareHandler<H, F, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MiddlewareHandler").field("handler", &self.handler).field("middleware", &self.middleware).finish()
+    }
+}
+
+#[async_trait]
+impl<H, F, R> RequestHandler for MiddlewareHandler<H, F, R>
+where
+    H: RequestHandler,
+    F: for<'a, 'server, 'req> Fn(&'a mut RequestContext<'server, 'req>, OptionReqBody, Next<'a>) -> BoxFuture<'a, R> + Send + Sync,
+    R: Responder,
+{
+    async fn invoke<'server, 'req>(&self, req: &mut RequestContext<'server, 'req>, req_body: OptionReqBody) -> Response<ResponseBody> {
+        let next = Next { handler: &self.handler };
+        let responder = (self.middleware.f)(req, req_body, next).await;
+        responder.response_to(req)
+    }
+}
+
+impl<H, F, R> HandlerDecorator<H> for MiddlewareFn<F, R>
+where
+    H: RequestHandler,
+    F: for<'a, 'server, 'req> Fn(&'a mut RequestContext<'server, 'req>, OptionReqBody, Next<'a>) -> BoxFuture<'a, R> + Send + Sync,
+    R: Responder,
+{
+    type Output = MiddlewareHandler<H, F, R>;
+
+    fn decorate(&self, handler: H) -> Self::Output {
+        MiddlewareHandler { handler, middleware: self.clone() }
+    }
+}
+
+impl<F, R> HandlerDecoratorFactory for MiddlewareFn<F, R>
+where
+    F: for<'a, 'server, 'req> Fn(&'a mut RequestContext<'server, 'req>, OptionReqBody, Next<'a>) -> BoxFuture<'a, R>
+        + Send
+        + Sync
+        + 'static,
+    R: Responder + 'static,
+{
+    type Output<In>
+        = MiddlewareFn<F, R>
+    where
+        In: RequestHandler;
+
+    fn create_decorator<In>(&self) -> Self::Output<In>
+    where
+        In: RequestHandler,
+    {
+        self.clone()
+    }
+}
@@ -55,6 +55,18 @@
 //!   - Cross-cutting concerns
 //!   - Built-in middleware components
 //!
+//! - **Static Files** ([`fs`])
+//!   - Directory and single file handlers
+//!   - Conditional requests and precompressed files
+//!
+//! - **Range Requests** ([`range`])
+//!   - Partial content for bodies of known length
+//!   - Single ranges and `multipart/byteranges`
+//!
+//! - **Testing** ([`testing`])
+//!   - In-process test client driving requests through the whole stack
+//!   - Assertion helpers on the collected response
+//!
 //! # Example
 //!
 //! ```no_run
@@ -98,6 +110,8 @@
 
 // Internal modules
 mod body;
+mod connect_info;
+mod cookie;
 mod fn_trait;
 mod handler;
 mod request;
@@ -107,15 +121,39 @@ mod server;
 pub mod date;
 pub mod encoding;
 pub mod extract;
+pub mod fs;
+pub mod range;
 pub mod responder;
 pub mod router;
+pub mod testing;
 
 // Public re-exports
 pub use body::OptionReqBody;
 pub use body::ResponseBody;
+pub use connect_info::ConnectInfo;
+pub use connect_info::InvalidCidr;
+pub use connect_info::IpCidr;
+pub use cookie::Cookie;
+pub use cookie::CookieJar;
+#[cfg(feature = "cookie-crypto")]
+pub use cookie::Key;
+#[cfg(feature = "cookie-crypto")]
+pub use cookie::KeyTooShort;
+#[cfg(feature = "cookie-crypto")]
+pub use cookie::PrivateCookieJar;
+pub use cookie::SameSite;
+#[cfg(feature = "cookie-crypto")]
+pub use cookie::SignedCookieJar;
 pub use fn_trait::FnTrait;
 pub use handler::FnHandler;
 pub use handler::handler_fn;
+pub use handler::middleware::MiddlewareFn;
+pub use handler::middleware::Next;
+pub use handler::middleware::middleware_fn;
 pub use request::PathParams;
 pub use request::RequestContext;
+pub use request::StateMap;
 pub use server::Server;
+pub use server::ServerBuildError;
+pub use server::ShutdownHandle;
+pub use server::ShutdownSummary;
@@ -0,0 +1,418 @@
+//! HTTP range requests
+//!
+//! This module answers `GET` requests carrying a `Range` header with `206 Partial Content`, as in
+//! RFC 9110 Section 14, for any response whose body has a known length:
+//!
+//! - [`Ranged<T>`]: A responder applying the requested ranges to the response of `T`
+//! - [`RangeDecorator`]: A decorator applying the requested ranges to the responses of handlers
+//!
+//! Responses with a known length advertise `Accept-Ranges: bytes`. A single range is answered with
+//! a `Content-Range` header, several ranges with a `multipart/byteranges` body. Overlapping or
+//! adjacent ranges are coalesced and the parts are sent in ascending order. A request without any
+//! satisfiable range is answered with `416 Range Not Satisfiable`.
+//!
+//! An `If-Range` header is honored against the `ETag` or the `Last-Modified` header of the
+//! response: when it doesn't match, the whole representation is sent. Invalid `Range` headers are
+//! ignored.
+//!
+//! [`ServeDir`](crate::fs::ServeDir) and [`ServeFile`](crate::fs::ServeFile) support range requests
+//! on their own, seeking to every range in the file instead of reading the bytes around them.
+//!
+//! # Example
+//!
+//! ```
+//! use bytes::Bytes;
+//! use micro_web::range::{RangeDecorator, Ranged};
+//! use micro_web::router::{get, Router};
+//!
+//! async fn video() -> Ranged<Bytes> {
+//!     Ranged(Bytes::from_static(b"not really a video"))
+//! }
+//!
+//! async fn report() -> String {
+//!     "a long report".to_string()
+//! }
+//!
+//! let router = Router::builder()
+//!     .route("/video", get(video))
+//!     .route("/report", get(report).decorate(RangeDecorator))
+//!     .build();
+//! ```
+
+mod range_body;
+mod range_decorator;
+
+pub use range_decorator::{RangeDecorator, RangeHandler};
+
+use crate::date::parse_http_date;
+use crate::responder::Responder;
+use crate::{RequestContext, ResponseBody};
+use bytes::Bytes;
+use http::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use http::{HeaderMap, HeaderValue, Method, Response, StatusCode};
+use http_body::Body;
+use micro_http::protocol::HttpError;
+use range_body::RangeBody;
+use std::hash::{BuildHasher, RandomState};
+
+pub(crate) use range_body::Part;
+
+/// Requests with more ranges are answered with the whole representation
+const MAX_RANGES: usize = 64;
+
+const BYTES: HeaderValue = HeaderValue::from_static("bytes");
+
+/// Responds with the ranges requested from the response of `T`.
+///
+/// The ranges apply only when `T` answers `200 OK` with a body of known length, e.g. a `String`
+/// or [`Bytes`].
+#[derive(Debug)]
+pub struct Ranged<T>(pub T);
+
+impl<T: Responder> Responder for Ranged<T> {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        let response = self.0.response_to(req);
+        apply_range(req, response)
+    }
+}
+
+/// A range of bytes of a representation, `end` is exclusive
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct ByteRange {
+    pub(crate) start: u64,
+    pub(crate) end: u64,
+}
+
+impl ByteRange {
+    pub(crate) fn len(self) -> u64 {
+        self.end - self.start
+    }
+
+    fn content_range(self, complete_len: u64) -> String {
+        format!("bytes {}-{}/{complete_len}", self.start, self.end - 1)
+    }
+}
+
+/// How a request for a representation of a known length is answered
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum RangeOutcome {
+    /// The whole representation, without a `Range` or with one that doesn't apply
+    Full,
+    /// The ranges, in ascending order without overlaps
+    Partial(Vec<ByteRange>),
+    /// None of the requested ranges is satisfiable
+    Unsatisfiable,
+}
+
+/// Evaluates the `Range` and `If-Range` headers of `req` against a `200 OK` response with the
+/// `headers` and a representation of `len` bytes.
+pub(crate) fn evaluate(req: &RequestContext, headers: &HeaderMap, len: u64) -> RangeOutcome {
+    if req.method() != Method::GET || !if_range_matches(req, headers) {
+        return RangeOutcome::Full;
+    }
+    let Some(range) = req.headers().get(RANGE).and_then(|value| value.to_str().ok()) else {
+        return RangeOutcome::Full;
+    };
+    parse_range(range, len)
+}
+
+/// Applies the requested ranges to a response whose body has a known length
+fn apply_range(req: &RequestContext, mut response: Response<ResponseBody>) -> Response<ResponseBody> {
+    let Some(len) = response.body().size_hint().exact() else {
+        return response;
+    };
+    if response.status() != StatusCode::OK || response.headers().contains_key(CONTENT_RANGE) {
+        return response;
+    }
+
+    response.headers_mut().insert(ACCEPT_RANGES, BYTES);
+    match evaluate(req, response.headers(), len) {
+        RangeOutcome::Full => response,
+        RangeOutcome::Partial(ranges) => partial_content(response, &ranges, len, RangeBody::new),
+        RangeOutcome::Unsatisfiable => range_not_satisfiable(len),
+    }
+}
+
+/// Turns a `200 OK` response for a representation of `len` bytes into a `206 Partial Content`
+/// response with `ranges`.
+///
+/// The new body is created by `range_body` from the body of `response`, the [`Part`] of every
+/// range and the trailer to send after them. It must have the exact size of the parts and the
+/// trailer.
+pub(crate) fn partial_content<B, F>(
+    response: Response<ResponseBody>,
+    ranges: &[ByteRange],
+    len: u64,
+    range_body: F,
+) -> Response<ResponseBody>
+where
+    B: Body<Data = Bytes, Error = HttpError> + Send + 'static,
+    F: FnOnce(ResponseBody, Vec<Part>, Bytes) -> B,
+{
+    let (mut parts, body) = response.into_parts();
+    parts.status = StatusCode::PARTIAL_CONTENT;
+
+    let body = if let [range] = ranges {
+        parts.headers.insert(CONTENT_RANGE, HeaderValue::from_str(&range.content_range(len)).unwrap());
+        range_body(body, vec![Part::new(*range, Bytes::new())], Bytes::new())
+    } else {
+        let boundary = boundary();
+        let content_type = parts.headers.get(CONTENT_TYPE).cloned();
+
+        let mut body_parts = Vec::with_capacity(ranges.len());
+        for (i, range) in ranges.iter().enumerate() {
+            let mut header = if i == 0 { format!("--{boundary}\r\n") } else { format!("\r\n--{boundary}\r\n") }.into_bytes();
+            if let Some(content_type) = &content_type {
+                header.extend_from_slice(b"Content-Type: ");
+                header.extend_from_slice(content_type.as_bytes());
+                header.extend_from_slice(b"\r\n");
+            }
+            header.extend_from_slice(format!("Content-Range: {}\r\n\r\n", range.content_range(len)).as_bytes());
+            body_parts.push(Part::new(*range, Bytes::from(header)));
+        }
+
+        let content_type = format!("multipart/byteranges; boundary={boundary}");
+        parts.headers.insert(CONTENT_TYPE, HeaderValue::try_from(content_type).unwrap());
+        range_body(body, body_parts, Bytes::from(format!("\r\n--{boundary}--\r\n")))
+    };
+
+    parts.headers.insert(CONTENT_LENGTH, HeaderValue::from(body.size_hint().exact().unwrap()));
+    Response::from_parts(parts, ResponseBody::stream(body))
+}
+
+/// Answers a request without any satisfiable range for a representation of `len` bytes
+pub(crate) fn range_not_satisfiable(len: u64) -> Response<ResponseBody> {
+    let mut response = Response::new(ResponseBody::empty());
+    *response.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+    response.headers_mut().insert(ACCEPT_RANGES, BYTES);
+    response.headers_mut().insert(CONTENT_RANGE, HeaderValue::try_from(format!("bytes */{len}")).unwrap());
+    response
+}
+
+/// Checks the `If-Range` header of `req` against the validators of the response, a request
+/// without it always matches
+fn if_range_matches(req: &RequestContext, headers: &HeaderMap) -> bool {
+    let Some(if_range) = req.headers().get(IF_RANGE) else {
+        return true;
+    };
+    let Ok(if_range) = if_range.to_str().map(str::trim) else {
+        return false;
+    };
+
+    if if_range.starts_with('"') {
+        // a strong comparison, a weak entity tag never matches
+        return headers.get(ETAG).is_some_and(|etag| etag.as_bytes() == if_range.as_bytes());
+    }
+    if if_range.starts_with("W/") {
+        return false;
+    }
+
+    let last_modified = headers.get(LAST_MODIFIED).and_then(|value| value.to_str().ok()).and_then(parse_http_date);
+    last_modified.is_some_and(|last_modified| parse_http_date(if_range) == Some(last_modified))
+}
+
+/// Parses a `Range` header for a representation of `len` bytes, as in RFC 9110 Section 14.1.2.
+fn parse_range(range: &str, len: u64) -> RangeOutcome {
+    let Some((unit, specs)) = range.split_once('=') else {
+        return RangeOutcome::Full;
+    };
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return RangeOutcome::Full;
+    }
+
+    let mut ranges = Vec::new();
+    let mut spec_count = 0;
+    for spec in specs.split(',').map(str::trim).filter(|spec| !spec.is_empty()) {
+        spec_count += 1;
+        let Some((first, last)) = spec.split_once('-') else {
+            return RangeOutcome::Full;
+        };
+
+        let range = match (first.trim(), last.trim()) {
+            ("", suffix) => {
+                let Some(suffix) = parse_digits(suffix) else {
+                    return RangeOutcome::Full;
+                };
+                ByteRange { start: len.saturating_sub(suffix), end: len }
+            }
+            (first, last) => {
+                let Some(first) = parse_digits(first) else {
+                    return RangeOutcome::Full;
+                };
+                let end = match last {
+                    "" => len,
+                    last => match parse_digits(last) {
+                        Some(last) if last >= first => last.saturating_add(1).min(len),
+                        _ => return RangeOutcome::Full,
+                    },
+                };
+                ByteRange { start: first, end }
+            }
+        };
+
+        // the range is unsatisfiable when it starts after the representation or is empty
+        if range.start < range.end {
+            ranges.push(range);
+        }
+    }
+
+    if spec_count == 0 || spec_count > MAX_RANGES {
+        return RangeOutcome::Full;
+    }
+    if ranges.is_empty() {
+        return RangeOutcome::Unsatisfiable;
+    }
+
+    ranges.sort_unstable_by_key(|range| range.start);
+    let mut coalesced: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match coalesced.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => coalesced.push(range),
+        }
+    }
+    RangeOutcome::Partial(coalesced)
+}
+
+/// Parses a non-empty sequence of ASCII digits
+fn parse_digits(digits: &str) -> Option<u64> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // saturate on overflow, such a position is past any representation
+    Some(digits.parse().unwrap_or(u64::MAX))
+}
+
+/// A boundary for a `multipart/byteranges` body, random so it doesn't appear in the parts
+fn boundary() -> String {
+    let state = RandomState::new();
+    format!("{:016x}{:016x}", state.hash_one(1), state.hash_one(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::responder::json::NdJson;
+    use crate::router::{Router, get};
+    use crate::testing::TestClient;
+
+    fn range(start: u64, end: u64) -> ByteRange {
+        ByteRange { start, end }
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-499", 1000), RangeOutcome::Partial(vec![range(0, 500)]));
+        assert_eq!(parse_range("bytes=500-", 1000), RangeOutcome::Partial(vec![range(500, 1000)]));
+        assert_eq!(parse_range("bytes=-200", 1000), RangeOutcome::Partial(vec![range(800, 1000)]));
+        assert_eq!(parse_range("bytes=-2000", 1000), RangeOutcome::Partial(vec![range(0, 1000)]));
+        assert_eq!(parse_range("bytes=900-1999", 1000), RangeOutcome::Partial(vec![range(900, 1000)]));
+        assert_eq!(parse_range("Bytes = 0-0 , -1", 1000), RangeOutcome::Partial(vec![range(0, 1), range(999, 1000)]));
+        assert_eq!(parse_range("bytes=0-99999999999999999999999", 10), RangeOutcome::Partial(vec![range(0, 10)]));
+    }
+
+    #[test]
+    fn test_parse_range_coalesces() {
+        assert_eq!(parse_range("bytes=500-599,0-99,50-149", 1000), RangeOutcome::Partial(vec![range(0, 150), range(500, 600)]));
+        assert_eq!(parse_range("bytes=0-9,10-19", 1000), RangeOutcome::Partial(vec![range(0, 20)]));
+    }
+
+    #[test]
+    fn test_parse_range_unsatisfiable() {
+        assert_eq!(parse_range("bytes=1000-", 1000), RangeOutcome::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), RangeOutcome::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), RangeOutcome::Unsatisfiable);
+        assert_eq!(parse_range("bytes=2000-2999, 1000-", 1000), RangeOutcome::Unsatisfiable);
+        assert_eq!(parse_range("bytes=2000-2999, 0-0", 1000), RangeOutcome::Partial(vec![range(0, 1)]));
+    }
+
+    #[test]
+    fn test_parse_range_invalid_is_ignored() {
+        assert_eq!(parse_range("items=0-1", 1000), RangeOutcome::Full);
+        assert_eq!(parse_range("bytes=", 1000), RangeOutcome::Full);
+        assert_eq!(parse_range("bytes=5-1", 1000), RangeOutcome::Full);
+        assert_eq!(parse_range("bytes=a-1", 1000), RangeOutcome::Full);
+        assert_eq!(parse_range("bytes=+1-2", 1000), RangeOutcome::Full);
+        assert_eq!(parse_range("bytes=1", 1000), RangeOutcome::Full);
+        assert_eq!(parse_range(&format!("bytes={}", vec!["0-0"; MAX_RANGES + 1].join(",")), 1000), RangeOutcome::Full);
+    }
+
+    async fn digits() -> Ranged<Bytes> {
+        Ranged(Bytes::from_static(b"0123456789"))
+    }
+
+    async fn streamed() -> Ranged<NdJson<impl futures::Stream<Item = u8>>> {
+        Ranged(NdJson(futures::stream::iter([1, 2])))
+    }
+
+    fn client() -> TestClient {
+        TestClient::new(Router::builder().route("/digits", get(digits)).route("/streamed", get(streamed)).build())
+    }
+
+    #[tokio::test]
+    async fn single_range() {
+        let client = client();
+
+        let response = client.get("/digits").send().await;
+        response.assert_status(StatusCode::OK).assert_header("accept-ranges", "bytes").assert_body("0123456789");
+
+        let response = client.get("/digits").header("range", "bytes=2-4").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT).assert_header("content-range", "bytes 2-4/10").assert_body("234");
+        response.assert_header("content-length", "3").assert_header("content-type", "application/octet-stream");
+
+        let response = client.get("/digits").header("range", "bytes=-3").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT).assert_header("content-range", "bytes 7-9/10").assert_body("789");
+
+        let response = client.head("/digits").header("range", "bytes=2-4").send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-length", "10");
+    }
+
+    #[tokio::test]
+    async fn multiple_ranges() {
+        let response = client().get("/digits").header("range", "bytes=7-8, 0-1").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT);
+        assert!(!response.headers().contains_key("content-range"));
+
+        let content_type = response.header("content-type").unwrap().to_str().unwrap();
+        let boundary = content_type.strip_prefix("multipart/byteranges; boundary=").unwrap();
+        let expected = format!(
+            "--{boundary}\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+             \r\n--{boundary}\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 7-8/10\r\n\r\n78\
+             \r\n--{boundary}--\r\n"
+        );
+        response.assert_body(&expected).assert_header("content-length", expected.len().to_string());
+    }
+
+    #[tokio::test]
+    async fn unsatisfiable_range() {
+        let response = client().get("/digits").header("range", "bytes=10-").send().await;
+        response.assert_status(StatusCode::RANGE_NOT_SATISFIABLE).assert_header("content-range", "bytes */10").assert_body("");
+    }
+
+    #[tokio::test]
+    async fn unknown_length_is_not_ranged() {
+        let response = client().get("/streamed").header("range", "bytes=0-0").send().await;
+        response.assert_status(StatusCode::OK).assert_body("1\n2\n");
+        assert!(!response.headers().contains_key("accept-ranges"));
+    }
+
+    #[test]
+    fn test_if_range() {
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+        response_headers.insert(LAST_MODIFIED, HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"));
+
+        let matches = |if_range: &str| {
+            let header = micro_http::protocol::RequestHeader::from(http::Request::builder().header(IF_RANGE, if_range).body(()).unwrap());
+            let params = crate::PathParams::empty();
+            if_range_matches(&RequestContext::new(&header, &params), &response_headers)
+        };
+
+        assert!(matches("\"v1\""));
+        assert!(!matches("\"v2\""));
+        assert!(!matches("W/\"v1\""));
+        assert!(matches("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert!(!matches("Sun, 06 Nov 1994 08:49:38 GMT"));
+        assert!(!matches("yesterday"));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::ResponseBody;
+use crate::range::ByteRange;
+use bytes::{Buf, Bytes};
+use http_body::{Body, Frame, SizeHint};
+use micro_http::protocol::{HttpError, SendError};
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+/// A range of the representation sent in a range body, after its part header
+#[derive(Debug)]
+pub(crate) struct Part {
+    pub(crate) header: Bytes,
+    pub(crate) range: ByteRange,
+}
+
+impl Part {
+    pub(crate) fn new(range: ByteRange, header: Bytes) -> Self {
+        Self { header, range }
+    }
+}
+
+/// A body sending ranges of another body, each after a header, and then a trailer.
+///
+/// The parts must be in ascending order without overlaps, so a single pass over the inner body
+/// sends all of them: the bytes before and between the ranges are skipped.
+#[derive(Debug)]
+pub(crate) struct RangeBody {
+    inner: ResponseBody,
+    /// The position in the representation of the first byte of `chunk`
+    position: u64,
+    /// The bytes read from `inner` and not consumed yet
+    chunk: Bytes,
+    parts: VecDeque<Part>,
+    trailer: Bytes,
+    remaining: u64,
+}
+
+impl RangeBody {
+    /// Creates a body sending `parts` of the representation yielded by `inner`
+    pub(crate) fn new(inner: ResponseBody, parts: Vec<Part>, trailer: Bytes) -> Self {
+        let remaining = parts.iter().map(|part| part.header.len() as u64 + part.range.len()).sum::<u64>() + trailer.len() as u64;
+        Self { inner, position: 0, chunk: Bytes::new(), parts: parts.into(), trailer, remaining }
+    }
+
+    fn frame(&mut self, bytes: Bytes) -> Poll<Option<Result<Frame<Bytes>, HttpError>>> {
+        self.remaining -= bytes.len() as u64;
+        Poll::Ready(Some(Ok(Frame::data(bytes))))
+    }
+}
+
+impl Body for RangeBody {
+    type Data = Bytes;
+    type Error = HttpError;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        loop {
+            let Some(part) = this.parts.front_mut() else {
+                let trailer = std::mem::take(&mut this.trailer);
+                return if trailer.is_empty() { Poll::Ready(None) } else { this.frame(trailer) };
+            };
+            if !part.header.is_empty() {
+                let header = std::mem::take(&mut part.header);
+                return this.frame(header);
+            }
+
+            let chunk_end = this.position + this.chunk.len() as u64;
+            if this.chunk.is_empty() || chunk_end <= part.range.start {
+                // nothing of the chunk is sent, skip it
+                this.position = chunk_end;
+                this.chunk.clear();
+
+                match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                    Some(Ok(frame)) => {
+                        if let Ok(data) = frame.into_data() {
+                            this.chunk = data;
+                        }
+                    }
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                    None => {
+                        let e = io::Error::new(io::ErrorKind::UnexpectedEof, "body is shorter than its announced length");
+                        this.parts.clear();
+                        this.trailer.clear();
+                        this.remaining = 0;
+                        return Poll::Ready(Some(Err(SendError::io(e).into())));
+                    }
+                }
+                continue;
+            }
+
+            // the chunk overlaps the range: send the overlap and keep the rest for the next parts
+            let from = usize::try_from(part.range.start.max(this.position) - this.position).unwrap();
+            let to = usize::try_from(part.range.end.min(chunk_end) - this.position).unwrap();
+            let data = this.chunk.slice(from..to);
+            this.chunk.advance(to);
+            this.position += to as u64;
+
+            part.range.start = this.position;
+            if part.range.start >= part.range.end {
+                this.parts.pop_front();
+            }
+            return this.frame(data);
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::{BodyExt, StreamBody};
+
+    fn chunked(chunks: &'static [&'static str]) -> ResponseBody {
+        let frames = chunks.iter().map(|chunk| Ok::<_, HttpError>(Frame::data(Bytes::from_static(chunk.as_bytes()))));
+        ResponseBody::stream(StreamBody::new(futures::stream::iter(frames)))
+    }
+
+    fn part(start: u64, end: u64, header: &'static str) -> Part {
+        Part::new(ByteRange { start, end }, Bytes::from_static(header.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn sends_ranges_across_chunks() {
+        let parts = vec![part(1, 3, "<a>"), part(4, 8, "<b>")];
+        let body = RangeBody::new(chunked(&["01", "234", "56789"]), parts, Bytes::from_static(b"<end>"));
+        assert_eq!(body.size_hint().exact(), Some(17));
+
+        assert_eq!(body.collect().await.unwrap().to_bytes(), "<a>12<b>4567<end>");
+    }
+
+    #[tokio::test]
+    async fn short_inner_body_is_an_error() {
+        let body = RangeBody::new(chunked(&["0123"]), vec![part(2, 8, "")], Bytes::new());
+        body.collect().await.unwrap_err();
+    }
+}
@@ -0,0 +1,78 @@
+//! A decorator answering range requests for the responses of handlers.
+//!
+//! See the [module documentation](crate::range) for how the ranges are applied.
+
+use crate::handler::RequestHandler;
+use crate::handler::handler_decorator::HandlerDecorator;
+use crate::handler::handler_decorator_factory::HandlerDecoratorFactory;
+use crate::range::apply_range;
+use crate::{OptionReqBody, RequestContext, ResponseBody};
+use async_trait::async_trait;
+use http::Response;
+
+/// A decorator applying the requested ranges to the responses of the decorated handlers.
+///
+/// It works both for a single route and as a global decorator. It must be applied inside
+/// decorators changing the body, e.g. compression, since it needs the length of the body.
+#[derive(Debug, Clone)]
+pub struct RangeDecorator;
+
+/// A request handler applying the requested ranges to the responses of the handler it wraps
+#[derive(Debug)]
+pub struct RangeHandler<H> {
+    handler: H,
+}
+
+impl<H: RequestHandler> HandlerDecorator<H> for RangeDecorator {
+    type Output = RangeHandler<H>;
+
+    fn decorate(&self, handler: H) -> Self::Output {
+        RangeHandler { handler }
+    }
+}
+
+impl HandlerDecoratorFactory for RangeDecorator {
+    type Output<In>
+        = RangeDecorator
+    where
+        In: RequestHandler;
+
+    fn create_decorator<In>(&self) -> Self::Output<In>
+    where
+        In: RequestHandler,
+    {
+        RangeDecorator
+    }
+}
+
+#[async_trait]
+impl<H: RequestHandler> RequestHandler for RangeHandler<H> {
+    async fn invoke<'server, 'req>(&self, req: &mut RequestContext<'server, 'req>, req_body: OptionReqBody) -> Response<ResponseBody> {
+        let resp = self.handler.invoke(req, req_body).await;
+        apply_range(req, resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::{Router, get};
+    use crate::testing::TestClient;
+    use http::StatusCode;
+
+    async fn report() -> String {
+        "a long report".to_string()
+    }
+
+    #[tokio::test]
+    async fn decorated_routes_answer_ranges() {
+        let router = Router::builder().route("/report", get(report).decorate(RangeDecorator)).route("/plain", get(report)).build();
+        let client = TestClient::new(router);
+
+        let response = client.get("/report").header("range", "bytes=2-5").send().await;
+        response.assert_status(StatusCode::PARTIAL_CONTENT).assert_header("content-range", "bytes 2-5/13").assert_body("long");
+
+        let response = client.get("/plain").header("range", "bytes=2-5").send().await;
+        response.assert_status(StatusCode::OK).assert_body("a long report");
+    }
+}
@@ -1,28 +1,56 @@
 //! Request handling module that provides access to HTTP request information and path parameters.
 //!
 //! This module contains the core types for working with HTTP requests in the web framework:
-//! - `RequestContext`: Provides access to request headers and path parameters
+//! - `RequestContext`: Provides access to request headers, path parameters and extensions
 //! - `PathParams`: Handles URL path parameters extracted from request paths
+//! - `StateMap`: Holds the shared application state registered with the server
 
-use http::{HeaderMap, Method, Uri, Version};
+use crate::extract::State;
+use http::{Extensions, HeaderMap, Method, Uri, Version};
 use matchit::Params;
 use micro_http::protocol::RequestHeader;
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+
+/// Used by request contexts created without the server's state
+static EMPTY_STATE_MAP: StateMap = StateMap::new();
 
 /// Represents the context of an HTTP request, providing access to both the request headers
 /// and any path parameters extracted from the URL.
 ///
+/// It also carries the request [`Extensions`], a typed map that starts out with the extensions of
+/// the `http::Request` received from micro-http. Handler decorators can insert values, such as an
+/// authenticated user, before invoking the inner handler, which reads them back with the
+/// [`Extension`](crate::extract::Extension) extractor.
+///
 /// The lifetime parameters ensure that the request context does not outlive the server
 /// or the request data it references.
 #[derive(Debug)]
 pub struct RequestContext<'server: 'req, 'req> {
     request_header: &'req RequestHeader,
     path_params: &'req PathParams<'server, 'req>,
+    state_map: &'req StateMap,
+    extensions: Extensions,
 }
 
 impl<'server, 'req> RequestContext<'server, 'req> {
     /// Creates a new RequestContext with the given request header and path parameters
     pub fn new(request_header: &'req RequestHeader, path_params: &'req PathParams<'server, 'req>) -> Self {
-        Self { request_header, path_params }
+        Self { request_header, path_params, state_map: &EMPTY_STATE_MAP, extensions: Extensions::new() }
+    }
+
+    /// Attaches the shared application state this request can access
+    #[must_use]
+    pub fn with_state_map(mut self, state_map: &'req StateMap) -> Self {
+        self.state_map = state_map;
+        self
+    }
+
+    /// Sets the initial extensions of this request
+    #[must_use]
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
     }
 
     /// Returns a reference to the underlying RequestHeader
@@ -54,6 +82,87 @@ impl<'server, 'req> RequestContext<'server, 'req> {
     pub fn path_params(&self) -> &PathParams<'server, 'req> {
         self.path_params
     }
+
+    /// Returns the shared application state registered with the server
+    #[must_use]
+    pub fn state_map(&self) -> &'req StateMap {
+        self.state_map
+    }
+
+    /// Returns the shared application state of type `T`, if it was registered
+    #[must_use]
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&'req T> {
+        self.state_map.get::<T>()
+    }
+
+    /// Returns a reference to the extensions of the request
+    #[must_use]
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Returns a mutable reference to the extensions of the request
+    #[must_use]
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+}
+
+/// Shared application state, holding at most one value per type.
+///
+/// Values are registered with `ServerBuilder::state` and read by handlers through the
+/// [`State`] extractor or [`RequestContext::state`].
+#[derive(Debug)]
+pub struct StateMap {
+    map: BTreeMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl StateMap {
+    /// Creates an empty `StateMap`
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { map: BTreeMap::new() }
+    }
+
+    /// Registers `value`, replacing the previously registered value of the same type
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.map.insert(TypeId::of::<T>(), Box::new(State(value)));
+    }
+
+    /// Returns the registered value of type `T`
+    #[must_use]
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.get_state::<T>().map(|state| &state.0)
+    }
+
+    /// Returns the registered value of type `T` wrapped as the [`State`] extractor
+    pub(crate) fn get_state<T: Send + Sync + 'static>(&self) -> Option<&State<T>> {
+        self.map.get(&TypeId::of::<T>()).and_then(|state| state.downcast_ref::<State<T>>())
+    }
+
+    /// Returns true if a value of type `T` is registered
+    #[must_use]
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns the number of registered values
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if no value is registered
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl Default for StateMap {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Represents path parameters extracted from the URL path of an HTTP request.
@@ -113,6 +222,16 @@ impl<'server, 'req> PathParams<'server, 'req> {
             PathParamsKind::None => None,
         }
     }
+
+    /// Returns an iterator over the path parameters as `(name, value)` pairs, in path order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&'server str, &'req str)> + '_ {
+        let params = match &self.kind {
+            PathParamsKind::Params(params) => Some(params.iter()),
+            PathParamsKind::None => None,
+        };
+        params.into_iter().flatten()
+    }
 }
 
 // Implementation of From trait to convert from Params to PathParams
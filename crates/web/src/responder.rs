@@ -2,17 +2,24 @@
 //!
 //! This module provides the [`Responder`] trait which defines how different types
 //! can be converted into HTTP responses. It includes implementations for common types
-//! like Result, Option, String, etc.
+//! like Result, Option, String, etc., as well as serialized [`Json`](crate::extract::Json) and
+//! [`Form`](crate::extract::Form) values.
 //!
 //! The [`Responder`] trait is a key part of the response pipeline, allowing handler
 //! return values to be automatically converted into proper HTTP responses.
 
+mod cookie;
+mod form;
+pub mod json;
 pub mod sse;
 
 use crate::RequestContext;
 use crate::body::ResponseBody;
+use bytes::Bytes;
 use http::{HeaderValue, Response, StatusCode};
 use std::convert::Infallible;
+use std::fmt::Display;
+use tracing::error;
 
 /// A trait for types that can be converted into HTTP responses.
 ///
@@ -89,6 +96,7 @@ impl Responder for () {
 }
 
 const TEXT_PLAIN_CONTENT_TYPE: HeaderValue = HeaderValue::from_static("text/plain; charset=utf-8");
+const APPLICATION_OCTET_STREAM_CONTENT_TYPE: HeaderValue = HeaderValue::from_static("application/octet-stream");
 
 /// Implementation for static strings returns them as plain text responses.
 impl Responder for &'static str {
@@ -113,12 +121,46 @@ impl Responder for String {
     }
 }
 
+/// Implementation for Bytes returns them as an `application/octet-stream` response.
+impl Responder for Bytes {
+    fn response_to(self, _req: &RequestContext) -> Response<ResponseBody> {
+        let mut builder = Response::builder();
+        let headers = builder.headers_mut().unwrap();
+        headers.reserve(16);
+        headers.insert(http::header::CONTENT_TYPE, APPLICATION_OCTET_STREAM_CONTENT_TYPE);
+
+        builder.status(StatusCode::OK).body(ResponseBody::once(self)).unwrap()
+    }
+}
+
 impl Responder for Infallible {
     fn response_to(self, _req: &RequestContext) -> Response<ResponseBody> {
         unreachable!()
     }
 }
 
+/// Builds a response with a complete serialized body, or a 500 if serializing failed
+pub(crate) fn serialized_response(
+    serialized: Result<Vec<u8>, impl Display>,
+    content_type: HeaderValue,
+    req: &RequestContext,
+) -> Response<ResponseBody> {
+    match serialized {
+        Ok(body) => {
+            let mut builder = Response::builder();
+            let headers = builder.headers_mut().unwrap();
+            headers.reserve(16);
+            headers.insert(http::header::CONTENT_TYPE, content_type);
+            headers.insert(http::header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+            builder.status(StatusCode::OK).body(ResponseBody::once(Bytes::from(body))).unwrap()
+        }
+        Err(e) => {
+            error!(cause = %e, "can't serialize response body");
+            (StatusCode::INTERNAL_SERVER_ERROR, "failed to serialize response").response_to(req)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct NotFound;
 
@@ -0,0 +1,113 @@
+//! Cookie responder
+//!
+//! Sends the cookies added to or removed from a [`CookieJar`] returned as `(CookieJar, T)` as
+//! `Set-Cookie` headers, answering with `500 Internal Server Error` when a cookie can't be sent.
+//! The signed and private jars of the `cookie-crypto` feature are sent the same way.
+
+use crate::responder::Responder;
+use crate::{CookieJar, RequestContext, ResponseBody};
+#[cfg(feature = "cookie-crypto")]
+use crate::{PrivateCookieJar, SignedCookieJar};
+use http::header::SET_COOKIE;
+use http::{HeaderValue, Response, StatusCode};
+use tracing::error;
+
+impl<T: Responder> Responder for (CookieJar, T) {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        let (jar, responder) = self;
+        let mut response = responder.response_to(req);
+
+        for cookie in jar.delta() {
+            let value = cookie.is_valid().then(|| HeaderValue::try_from(cookie.to_string()).ok()).flatten();
+            let Some(value) = value else {
+                error!(cookie = cookie.name(), "can't send invalid cookie");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "failed to set cookie").response_to(req);
+            };
+            response.headers_mut().append(SET_COOKIE, value);
+        }
+        response
+    }
+}
+
+#[cfg(feature = "cookie-crypto")]
+impl<T: Responder> Responder for (SignedCookieJar, T) {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        let (jar, responder) = self;
+        (jar.into_inner(), responder).response_to(req)
+    }
+}
+
+#[cfg(feature = "cookie-crypto")]
+impl<T: Responder> Responder for (PrivateCookieJar, T) {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        let (jar, responder) = self;
+        (jar.into_inner(), responder).response_to(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::{Router, get};
+    use crate::testing::TestClient;
+    use crate::{Cookie, CookieJar};
+    use http::StatusCode;
+    use http::header::SET_COOKIE;
+
+    async fn visit(jar: CookieJar) -> (CookieJar, String) {
+        let visits = jar.get("visits").and_then(|cookie| cookie.value().parse::<u32>().ok()).unwrap_or(0) + 1;
+        let jar =
+            jar.insert(Cookie::new("visits", visits.to_string()).with_path("/").with_http_only(true)).remove(Cookie::new("legacy", ""));
+        (jar, format!("visit #{visits}"))
+    }
+
+    async fn invalid(jar: CookieJar) -> (CookieJar, &'static str) {
+        (jar.insert(Cookie::new("name", "a;b")), "unreachable")
+    }
+
+    #[tokio::test]
+    async fn set_cookie_headers() {
+        let router = Router::builder().route("/", get(visit)).route("/invalid", get(invalid)).build();
+        let client = TestClient::new(router);
+
+        let response = client.get("/").header("cookie", "visits=41; legacy=1").send().await;
+        response.assert_status(StatusCode::OK).assert_body("visit #42");
+        let set_cookies = response.headers().get_all(SET_COOKIE).iter().map(|value| value.to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(set_cookies, ["visits=42; Path=/; HttpOnly", "legacy=; Max-Age=0"]);
+
+        client.get("/invalid").send().await.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[cfg(feature = "cookie-crypto")]
+    #[tokio::test]
+    async fn signed_and_private_cookies() {
+        use crate::{Key, PrivateCookieJar, Server, SignedCookieJar};
+
+        async fn login(signed: SignedCookieJar, private: PrivateCookieJar) -> (SignedCookieJar, (PrivateCookieJar, &'static str)) {
+            (signed.insert(Cookie::new("user", "alice")), (private.insert(Cookie::new("email", "alice@example.com")), "logged in"))
+        }
+
+        async fn whoami(signed: SignedCookieJar, private: PrivateCookieJar) -> String {
+            let user = signed.get("user").map_or("anonymous".to_string(), |cookie| cookie.value().to_string());
+            let email = private.get("email").map_or("unknown".to_string(), |cookie| cookie.value().to_string());
+            format!("{user} <{email}>")
+        }
+
+        let router = Router::builder().route("/login", get(login)).route("/whoami", get(whoami)).build();
+        let server = Server::builder().router(router).state(Key::generate()).bind("127.0.0.1:0").build().unwrap();
+        let client = TestClient::new(server);
+
+        let response = client.get("/login").send().await;
+        response.assert_status(StatusCode::OK).assert_body("logged in");
+        let cookies = response.headers().get_all(SET_COOKIE).iter().map(|value| value.to_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(cookies.len(), 2);
+        assert!(cookies.iter().all(|cookie| !cookie.contains("alice@example.com")));
+
+        let response = client.get("/whoami").header("cookie", cookies.join("; ")).send().await;
+        response.assert_body("alice <alice@example.com>");
+        let response = client.get("/whoami").header("cookie", "user=alice; email=alice@example.com").send().await;
+        response.assert_body("anonymous <unknown>");
+
+        let client = TestClient::new(Router::builder().route("/whoami", get(whoami)).build());
+        client.get("/whoami").send().await.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,48 @@
+//! Form responder
+//!
+//! Serializes [`Form<T>`] into an `application/x-www-form-urlencoded` response, answering
+//! with `500 Internal Server Error` when the value can't be encoded.
+
+use crate::extract::Form;
+use crate::responder::{Responder, serialized_response};
+use crate::{RequestContext, ResponseBody};
+use http::{HeaderValue, Response};
+use serde::Serialize;
+
+const FORM_URLENCODED_CONTENT_TYPE: HeaderValue = HeaderValue::from_static("application/x-www-form-urlencoded");
+
+impl<T: Serialize> Responder for Form<T> {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        serialized_response(serde_urlencoded::to_string(&self.0).map(String::into_bytes), FORM_URLENCODED_CONTENT_TYPE, req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathParams;
+    use bytes::Bytes;
+    use http::StatusCode;
+    use http_body_util::BodyExt;
+    use micro_http::protocol::RequestHeader;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Search {
+        q: &'static str,
+        page: u32,
+    }
+
+    #[tokio::test]
+    async fn form_response() {
+        let header = RequestHeader::from(http::Request::new(()));
+        let params = PathParams::empty();
+        let response = Form(Search { q: "micro http", page: 2 }).response_to(&RequestContext::new(&header, &params));
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[http::header::CONTENT_TYPE], "application/x-www-form-urlencoded");
+        assert_eq!(response.headers()[http::header::CONTENT_LENGTH], "19");
+        let body: Bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, &b"q=micro+http&page=2"[..]);
+    }
+}
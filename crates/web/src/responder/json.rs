@@ -0,0 +1,155 @@
+//! JSON responders
+//!
+//! This module turns serializable values into `application/json` responses:
+//!
+//! - [`Json<T>`]: Compact JSON with an exact `Content-Length`
+//! - [`PrettyJson<T>`]: Indented JSON, handy for endpoints read by humans
+//! - [`NdJson<S>`]: A stream of values sent as newline delimited JSON
+//!
+//! A value that can't be serialized is answered with `500 Internal Server Error`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use futures::stream;
+//! use micro_web::extract::Json;
+//! use micro_web::responder::json::NdJson;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! async fn user() -> Json<User> {
+//!     Json(User { name: "alice".to_string() })
+//! }
+//!
+//! async fn users() -> NdJson<impl futures::Stream<Item = User>> {
+//!     NdJson(stream::iter(["alice", "bob"].map(|name| User { name: name.to_string() })))
+//! }
+//! ```
+
+use crate::extract::Json;
+use crate::responder::{Responder, serialized_response};
+use crate::{RequestContext, ResponseBody};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use http::{HeaderValue, Response, StatusCode};
+use http_body::Frame;
+use http_body_util::StreamBody;
+use micro_http::protocol::{HttpError, SendError};
+use serde::Serialize;
+
+const APPLICATION_JSON_CONTENT_TYPE: HeaderValue = HeaderValue::from_static("application/json");
+const APPLICATION_NDJSON_CONTENT_TYPE: HeaderValue = HeaderValue::from_static("application/x-ndjson");
+
+/// Responds with the value serialized as indented JSON.
+#[derive(Debug)]
+pub struct PrettyJson<T>(pub T);
+
+/// Responds with every item of the stream serialized as JSON on its own line.
+///
+/// The response is streamed with chunked encoding. An item that can't be serialized
+/// aborts the response, since the status has already been sent.
+#[derive(Debug)]
+pub struct NdJson<S>(pub S);
+
+impl<T: Serialize> Responder for Json<T> {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        serialized_response(serde_json::to_vec(&self.0), APPLICATION_JSON_CONTENT_TYPE, req)
+    }
+}
+
+impl<T: Serialize> Responder for PrettyJson<T> {
+    fn response_to(self, req: &RequestContext) -> Response<ResponseBody> {
+        serialized_response(serde_json::to_vec_pretty(&self.0), APPLICATION_JSON_CONTENT_TYPE, req)
+    }
+}
+
+impl<S> Responder for NdJson<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    fn response_to(self, _req: &RequestContext) -> Response<ResponseBody> {
+        let mut builder = Response::builder();
+        let headers = builder.headers_mut().unwrap();
+        headers.reserve(16);
+        headers.insert(http::header::CONTENT_TYPE, APPLICATION_NDJSON_CONTENT_TYPE);
+
+        let lines = self.0.map(|item| {
+            let mut line = serde_json::to_vec(&item).map_err(|e| HttpError::from(SendError::invalid_body(e)))?;
+            line.push(b'\n');
+            Ok(Frame::data(Bytes::from(line)))
+        });
+
+        builder.status(StatusCode::OK).body(ResponseBody::stream(StreamBody::new(lines))).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathParams;
+    use http_body_util::BodyExt;
+    use micro_http::protocol::RequestHeader;
+    use serde::ser::Error;
+    use serde::{Serialize, Serializer};
+
+    #[derive(Serialize)]
+    struct User {
+        name: &'static str,
+        age: u8,
+    }
+
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(S::Error::custom("not serializable"))
+        }
+    }
+
+    async fn respond(responder: impl Responder) -> (Response<()>, Bytes) {
+        let header = RequestHeader::from(http::Request::new(()));
+        let params = PathParams::empty();
+        let response = responder.response_to(&RequestContext::new(&header, &params));
+        let (parts, body) = response.into_parts();
+        (Response::from_parts(parts, ()), body.collect().await.unwrap().to_bytes())
+    }
+
+    #[tokio::test]
+    async fn json_response() {
+        let (response, body) = respond(Json(User { name: "alice", age: 30 })).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[http::header::CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers()[http::header::CONTENT_LENGTH], "25");
+        assert_eq!(body, &br#"{"name":"alice","age":30}"#[..]);
+    }
+
+    #[tokio::test]
+    async fn pretty_json_response() {
+        let (response, body) = respond(PrettyJson(User { name: "alice", age: 30 })).await;
+
+        assert_eq!(response.headers()[http::header::CONTENT_LENGTH], body.len().to_string().as_str());
+        assert_eq!(body, &b"{\n  \"name\": \"alice\",\n  \"age\": 30\n}"[..]);
+    }
+
+    #[tokio::test]
+    async fn serialization_failure_is_internal_server_error() {
+        let (response, _) = respond(Json(Unserializable)).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn ndjson_response() {
+        let users = futures::stream::iter([User { name: "alice", age: 30 }, User { name: "bob", age: 25 }]);
+        let (response, body) = respond(NdJson(users)).await;
+
+        assert_eq!(response.headers()[http::header::CONTENT_TYPE], "application/x-ndjson");
+        assert!(!response.headers().contains_key(http::header::CONTENT_LENGTH));
+        assert_eq!(body, &b"{\"name\":\"alice\",\"age\":30}\n{\"name\":\"bob\",\"age\":25}\n"[..]);
+    }
+}
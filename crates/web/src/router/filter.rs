@@ -30,8 +30,10 @@
 
 use std::any::type_name_of_val;
 use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 use crate::RequestContext;
 use http::{HeaderName, HeaderValue, Method};
+use thiserror::Error;
 
 /// Core trait for request filtering.
 ///
@@ -47,6 +49,22 @@ pub trait Filter: Send + Sync + Debug {
     ///
     /// Returns `true` if the request should be allowed, `false` otherwise.
     fn matches(&self, req: &RequestContext) -> bool;
+
+    /// Adds the methods this filter matches on to `methods`.
+    ///
+    /// The router probes them, besides the standard methods, when listing the methods a path
+    /// allows, so only filters on the method need to implement it.
+    fn collect_methods(&self, _methods: &mut Vec<Method>) {}
+}
+
+impl<F: Filter + ?Sized> Filter for Arc<F> {
+    fn matches(&self, req: &RequestContext) -> bool {
+        self.as_ref().matches(req)
+    }
+
+    fn collect_methods(&self, methods: &mut Vec<Method>) {
+        self.as_ref().collect_methods(methods);
+    }
 }
 
 /// A filter that wraps a closure.
@@ -157,6 +175,12 @@ impl Filter for AnyFilter {
 
         false
     }
+
+    fn collect_methods(&self, methods: &mut Vec<Method>) {
+        for filter in &self.filters {
+            filter.collect_methods(methods);
+        }
+    }
 }
 
 /// Creates a new AND-composed filter chain.
@@ -199,6 +223,12 @@ impl Filter for AllFilter {
 
         true
     }
+
+    fn collect_methods(&self, methods: &mut Vec<Method>) {
+        for filter in &self.filters {
+            filter.collect_methods(methods);
+        }
+    }
 }
 
 /// A filter that matches HTTP methods.
@@ -209,6 +239,17 @@ impl Filter for MethodFilter {
     fn matches(&self, req: &RequestContext) -> bool {
         self.0.eq(req.method())
     }
+
+    fn collect_methods(&self, methods: &mut Vec<Method>) {
+        methods.push(self.0.clone());
+    }
+}
+
+/// Creates a filter that matches `method`, e.g. an extension method like `PROPFIND`.
+#[inline]
+#[must_use]
+pub fn method(method: Method) -> MethodFilter {
+    MethodFilter(method)
 }
 
 macro_rules! method_filter {
@@ -232,6 +273,10 @@ method_filter!(patch_method, PATCH);
 method_filter!(trace_method, TRACE);
 
 /// Creates a filter that matches a specific header name and value.
+///
+/// # Panics
+///
+/// Panics if the name or the value isn't a valid header, see [`try_header`].
 #[inline]
 pub fn header<K, V>(header_name: K, header_value: V) -> HeaderFilter
 where
@@ -240,10 +285,37 @@ where
     HeaderValue: TryFrom<V>,
     <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
 {
-    // TODO: need to process the unwrap
-    let name = <HeaderName as TryFrom<K>>::try_from(header_name).map_err(Into::into).unwrap();
-    let value = <HeaderValue as TryFrom<V>>::try_from(header_value).map_err(Into::into).unwrap();
-    HeaderFilter(name, value)
+    try_header(header_name, header_value).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Creates a filter that matches a specific header name and value.
+///
+/// # Errors
+///
+/// Returns an error if the name or the value isn't a valid header.
+pub fn try_header<K, V>(header_name: K, header_value: V) -> Result<HeaderFilter, InvalidHeaderFilter>
+where
+    HeaderName: TryFrom<K>,
+    <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+    HeaderValue: TryFrom<V>,
+    <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+{
+    let name = <HeaderName as TryFrom<K>>::try_from(header_name).map_err(|e| InvalidHeaderFilter::InvalidName { source: e.into() })?;
+    let value = <HeaderValue as TryFrom<V>>::try_from(header_value)
+        .map_err(|e| InvalidHeaderFilter::InvalidValue { name: name.clone(), source: e.into() })?;
+    Ok(HeaderFilter(name, value))
+}
+
+/// Error creating a [`HeaderFilter`] from an invalid header name or value
+#[derive(Debug, Error)]
+pub enum InvalidHeaderFilter {
+    /// The header name isn't a valid header name
+    #[error("invalid header filter: invalid header name: {source}")]
+    InvalidName { source: http::Error },
+
+    /// The header value isn't a valid value, the name is the one of the filter
+    #[error("invalid header filter on `{name}`: invalid header value: {source}")]
+    InvalidValue { name: HeaderName, source: http::Error },
 }
 
 /// A filter that matches HTTP headers.
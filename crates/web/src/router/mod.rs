@@ -1,14 +1,19 @@
 pub mod filter;
 
-use crate::{handler_fn, FnTrait, PathParams};
+use crate::{handler_fn, FnTrait, OptionReqBody, PathParams, RequestContext, ResponseBody};
 use crate::handler::RequestHandler;
 
 use crate::handler::handler_decorator::HandlerDecorator;
 use crate::handler::handler_decorator_factory::{
     HandlerDecoratorFactory, HandlerDecoratorFactoryComposer, HandlerDecoratorFactoryExt, IdentityHandlerDecoratorFactory,
 };
+use async_trait::async_trait;
 use filter::{AllFilter, Filter};
+use http::{Extensions, HeaderValue, Method, Request, Response, StatusCode};
+use micro_http::protocol::RequestHeader;
 use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
 use tracing::error;
 use crate::extract::FromRequest;
 use crate::responder::Responder;
@@ -16,10 +21,18 @@ use crate::responder::Responder;
 type RouterFilter = dyn Filter + Send + Sync + 'static;
 type InnerRouter<T> = matchit::Router<T>;
 
+/// The methods always probed when listing the methods a path allows, in the order they are listed
+const PROBED_METHODS: [Method; 8] =
+    [Method::GET, Method::HEAD, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::CONNECT, Method::TRACE];
+
 /// Main router structure that handles HTTP request routing
 #[derive(Debug)]
 pub struct Router {
     inner_router: InnerRouter<Vec<RouterItem>>,
+    /// The largest body size limit overridden by any route
+    max_route_body_size: Option<u64>,
+    /// Answers requests whose path matched but whose method no route accepts
+    method_not_allowed_handler: Box<dyn RequestHandler>,
 }
 
 /// A router item containing a filter and handler
@@ -27,6 +40,9 @@ pub struct Router {
 pub struct RouterItem {
     filter: Box<RouterFilter>,
     handler: Box<dyn RequestHandler>,
+    max_body_size: Option<u64>,
+    /// The methods the filter matches on, see [`Filter::collect_methods`]
+    methods: Vec<Method>,
 }
 
 /// Result of matching a route, containing matched items and path parameters
@@ -55,6 +71,49 @@ impl Router {
             .map_err(|e| error!("match '{}' error: {}", path, e))
             .unwrap_or(RouteResult::empty())
     }
+
+    /// Returns the largest request body size limit set on any route
+    pub(crate) fn max_route_body_size(&self) -> Option<u64> {
+        self.max_route_body_size
+    }
+
+    /// Returns the handler answering a request whose path matched but whose method no route accepts
+    ///
+    /// It expects the [`AllowedMethods`] of the path in the request extensions, and is wrapped by
+    /// the global decorators of the router like every route.
+    pub(crate) fn method_not_allowed_handler(&self) -> &dyn RequestHandler {
+        self.method_not_allowed_handler.as_ref()
+    }
+}
+
+/// The methods the matched path allows, see [`RouteResult::allowed_methods`]
+///
+/// Inserted into the request extensions when no route accepts the request's method, before
+/// the router answers it with `405 Method Not Allowed` or `204 No Content`.
+#[derive(Debug, Clone)]
+pub struct AllowedMethods(pub Vec<Method>);
+
+/// Answers a request whose path exists but whose method no route accepts
+///
+/// An `OPTIONS` request gets `204 No Content`, any other method `405 Method Not Allowed`,
+/// both listing the [`AllowedMethods`] in the `Allow` header.
+#[derive(Debug)]
+struct MethodNotAllowedHandler;
+
+#[async_trait]
+impl RequestHandler for MethodNotAllowedHandler {
+    async fn invoke<'server, 'req>(&self, req: &mut RequestContext<'server, 'req>, _req_body: OptionReqBody) -> Response<ResponseBody> {
+        let mut response = if req.method() == Method::OPTIONS {
+            (StatusCode::NO_CONTENT, ()).response_to(req)
+        } else {
+            (StatusCode::METHOD_NOT_ALLOWED, "405 Method Not Allowed").response_to(req)
+        };
+
+        let allowed_methods = req.extensions().get::<AllowedMethods>().map_or(&[][..], |allowed_methods| allowed_methods.0.as_slice());
+        let allow = allowed_methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+        response.headers_mut().insert(http::header::ALLOW, HeaderValue::try_from(allow).unwrap());
+        response
+    }
 }
 
 impl RouterItem {
@@ -67,6 +126,11 @@ impl RouterItem {
     pub fn handler(&self) -> &dyn RequestHandler {
         self.handler.as_ref()
     }
+
+    /// Gets the request body size limit of this router item, if it overrides the server's default
+    pub fn max_body_size(&self) -> Option<u64> {
+        self.max_body_size
+    }
 }
 
 impl<'router, 'req> RouteResult<'router, 'req> {
@@ -89,17 +153,148 @@ impl<'router, 'req> RouteResult<'router, 'req> {
     pub fn router_items(&self) -> &'router [RouterItem] {
         self.router_items
     }
+
+    /// Finds the first matched router item whose filter accepts the request
+    ///
+    /// A `HEAD` request without a dedicated route falls back to the route that would handle the
+    /// same request as `GET`, the connection then sends the response header without its body.
+    pub fn find(&self, request_context: &RequestContext) -> Option<&'router RouterItem> {
+        let item = self.router_items.iter().find(|item| item.filter().matches(request_context));
+        if item.is_some() || request_context.method() != Method::HEAD {
+            return item;
+        }
+
+        MethodProbe::new(request_context).find(self.router_items, Method::GET)
+    }
+
+    /// Lists the methods the matched router items would accept for this request
+    ///
+    /// Every filter is probed with the request as if it used another method, so filters on other
+    /// parts of the request still apply. The standard methods are probed, then the other methods
+    /// the matched routes filter on, like extension methods. `HEAD` is allowed whenever `GET` is,
+    /// and `OPTIONS` is allowed along with any other method since it's answered automatically.
+    /// Returns an empty list if no route matched the path or no filter accepts any method.
+    #[must_use]
+    pub fn allowed_methods(&self, request_context: &RequestContext) -> Vec<Method> {
+        if self.router_items.is_empty() {
+            return vec![];
+        }
+
+        let mut probed_methods = PROBED_METHODS.to_vec();
+        for method in self.router_items.iter().flat_map(|item| &item.methods) {
+            if *method != Method::OPTIONS && !probed_methods.contains(method) {
+                probed_methods.push(method.clone());
+            }
+        }
+
+        let mut probe = MethodProbe::new(request_context);
+        let mut allowed_methods = Vec::with_capacity(probed_methods.len() + 1);
+        let mut get_allowed = false;
+        // `GET` is probed before `HEAD`, which it allows as well
+        for method in probed_methods {
+            let allowed = (method == Method::HEAD && get_allowed) || probe.find(self.router_items, method.clone()).is_some();
+            if allowed {
+                get_allowed |= method == Method::GET;
+                allowed_methods.push(method);
+            }
+        }
+
+        if !allowed_methods.is_empty() || probe.find(self.router_items, Method::OPTIONS).is_some() {
+            allowed_methods.push(Method::OPTIONS);
+        }
+        allowed_methods
+    }
+}
+
+/// A copy of a request header whose method is swapped to probe the filters of router items
+///
+/// The header and the extensions are copied once and reused for every probed method, the probes
+/// see the path parameters, the state and the extensions of the original request.
+struct MethodProbe<'ctx, 'server, 'req> {
+    header: RequestHeader,
+    extensions: Extensions,
+    request_context: &'ctx RequestContext<'server, 'req>,
 }
 
+impl<'ctx, 'server, 'req> MethodProbe<'ctx, 'server, 'req> {
+    fn new(request_context: &'ctx RequestContext<'server, 'req>) -> Self {
+        let mut request = Request::new(());
+        *request.uri_mut() = request_context.uri().clone();
+        *request.version_mut() = request_context.version();
+        *request.headers_mut() = request_context.headers().clone();
+        Self { header: RequestHeader::from(request), extensions: request_context.extensions().clone(), request_context }
+    }
+
+    /// Finds the first of `router_items` accepting the request if it used `method` instead
+    fn find<'router>(&mut self, router_items: &'router [RouterItem], method: Method) -> Option<&'router RouterItem> {
+        *self.header.as_mut().method_mut() = method;
+        let mut context = RequestContext::new(&self.header, self.request_context.path_params())
+            .with_state_map(self.request_context.state_map())
+            .with_extensions(std::mem::take(&mut self.extensions));
+
+        let item = router_items.iter().find(|item| item.filter().matches(&context));
+        // filters only read the extensions, they are handed back for the next probe
+        self.extensions = std::mem::take(context.extensions_mut());
+        item
+    }
+}
+
+/// Builder collecting the routes of a [`Router`]
+///
+/// Routes sharing a path prefix can be grouped with [`RouterBuilder::nest`] or
+/// [`RouterBuilder::scope`]. A nested builder keeps its own filters and decorators, which only
+/// apply to its routes and run inside the filters and decorators of the builders it's nested in.
+/// Decorators of a single route are added with [`RouterItemBuilder::decorate`] and run innermost.
+///
+/// # Example
+/// ```
+/// use micro_web::encoding::encoder::EncodeDecorator;
+/// use micro_web::router::filter::header;
+/// use micro_web::router::{get, post, Router};
+///
+/// async fn hello() -> &'static str {
+///     "hello"
+/// }
+///
+/// let router = Router::builder()
+///     .route("/", get(hello))
+///     .scope("/api/v1", |api| {
+///         api.route("/users", get(hello))
+///             .route("/users/{id}", post(hello))
+///             .with_filter(header("x-api-key", "secret"))
+///             .with_global_decorator(EncodeDecorator)
+///     })
+///     .try_build()
+///     .unwrap();
+///
+/// assert!(!router.at("/api/v1/users/42").is_empty());
+/// ```
 #[derive(Debug)]
 pub struct RouterBuilder<DF> {
     data: HashMap<String, Vec<RouterItemBuilder>>,
+    filters: Vec<Arc<RouterFilter>>,
     decorator_factory: DF,
 }
 
+/// Error building a [`Router`] from routes that can't be inserted, such as conflicting routes
+#[derive(Debug, Error)]
+#[error("invalid route `{path}`: {source}")]
+pub struct RouterBuildError {
+    path: String,
+    source: matchit::InsertError,
+}
+
+impl RouterBuildError {
+    /// Returns the path of the route that couldn't be inserted
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
 impl RouterBuilder<IdentityHandlerDecoratorFactory> {
     fn new() -> Self {
-        Self { data: HashMap::new(), decorator_factory: IdentityHandlerDecoratorFactory }
+        Self { data: HashMap::new(), filters: vec![], decorator_factory: IdentityHandlerDecoratorFactory }
     }
 }
 impl<DF> RouterBuilder<DF> {
@@ -109,36 +304,118 @@ impl<DF> RouterBuilder<DF> {
         self
     }
 
+    /// Adds a filter that every route of this builder must pass, including the nested ones
+    #[must_use]
+    pub fn with_filter<F: Filter + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
     pub fn with_global_decorator<DF2>(self, factory: DF2) -> RouterBuilder<HandlerDecoratorFactoryComposer<DF, DF2>>
     where
         DF: HandlerDecoratorFactory,
         DF2: HandlerDecoratorFactory,
     {
-        RouterBuilder { data: self.data, decorator_factory: self.decorator_factory.and_then(factory) }
+        RouterBuilder { data: self.data, filters: self.filters, decorator_factory: self.decorator_factory.and_then(factory) }
+    }
+
+    /// Mounts the routes of `router` under `prefix`
+    ///
+    /// The filters and decorators of `router` are applied to its routes here, so they don't
+    /// leak to the other routes of this builder.
+    #[must_use]
+    pub fn nest<DF2>(mut self, prefix: &str, router: RouterBuilder<DF2>) -> Self
+    where
+        DF2: HandlerDecoratorFactory,
+    {
+        for (path, items) in router.into_routes() {
+            self.data.entry(join_path(prefix, &path)).or_default().extend(items);
+        }
+        self
+    }
+
+    /// Mounts the routes registered by `f` on a new builder under `prefix`, see [`RouterBuilder::nest`]
+    #[must_use]
+    pub fn scope<DF2, F>(self, prefix: &str, f: F) -> Self
+    where
+        DF2: HandlerDecoratorFactory,
+        F: FnOnce(RouterBuilder<IdentityHandlerDecoratorFactory>) -> RouterBuilder<DF2>,
+    {
+        self.nest(prefix, f(RouterBuilder::new()))
     }
 
     /// Builds the router from the accumulated routes and wrappers
+    ///
+    /// # Panics
+    ///
+    /// Panics if a route can't be inserted, see [`RouterBuilder::try_build`].
     pub fn build(self) -> Router
+    where
+        DF: HandlerDecoratorFactory,
+    {
+        self.try_build().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Builds the router from the accumulated routes and wrappers
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a route path is malformed or conflicts with another route.
+    pub fn try_build(self) -> Result<Router, RouterBuildError>
     where
         DF: HandlerDecoratorFactory,
     {
         let mut inner_router = InnerRouter::new();
+        let mut max_route_body_size = None;
+        let method_not_allowed_handler = Box::new(self.decorator_factory.create_decorator().decorate(MethodNotAllowedHandler));
+
+        // sorted so that a conflict is always reported for the same route
+        let mut routes = self.into_routes().collect::<Vec<_>>();
+        routes.sort_unstable_by(|(path_1, _), (path_2, _)| path_1.cmp(path_2));
+
+        for (path, items) in routes {
+            let router_items = items.into_iter().map(RouterItemBuilder::build).collect::<Vec<_>>();
+
+            max_route_body_size = router_items.iter().filter_map(RouterItem::max_body_size).chain(max_route_body_size).max();
+            if let Err(source) = inner_router.insert(path.as_str(), router_items) {
+                return Err(RouterBuildError { path, source });
+            }
+        }
 
-        for (path, items) in self.data.into_iter() {
-            let router_items = items
+        Ok(Router { inner_router, max_route_body_size, method_not_allowed_handler })
+    }
+
+    /// Applies the filters and decorators of this builder to its routes
+    fn into_routes(self) -> impl Iterator<Item = (String, Vec<RouterItemBuilder>)>
+    where
+        DF: HandlerDecoratorFactory,
+    {
+        let Self { data, filters, decorator_factory } = self;
+        data.into_iter().map(move |(path, items)| {
+            let items = items
                 .into_iter()
-                .map(|item_builder| item_builder.build())
-                .map(|item| {
-                    let decorator = self.decorator_factory.create_decorator();
+                .map(|mut item| {
+                    for filter in &filters {
+                        item.filters.and(Arc::clone(filter));
+                    }
+                    let decorator = decorator_factory.create_decorator();
                     let handler = decorator.decorate(item.handler);
-                    RouterItem { handler: Box::new(handler), ..item }
+                    RouterItemBuilder { handler: Box::new(handler), ..item }
                 })
-                .collect::<Vec<_>>();
-
-            inner_router.insert(path, router_items).unwrap();
-        }
+                .collect();
+            (path, items)
+        })
+    }
+}
 
-        Router { inner_router }
+/// Joins a scope prefix and a route path, the root of a scope is the prefix itself
+fn join_path(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    match path {
+        "" | "/" if prefix.is_empty() => "/".to_string(),
+        "" | "/" => prefix.to_string(),
+        _ if path.starts_with('/') => format!("{prefix}{path}"),
+        _ => format!("{prefix}/{path}"),
     }
 }
 
@@ -148,7 +425,7 @@ macro_rules! inner_method_router_filter {
         pub fn $method<H: RequestHandler + 'static>(handler: H) -> RouterItemBuilder {
             let mut filters = filter::all_filter();
             filters.and(filter::$method_name());
-            RouterItemBuilder { filters, handler: Box::new(handler) }
+            RouterItemBuilder { filters, handler: Box::new(handler), max_body_size: None }
         }
     };
 }
@@ -188,11 +465,31 @@ method_router_filter!(connect, inner_connect);
 method_router_filter!(patch, inner_patch);
 method_router_filter!(trace, inner_trace);
 
+/// Routes requests using `method` to `handler`, e.g. an extension method like `PROPFIND`
+#[inline]
+pub fn inner_on<H: RequestHandler + 'static>(method: Method, handler: H) -> RouterItemBuilder {
+    let mut filters = filter::all_filter();
+    filters.and(filter::method(method));
+    RouterItemBuilder { filters, handler: Box::new(handler), max_body_size: None }
+}
+
+/// Routes requests using `method` to the async function `f`, e.g. an extension method like `PROPFIND`
+pub fn on<F, Args>(method: Method, f: F) -> RouterItemBuilder
+where
+    for<'r> F: FnTrait<Args> + 'r,
+    for<'r> Args: FromRequest + 'r,
+    for<'r> F: FnTrait<Args::Output<'r>>,
+    for<'r> <F as FnTrait<Args::Output<'r>>>::Output: Responder,
+{
+    inner_on(method, handler_fn(f))
+}
+
 
 #[derive(Debug)]
 pub struct RouterItemBuilder {
     filters: AllFilter,
     handler: Box<dyn RequestHandler>,
+    max_body_size: Option<u64>,
 }
 
 impl RouterItemBuilder {
@@ -201,16 +498,39 @@ impl RouterItemBuilder {
         self
     }
 
+    /// Wraps the handler of this route with `decorator`, e.g. to add auth or a timeout to a single route
+    ///
+    /// Decorators compose from the inside out: the ones added to the route wrap its handler first,
+    /// in the order they're added, then the decorators of the enclosing scopes from the innermost
+    /// to the outermost, and the global decorators of the root [`RouterBuilder`] last.
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value, reason = "decorators are usually unit structs, taken by value like filters")]
+    pub fn decorate<D>(self, decorator: D) -> Self
+    where
+        D: HandlerDecorator<Box<dyn RequestHandler>>,
+        D::Output: 'static,
+    {
+        RouterItemBuilder { handler: Box::new(decorator.decorate(self.handler)), ..self }
+    }
+
+    /// Overrides the server's request body size limit for this route, e.g. to allow large uploads
+    pub fn max_body_size(mut self, max_body_size: u64) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
     fn build(self) -> RouterItem {
+        let mut methods = vec![];
+        self.filters.collect_methods(&mut methods);
         // todo: we can remove indirect when filters has only one filter
-        RouterItem { filter: Box::new(self.filters), handler: self.handler }
+        RouterItem { filter: Box::new(self.filters), handler: self.handler, max_body_size: self.max_body_size, methods }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::filter::header;
-    use super::{Router, get, post};
+    use super::filter::{InvalidHeaderFilter, filter_fn, header, try_header};
+    use super::{Router, get, head, join_path, on, options, post};
     use crate::{PathParams, RequestContext};
     use http::{HeaderValue, Method, Request};
     use micro_http::protocol::RequestHeader;
@@ -301,4 +621,142 @@ mod tests {
         assert!(items[1].filter.matches(&req_ctx));
         assert!(items[2].filter.matches(&req_ctx));
     }
+
+    #[test]
+    fn test_route_max_body_size() {
+        let router = Router::builder()
+            .route("/", get(simple_get_1))
+            .route("/upload", post(simple_get_1).max_body_size(64 * 1024 * 1024))
+            .route("/small", post(simple_get_1).max_body_size(1024))
+            .build();
+
+        assert_eq!(router.max_route_body_size(), Some(64 * 1024 * 1024));
+        assert_eq!(router.at("/").router_items()[0].max_body_size(), None);
+        assert_eq!(router.at("/small").router_items()[0].max_body_size(), Some(1024));
+
+        let router = Router::builder().route("/", get(simple_get_1)).build();
+        assert_eq!(router.max_route_body_size(), None);
+    }
+
+    #[test]
+    fn test_route_head_falls_back_to_get() {
+        let router = Router::builder()
+            .route("/", get(simple_get_1).max_body_size(1))
+            .route("/head", get(simple_get_1).max_body_size(1))
+            .route("/head", head(simple_get_2).max_body_size(2))
+            .route("/post", post(simple_get_1))
+            .build();
+
+        let header: RequestHeader = Request::builder().method(Method::HEAD).body(()).unwrap().into_parts().0.into();
+        let params = PathParams::empty();
+        let req_ctx = RequestContext::new(&header, &params);
+
+        assert_eq!(router.at("/").find(&req_ctx).unwrap().max_body_size(), Some(1));
+        assert_eq!(router.at("/head").find(&req_ctx).unwrap().max_body_size(), Some(2));
+        assert!(router.at("/post").find(&req_ctx).is_none());
+    }
+
+    #[test]
+    fn test_route_allowed_methods() {
+        let router = router();
+
+        let header: RequestHeader = Request::builder().method(Method::DELETE).body(()).unwrap().into_parts().0.into();
+        let params = PathParams::empty();
+        let req_ctx = RequestContext::new(&header, &params);
+
+        assert_eq!(router.at("/").allowed_methods(&req_ctx), [Method::GET, Method::HEAD, Method::POST, Method::OPTIONS]);
+        assert_eq!(router.at("/2").allowed_methods(&req_ctx), [Method::GET, Method::HEAD, Method::OPTIONS]);
+        assert!(router.at("/missing").allowed_methods(&req_ctx).is_empty());
+    }
+
+    #[test]
+    fn test_route_allowed_methods_beyond_the_standard_ones() {
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+        let router = Router::builder()
+            .route("/preflight", options(simple_get_1))
+            .route("/dav", on(propfind.clone(), simple_get_1))
+            .route("/dav", get(simple_get_2))
+            .build();
+
+        let header: RequestHeader = Request::builder().method(Method::POST).body(()).unwrap().into_parts().0.into();
+        let params = PathParams::empty();
+        let req_ctx = RequestContext::new(&header, &params);
+
+        assert_eq!(router.at("/preflight").allowed_methods(&req_ctx), [Method::OPTIONS]);
+        assert_eq!(router.at("/dav").allowed_methods(&req_ctx), [Method::GET, Method::HEAD, propfind, Method::OPTIONS]);
+    }
+
+    #[test]
+    fn test_route_probes_see_extensions() {
+        #[derive(Clone)]
+        struct Admin;
+
+        let router = Router::builder()
+            .route("/", get(simple_get_1).with(filter_fn(|req| req.extensions().get::<Admin>().is_some())))
+            .route("/", post(simple_get_1))
+            .build();
+
+        let header: RequestHeader = Request::builder().method(Method::HEAD).body(()).unwrap().into_parts().0.into();
+        let params = PathParams::empty();
+        let mut req_ctx = RequestContext::new(&header, &params);
+        assert!(router.at("/").find(&req_ctx).is_none());
+        assert_eq!(router.at("/").allowed_methods(&req_ctx), [Method::POST, Method::OPTIONS]);
+
+        req_ctx.extensions_mut().insert(Admin);
+        assert!(router.at("/").find(&req_ctx).is_some());
+        assert_eq!(router.at("/").allowed_methods(&req_ctx), [Method::GET, Method::HEAD, Method::POST, Method::OPTIONS]);
+    }
+
+    #[test]
+    fn test_join_path() {
+        assert_eq!(join_path("/api/v1", "/users"), "/api/v1/users");
+        assert_eq!(join_path("/api/v1/", "users"), "/api/v1/users");
+        assert_eq!(join_path("/api/v1", "/"), "/api/v1");
+        assert_eq!(join_path("/", "/"), "/");
+        assert_eq!(join_path("", "/users"), "/users");
+    }
+
+    #[test]
+    fn test_route_nested_scopes() {
+        let admin = Router::builder().route("/stats", get(simple_get_1)).with_filter(header("x-admin", "1"));
+        let router = Router::builder()
+            .route("/", get(simple_get_1))
+            .scope("/api/v1", |api| api.route("/", get(simple_get_2)).route("/users/{id}", get(simple_get_2)).nest("/admin", admin))
+            .try_build()
+            .unwrap();
+
+        assert!(!router.at("/api/v1").is_empty());
+        assert_eq!(router.at("/api/v1/users/42").params().get("id"), Some("42"));
+        assert!(router.at("/users/42").is_empty());
+
+        let header: RequestHeader = Request::builder().method(Method::GET).body(()).unwrap().into_parts().0.into();
+        let params = PathParams::empty();
+        let req_ctx = RequestContext::new(&header, &params);
+        assert!(router.at("/api/v1/admin/stats").find(&req_ctx).is_none());
+        assert!(router.at("/api/v1/users/42").find(&req_ctx).is_some());
+
+        let header: RequestHeader = Request::builder().method(Method::GET).header("x-admin", "1").body(()).unwrap().into_parts().0.into();
+        let req_ctx = RequestContext::new(&header, &params);
+        assert!(router.at("/api/v1/admin/stats").find(&req_ctx).is_some());
+    }
+
+    #[test]
+    fn test_route_conflict_is_error() {
+        let error = Router::builder()
+            .route("/users/{id}", get(simple_get_1))
+            .scope("/users", |users| users.route("/{name}", get(simple_get_2)))
+            .try_build()
+            .unwrap_err();
+
+        assert_eq!(error.path(), "/users/{name}");
+    }
+
+    #[test]
+    fn test_try_header_rejects_invalid_header() {
+        try_header("x-api-key", "secret").unwrap();
+        let e = try_header("x api key", "secret").unwrap_err();
+        assert!(matches!(e, InvalidHeaderFilter::InvalidName { .. }));
+        let e = try_header("x-api-key", "line\nbreak").unwrap_err();
+        assert_eq!(e.to_string(), "invalid header filter on `x-api-key`: invalid header value: failed to parse header value");
+    }
 }
@@ -5,6 +5,9 @@
 //! - HTTP request routing and handling
 //! - Connection management and error handling
 //! - Default request handling
+//! - Automatic `405 Method Not Allowed` and `OPTIONS` responses
+//! - Graceful shutdown with connection draining
+//! - Client addresses, optionally forwarded by trusted proxies
 //!
 //! # Examples
 //!
@@ -32,48 +35,117 @@
 //! ```
 
 use crate::handler::RequestHandler;
-use crate::router::Router;
-use crate::{OptionReqBody, RequestContext, ResponseBody, handler_fn, FnTrait};
+use crate::handler::handler_decorator_factory::HandlerDecoratorFactory;
+use crate::router::{AllowedMethods, Router, RouterBuildError, RouterBuilder};
+use crate::connect_info::TrustedProxies;
+use crate::{IpCidr, OptionReqBody, RequestContext, ResponseBody, StateMap, handler_fn, FnTrait};
 use http::{Request, Response, StatusCode};
-use micro_http::connection::HttpConnection;
+use micro_http::connection::{ConnectionConfig, ConnectionInfo, HttpConnection};
 use micro_http::handler::Handler;
 use micro_http::protocol::RequestHeader;
 use micro_http::protocol::body::ReqBody;
 use std::error::Error;
+use std::io;
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
 use thiserror::Error;
 use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use tracing::{Level, error, info, warn};
 use tracing_subscriber::FmtSubscriber;
 use triomphe::Arc;
 use crate::extract::FromRequest;
 use crate::responder::Responder;
 
+/// Default time in-flight requests get to finish once the server shuts down
+const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
 /// Builder for configuring and constructing a [`Server`] instance.
 ///
 /// The builder provides a fluent API for setting server options including:
 /// - Binding address
 /// - Request router
 /// - Default request handler
+/// - Request body size limit
+/// - Shutdown grace period
+/// - Shared application state
+/// - Trusted proxies
+///
+/// # Errors Instead of Panics
+///
+/// [`bind`](Self::bind) panics on an address that can't be resolved, like [`RouterBuilder::build`]
+/// on a router that can't be built. Configuration read at startup can be reported instead: set the
+/// router with [`router_builder`](Self::router_builder), whose errors are returned by
+/// [`build`](Self::build), and the address with [`try_bind`](Self::try_bind).
+///
+/// ```no_run
+/// use micro_web::router::{get, Router};
+/// use micro_web::{Server, ServerBuildError};
+///
+/// async fn hello() -> &'static str {
+///     "hello"
+/// }
+///
+/// fn server(address: &str) -> Result<Server, ServerBuildError> {
+///     Server::builder().router_builder(Router::builder().route("/", get(hello))).try_bind(address)?.build()
+/// }
+/// ```
 #[derive(Debug)]
 pub struct ServerBuilder {
-    router: Option<Router>,
+    router: Option<Result<Router, RouterBuildError>>,
     default_handler: Option<Box<dyn RequestHandler>>,
     address: Option<Vec<SocketAddr>>,
+    max_body_size: Option<u64>,
+    shutdown_grace_period: Duration,
+    state_map: StateMap,
+    trusted_proxies: TrustedProxies,
 }
 
 impl ServerBuilder {
     fn new() -> Self {
-        Self { router: None, default_handler: None, address: None }
+        Self {
+            router: None,
+            default_handler: None,
+            address: None,
+            max_body_size: None,
+            shutdown_grace_period: DEFAULT_SHUTDOWN_GRACE_PERIOD,
+            state_map: StateMap::new(),
+            trusted_proxies: TrustedProxies::default(),
+        }
     }
 
-    pub fn bind<A: ToSocketAddrs>(mut self, address: A) -> Self {
-        self.address = Some(address.to_socket_addrs().unwrap().collect::<Vec<_>>());
-        self
+    /// Sets the address to listen on, resolving it right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address can't be resolved, see [`ServerBuilder::try_bind`].
+    pub fn bind<A: ToSocketAddrs>(self, address: A) -> Self {
+        self.try_bind(address).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Sets the address to listen on, resolving it right away.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ServerBuildError::InvalidAddress`] if the address can't be resolved.
+    pub fn try_bind<A: ToSocketAddrs>(mut self, address: A) -> Result<Self, ServerBuildError> {
+        let address = address.to_socket_addrs().map_err(ServerBuildError::InvalidAddress)?;
+        self.address = Some(address.collect());
+        Ok(self)
     }
 
     pub fn router(mut self, router: Router) -> Self {
-        self.router = Some(router);
+        self.router = Some(Ok(router));
+        self
+    }
+
+    /// Sets the router to build from `router_builder`, a router that can't be built fails
+    /// [`ServerBuilder::build`] instead of panicking like [`RouterBuilder::build`].
+    ///
+    /// Together with [`ServerBuilder::try_bind`] the server is built without panicking.
+    pub fn router_builder<DF: HandlerDecoratorFactory>(mut self, router_builder: RouterBuilder<DF>) -> Self {
+        self.router = Some(router_builder.try_build());
         self
     }
 
@@ -89,13 +161,70 @@ impl ServerBuilder {
         self
     }
 
+    /// Sets the request body size limit for routes that don't override it, request bodies are
+    /// unlimited by default.
+    ///
+    /// Requests over the limit are answered with `413 Payload Too Large`.
+    pub fn max_body_size(mut self, max_body_size: u64) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
+    /// Sets how long in-flight requests may take to finish once the server shuts down, defaults to 30 seconds.
+    ///
+    /// Connections still busy when the grace period ends are aborted.
+    pub fn shutdown_grace_period(mut self, shutdown_grace_period: Duration) -> Self {
+        self.shutdown_grace_period = shutdown_grace_period;
+        self
+    }
+
+    /// Registers shared application state, extracted by handlers with [`State`](crate::extract::State).
+    ///
+    /// Values are resolved by their type, so registering several types works, while registering
+    /// the same type again replaces the previous value.
+    pub fn state<T: Send + Sync + 'static>(mut self, state: T) -> Self {
+        self.state_map.insert(state);
+        self
+    }
+
+    /// Trusts the forwarding headers of peers in `cidr`, none are trusted by default.
+    ///
+    /// When a request comes from a trusted proxy, [`ConnectInfo::client_ip`](crate::ConnectInfo::client_ip)
+    /// is resolved from `Forwarded` or `X-Forwarded-For` instead of the peer address.
+    pub fn trusted_proxy(mut self, cidr: IpCidr) -> Self {
+        self.trusted_proxies.push(cidr);
+        self
+    }
+
+    /// Builds the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the router or the address is missing, or if the router set with
+    /// [`ServerBuilder::router_builder`] can't be built.
     pub fn build(self) -> Result<Server, ServerBuildError> {
         let new_builder = if self.default_handler.is_none() { self.default_handler(default_handler) } else { self };
-        let router = new_builder.router.ok_or(ServerBuildError::MissingRouter)?;
+        let router = new_builder.router.ok_or(ServerBuildError::MissingRouter)??;
         let address = new_builder.address.ok_or(ServerBuildError::MissingAddress)?;
 
+        // connections enforce the largest limit any route allows, the route's own limit is applied per request.
+        // Without a server wide limit, routes that don't set one are unlimited, and so are connections
+        let connection_max_body_size =
+            new_builder.max_body_size.map(|size| router.max_route_body_size().map_or(size, |route_size| route_size.max(size)));
+        let connection_config = ConnectionConfig::default().with_max_body_size(connection_max_body_size);
+
         // unwrap is safe here because we set it in the new_builder
-        Ok(Server { router, default_handler: new_builder.default_handler.unwrap(), address })
+        Ok(Server {
+            router,
+            default_handler: new_builder.default_handler.unwrap(),
+            address,
+            max_body_size: new_builder.max_body_size,
+            connection_config,
+            shutdown_grace_period: new_builder.shutdown_grace_period,
+            shutdown: CancellationToken::new(),
+            state_map: new_builder.state_map,
+            trusted_proxies: new_builder.trusted_proxies,
+        })
     }
 }
 
@@ -111,11 +240,91 @@ async fn default_handler() -> (StatusCode, &'static str) {
 /// - Managing connection lifecycle
 /// - Error handling and logging
 ///
+/// # Unmatched Methods
+///
+/// When a route exists for the request path but none accepts the request's method, the
+/// server answers `405 Method Not Allowed` instead of calling the default handler, with an
+/// `Allow` header listing the accepted methods. An `OPTIONS` request that no route handles
+/// gets `204 No Content` with the same `Allow` header. Both go through the global decorators
+/// of the router, which find the accepted methods in the [`AllowedMethods`] request extension,
+/// so a global middleware can answer a CORS preflight.
+///
+/// # Graceful Shutdown
+///
+/// Once shutdown is triggered, either by the signal passed to [`Server::start_with_shutdown`]
+/// or through a [`ShutdownHandle`], the server stops accepting connections. Idle connections
+/// are closed, in-flight requests are answered with `Connection: close`, and connections
+/// still busy after the shutdown grace period are aborted.
 #[derive(Debug)]
 pub struct Server {
     router: Router,
     default_handler: Box<dyn RequestHandler>,
     address: Vec<SocketAddr>,
+    max_body_size: Option<u64>,
+    connection_config: ConnectionConfig,
+    shutdown_grace_period: Duration,
+    shutdown: CancellationToken,
+    state_map: StateMap,
+    trusted_proxies: TrustedProxies,
+}
+
+/// A handle that shuts down a running [`Server`] from code.
+///
+/// # Example
+///
+/// ```no_run
+/// use micro_web::{Server, router::{Router, get}};
+///
+/// async fn hello_world() -> &'static str {
+///     "Hello, World!"
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let router = Router::builder().route("/", get(hello_world)).build();
+///     let server = Server::builder().router(router).bind("127.0.0.1:3000").build().unwrap();
+///
+///     let shutdown_handle = server.shutdown_handle();
+///     tokio::spawn(async move {
+///         tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+///         shutdown_handle.shutdown();
+///     });
+///
+///     let summary = server.start_with_shutdown(std::future::pending()).await.unwrap();
+///     println!("drained {} connections, aborted {}", summary.drained(), summary.aborted());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    token: CancellationToken,
+}
+
+impl ShutdownHandle {
+    /// Triggers the graceful shutdown of the server, calling it more than once has no effect.
+    pub fn shutdown(&self) {
+        self.token.cancel();
+    }
+}
+
+/// What happened to the connections that were open when the server shut down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownSummary {
+    drained: usize,
+    aborted: usize,
+}
+
+impl ShutdownSummary {
+    /// Returns the number of connections that finished within the grace period.
+    #[must_use]
+    pub fn drained(&self) -> usize {
+        self.drained
+    }
+
+    /// Returns the number of connections aborted when the grace period ended.
+    #[must_use]
+    pub fn aborted(&self) -> usize {
+        self.aborted
+    }
 }
 
 /// Errors that can occur during server construction.
@@ -128,6 +337,14 @@ pub enum ServerBuildError {
     /// Bind address was not configured
     #[error("address must be set")]
     MissingAddress,
+
+    /// Bind address set with [`ServerBuilder::try_bind`] could not be resolved
+    #[error("invalid address: {0}")]
+    InvalidAddress(#[source] io::Error),
+
+    /// Router set with [`ServerBuilder::router_builder`] could not be built, see [`RouterBuilder::try_build`]
+    #[error(transparent)]
+    InvalidRouter(#[from] RouterBuildError),
 }
 
 impl Server {
@@ -135,23 +352,57 @@ impl Server {
         ServerBuilder::new()
     }
 
+    /// Returns a handle that triggers the graceful shutdown of this server.
+    #[must_use]
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle { token: self.shutdown.clone() }
+    }
+
+    /// Returns the configuration of the connections this server accepts
+    pub(crate) fn connection_config(&self) -> &ConnectionConfig {
+        &self.connection_config
+    }
+
+    /// Starts the server and runs it until `ctrl_c` is received, then shuts down gracefully.
     pub async fn start(self) {
         let subscriber = FmtSubscriber::builder().with_max_level(Level::WARN).finish();
         tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
-        info!("start listening at {:?}", self.address);
-        let tcp_listener = match TcpListener::bind(self.address.as_slice()).await {
-            Ok(tcp_listener) => tcp_listener,
-            Err(e) => {
-                error!(cause = %e, "bind server error");
-                return;
+        let ctrl_c = async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                error!(cause = %e, "can't listen for ctrl_c");
             }
         };
 
+        if let Err(e) = self.start_with_shutdown(ctrl_c).await {
+            error!(cause = %e, "bind server error");
+        }
+    }
+
+    /// Starts the server and runs it until `signal` completes or its [`ShutdownHandle`] is triggered.
+    ///
+    /// The server then stops accepting connections and waits up to the shutdown grace period for
+    /// the open connections to finish, aborting the rest. Returns how many connections were
+    /// drained or aborted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server can't bind its address.
+    pub async fn start_with_shutdown(self, signal: impl Future<Output = ()>) -> io::Result<ShutdownSummary> {
+        info!("start listening at {:?}", self.address);
+        let tcp_listener = TcpListener::bind(self.address.as_slice()).await?;
+
         let handler = Arc::new(self);
+        let shutdown = handler.shutdown.clone();
+        let mut connections = JoinSet::new();
+        let mut signal = std::pin::pin!(signal);
+
         loop {
-            let (tcp_stream, _remote_addr) = tokio::select! {
-                _ = tokio::signal::ctrl_c() => { break; },
+            let (tcp_stream, remote_addr) = tokio::select! {
+                () = &mut signal => { break; },
+                () = shutdown.cancelled() => { break; },
+                // reap finished connections so the set only holds open ones
+                Some(_) = connections.join_next(), if !connections.is_empty() => { continue; },
                 result = tcp_listener.accept() => {
                     match result {
                         Ok(stream_and_addr) => stream_and_addr,
@@ -164,21 +415,49 @@ impl Server {
             };
 
             let handler = handler.clone();
+            let shutdown = shutdown.clone();
 
-            tokio::spawn(async move {
+            connections.spawn(async move {
                 tcp_stream.set_nodelay(true).unwrap();
+                let mut connection_info = ConnectionInfo::default().with_peer_addr(remote_addr);
+                if let Ok(local_addr) = tcp_stream.local_addr() {
+                    connection_info = connection_info.with_local_addr(local_addr);
+                }
                 let (reader, writer) = tcp_stream.into_split();
-                let connection = HttpConnection::new(reader, writer);
-                match connection.process(handler.as_ref()).await {
+                let connection =
+                    HttpConnection::with_config(reader, writer, handler.connection_config.clone()).with_connection_info(connection_info);
+                match connection.process_with_shutdown(handler.as_ref(), shutdown.cancelled()).await {
                     Ok(_) => {
                         info!("finished process, connection shutdown");
                     }
+                    Err(e) if e.is_timeout() => {
+                        info!("connection timed out, cause {}, connection shutdown", e);
+                    }
                     Err(e) => {
                         error!("service has error, cause {}, connection shutdown", e);
                     }
                 }
             });
         }
+
+        drop(tcp_listener);
+        shutdown.cancel();
+        info!(connections = connections.len(), "shutting down, draining connections");
+
+        let mut drained = 0;
+        let drain = async {
+            while connections.join_next().await.is_some() {
+                drained += 1;
+            }
+        };
+        if tokio::time::timeout(handler.shutdown_grace_period, drain).await.is_err() {
+            warn!(connections = connections.len(), "shutdown grace period elapsed, aborting connections");
+        }
+
+        let aborted = connections.len();
+        connections.shutdown().await;
+
+        Ok(ShutdownSummary { drained, aborted })
     }
 }
 
@@ -187,26 +466,467 @@ impl Handler for Server {
     type Error = Box<dyn Error + Send + Sync>;
 
     async fn call(&self, req: Request<ReqBody>) -> Result<Response<Self::RespBody>, Self::Error> {
-        let (parts, body) = req.into_parts();
+        let (mut parts, mut body) = req.into_parts();
+        let mut extensions = std::mem::take(&mut parts.extensions);
         let header = RequestHeader::from(parts);
-        // TODO: insignificant memory allocate
-        let req_body = OptionReqBody::from(body);
+
+        let connect_info = extensions.get::<ConnectionInfo>().and_then(|info| self.trusted_proxies.connect_info(info, header.headers()));
+        if let Some(connect_info) = connect_info {
+            extensions.insert(connect_info);
+        }
 
         let path = header.uri().path();
         let route_result = self.router.at(path);
 
-        let mut request_context = RequestContext::new(&header, route_result.params());
+        let mut request_context =
+            RequestContext::new(&header, route_result.params()).with_state_map(&self.state_map).with_extensions(extensions);
+
+        let (handler, max_body_size) = if let Some(item) = route_result.find(&request_context) {
+            (item.handler(), item.max_body_size().or(self.max_body_size))
+        } else {
+            let allowed_methods = route_result.allowed_methods(&request_context);
+            if allowed_methods.is_empty() {
+                (self.default_handler.as_ref(), self.max_body_size)
+            } else {
+                request_context.extensions_mut().insert(AllowedMethods(allowed_methods));
+                (self.router.method_not_allowed_handler(), self.max_body_size)
+            }
+        };
+
+        if let Some(max_body_size) = max_body_size
+            && let Err(e) = body.set_max_size(max_body_size)
+        {
+            return Ok(e.response_to(&request_context));
+        }
 
-        let handler = route_result
-            .router_items()
-            .iter()
-            .filter(|item| item.filter().matches(&request_context))
-            .map(|item| item.handler())
-            .take(1)
-            .next()
-            .unwrap_or(self.default_handler.as_ref());
+        // TODO: insignificant memory allocate
+        let req_body = OptionReqBody::from(body);
 
         let response = handler.invoke(&mut request_context, req_body).await;
         Ok(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::{Extension, State};
+    use crate::handler::handler_decorator::HandlerDecorator;
+    use crate::router::{get, inner_get};
+    use async_trait::async_trait;
+    use http::HeaderValue;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    async fn hello() -> &'static str {
+        "hello"
+    }
+
+    fn server() -> (Server, SocketAddr) {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder().route("/", get(hello)).build();
+        let server = Server::builder().router(router).bind(address).build().unwrap();
+        (server, address)
+    }
+
+    async fn connect(address: SocketAddr) -> TcpStream {
+        loop {
+            match TcpStream::connect(address).await {
+                Ok(stream) => return stream,
+                Err(_) => tokio::task::yield_now().await,
+            }
+        }
+    }
+
+    async fn send(address: SocketAddr, request: &str) -> String {
+        let mut stream = connect(address).await;
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[derive(Clone)]
+    struct Greeting(&'static str);
+
+    struct Visits(std::sync::atomic::AtomicUsize);
+
+    async fn greet(State(greeting): State<Greeting>, visits: &State<Visits>) -> String {
+        let visits = visits.0.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        format!("{} #{visits}", greeting.0)
+    }
+
+    #[tokio::test]
+    async fn state_resolved_by_type() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder().route("/", get(greet)).build();
+        let server = Server::builder()
+            .router(router)
+            .state(Greeting("hello"))
+            .state(Visits(std::sync::atomic::AtomicUsize::new(0)))
+            .bind(address)
+            .build()
+            .unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+        assert!(send(address, request).await.ends_with("hello #1"));
+        assert!(send(address, request).await.ends_with("hello #2"));
+
+        shutdown_handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_state_is_internal_server_error() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder().route("/", get(greet)).build();
+        let server = Server::builder().router(router).state(Greeting("hello")).bind(address).build().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let response = send(address, "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+
+        shutdown_handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[derive(Clone)]
+    struct User(&'static str);
+
+    #[derive(Clone)]
+    struct TraceId(u32);
+
+    /// Stands in for an auth decorator, inserting the user before invoking the handler
+    struct Authenticate<H>(H);
+
+    #[async_trait]
+    impl<H: RequestHandler> RequestHandler for Authenticate<H> {
+        async fn invoke<'server, 'req>(&self, req: &mut RequestContext<'server, 'req>, req_body: OptionReqBody) -> Response<ResponseBody> {
+            req.extensions_mut().insert(User("alice"));
+            self.0.invoke(req, req_body).await
+        }
+    }
+
+    /// Stands in for a micro-http handler wrapping the server, inserting into the request extensions
+    struct Traced(Server);
+
+    impl Handler for Traced {
+        type RespBody = ResponseBody;
+        type Error = Box<dyn Error + Send + Sync>;
+
+        async fn call(&self, mut req: Request<ReqBody>) -> Result<Response<Self::RespBody>, Self::Error> {
+            req.extensions_mut().insert(TraceId(7));
+            self.0.call(req).await
+        }
+    }
+
+    async fn whoami(Extension(user): Extension<User>, trace_id: Option<Extension<TraceId>>) -> String {
+        format!("{} {}", user.0, trace_id.map_or(0, |trace_id| trace_id.0.0))
+    }
+
+    #[tokio::test]
+    async fn extensions_reach_handler() {
+        let router = Router::builder().route("/", inner_get(Authenticate(handler_fn(whoami)))).route("/anonymous", get(whoami)).build();
+        let server = Server::builder().router(router).bind("127.0.0.1:0").build().unwrap();
+
+        let (client, io) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(io);
+        let connection = tokio::spawn(async move { HttpConnection::new(reader, writer).process(&Traced(server)).await });
+
+        let (mut reader, mut writer) = tokio::io::split(client);
+        writer.write_all(b"GET / HTTP/1.1\r\n\r\nGET /anonymous HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        reader.read_to_string(&mut response).await.unwrap();
+        connection.await.unwrap().unwrap();
+
+        assert!(response.contains("\r\n\r\nalice 7HTTP/1.1 500 Internal Server Error\r\n"));
+    }
+
+    async fn client(peer_addr: SocketAddr, connect_info: crate::ConnectInfo) -> String {
+        format!("{} {}", connect_info.client_ip(), peer_addr.ip())
+    }
+
+    #[tokio::test]
+    async fn connect_info_resolves_trusted_proxy() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder().route("/", get(client)).build();
+        let server = Server::builder().router(router).trusted_proxy("127.0.0.0/8".parse().unwrap()).bind(address).build().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let response = send(address, "GET / HTTP/1.1\r\nX-Forwarded-For: 203.0.113.9\r\nConnection: close\r\n\r\n").await;
+        assert!(response.ends_with("203.0.113.9 127.0.0.1"));
+        let response = send(address, "GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+        assert!(response.ends_with("127.0.0.1 127.0.0.1"));
+
+        shutdown_handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn unmatched_method_gets_405_and_options_204() {
+        let router = Router::builder()
+            .route("/", get(hello))
+            .route("/", crate::router::post(hello))
+            .route("/preflight", crate::router::options(hello))
+            .build();
+        let server = Server::builder().router(router).bind("127.0.0.1:0").build().unwrap();
+
+        let (client, io) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(io);
+        let connection = tokio::spawn(async move { HttpConnection::new(reader, writer).process(&server).await });
+
+        let (mut reader, mut writer) = tokio::io::split(client);
+        let requests = "DELETE / HTTP/1.1\r\n\r\nOPTIONS / HTTP/1.1\r\n\r\nGET /preflight HTTP/1.1\r\n\r\n\
+                        GET /missing HTTP/1.1\r\nConnection: close\r\n\r\n";
+        writer.write_all(requests.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        reader.read_to_string(&mut response).await.unwrap();
+        connection.await.unwrap().unwrap();
+
+        let responses: Vec<&str> = response.split("HTTP/1.1 ").skip(1).collect();
+        assert_eq!(responses.len(), 4);
+        assert!(responses[0].starts_with("405 Method Not Allowed\r\n"));
+        assert!(responses[0].contains("allow: GET, HEAD, POST, OPTIONS\r\n"));
+        assert!(responses[1].starts_with("204 No Content\r\n"));
+        assert!(responses[1].contains("allow: GET, HEAD, POST, OPTIONS\r\n"));
+        // a path only routing `OPTIONS` still exists
+        assert!(responses[2].starts_with("405 Method Not Allowed\r\n"));
+        assert!(responses[2].contains("allow: OPTIONS\r\n"));
+        assert!(responses[3].starts_with("404 Not Found\r\n"));
+    }
+
+    #[tokio::test]
+    async fn global_middleware_sees_405_and_options_204() {
+        let cors = crate::middleware_fn(|req, body, next| {
+            Box::pin(async move {
+                let allow = req.extensions().get::<AllowedMethods>().map(|allowed_methods| allowed_methods.0.len());
+                let mut response = next.run(req, body).await;
+                response.headers_mut().insert("access-control-allow-origin", HeaderValue::from_static("*"));
+                response.headers_mut().insert("x-allowed-methods", allow.unwrap_or(0).into());
+                response
+            })
+        });
+
+        let router = Router::builder().route("/", get(hello)).with_global_decorator(cors).build();
+        let server = Server::builder().router(router).bind("127.0.0.1:0").build().unwrap();
+
+        let (client, io) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(io);
+        let connection = tokio::spawn(async move { HttpConnection::new(reader, writer).process(&server).await });
+
+        let (mut reader, mut writer) = tokio::io::split(client);
+        let requests = "OPTIONS / HTTP/1.1\r\n\r\nDELETE / HTTP/1.1\r\nConnection: close\r\n\r\n";
+        writer.write_all(requests.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        reader.read_to_string(&mut response).await.unwrap();
+        connection.await.unwrap().unwrap();
+
+        let responses: Vec<&str> = response.split("HTTP/1.1 ").skip(1).collect();
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0].starts_with("204 No Content\r\n"));
+        assert!(responses[1].starts_with("405 Method Not Allowed\r\n"));
+        for response in responses {
+            assert!(response.contains("access-control-allow-origin: *\r\n"));
+            // the allowed methods are in the request extensions before the 405 handler runs
+            assert!(response.contains("x-allowed-methods: 3\r\n"));
+            assert!(response.contains("allow: GET, HEAD, OPTIONS\r\n"));
+        }
+    }
+
+    #[tokio::test]
+    async fn scope_decorators_apply_to_scoped_routes_only() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder()
+            .route("/", get(hello))
+            .scope("/api", |api| api.route("/", get(hello)).with_global_decorator(crate::date::DateServiceDecorator))
+            .build();
+        let server = Server::builder().router(router).bind(address).build().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let response = send(address, "GET /api HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+        assert!(response.to_ascii_lowercase().contains("\r\ndate: "));
+        let response = send(address, "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+        assert!(!response.to_ascii_lowercase().contains("\r\ndate: "));
+
+        shutdown_handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn invalid_address_is_build_error() {
+        let router = Router::builder().route("/", get(hello)).build();
+        let error = Server::builder().router(router).try_bind("127.0.0.1:http").unwrap_err();
+        assert!(matches!(error, ServerBuildError::InvalidAddress(_)));
+
+        let error = Server::builder().bind("127.0.0.1:0").build().unwrap_err();
+        assert!(matches!(error, ServerBuildError::MissingRouter));
+    }
+
+    #[test]
+    fn invalid_router_is_build_error() {
+        let router_builder = Router::builder().route("/{id}", get(hello)).route("/{name}", get(hello));
+        let error = Server::builder().router_builder(router_builder).bind("127.0.0.1:0").build().unwrap_err();
+        assert!(matches!(error, ServerBuildError::InvalidRouter(_)));
+
+        let router_builder = Router::builder().route("/", get(hello));
+        Server::builder().router_builder(router_builder).bind("127.0.0.1:0").build().unwrap();
+    }
+
+    /// Appends its name to the `x-tags` response header, recording the order decorators run in
+    #[derive(Clone, Copy)]
+    struct Tag(&'static str);
+
+    struct Tagged<H>(H, &'static str);
+
+    #[async_trait]
+    impl<H: RequestHandler> RequestHandler for Tagged<H> {
+        async fn invoke<'server, 'req>(&self, req: &mut RequestContext<'server, 'req>, req_body: OptionReqBody) -> Response<ResponseBody> {
+            let mut response = self.0.invoke(req, req_body).await;
+            response.headers_mut().append("x-tags", HeaderValue::from_static(self.1));
+            response
+        }
+    }
+
+    impl<H: RequestHandler> HandlerDecorator<H> for Tag {
+        type Output = Tagged<H>;
+
+        fn decorate(&self, handler: H) -> Self::Output {
+            Tagged(handler, self.0)
+        }
+    }
+
+    impl HandlerDecoratorFactory for Tag {
+        type Output<In>
+            = Tag
+        where
+            In: RequestHandler;
+
+        fn create_decorator<In>(&self) -> Self::Output<In>
+        where
+            In: RequestHandler,
+        {
+            *self
+        }
+    }
+
+    #[tokio::test]
+    async fn route_decorators_run_inside_scope_and_global_decorators() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder()
+            .route("/", get(hello))
+            .scope("/admin", |admin| {
+                admin.route("/", get(hello).decorate(Tag("route-1")).decorate(Tag("route-2"))).with_global_decorator(Tag("scope"))
+            })
+            .with_global_decorator(Tag("global"))
+            .build();
+        let server = Server::builder().router(router).bind(address).build().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let response = send(address, "GET /admin HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+        assert!(response.contains("x-tags: route-1\r\nx-tags: route-2\r\nx-tags: scope\r\nx-tags: global\r\n"));
+        let response = send(address, "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+        assert!(response.contains("\r\nx-tags: global\r\n"));
+        assert!(!response.contains("route-1"));
+
+        shutdown_handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn middleware_fn_short_circuits_and_post_processes() {
+        let auth = crate::middleware_fn(|req, body, next| {
+            Box::pin(async move {
+                if !req.headers().contains_key("x-api-key") {
+                    return Err((StatusCode::UNAUTHORIZED, "unauthorized"));
+                }
+                req.extensions_mut().insert(User("alice"));
+                let mut response = next.run(req, body).await;
+                response.headers_mut().insert("x-authenticated", HeaderValue::from_static("true"));
+                Ok(response)
+            })
+        });
+
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder().route("/", get(hello)).route("/admin", get(whoami).decorate(auth.clone())).build();
+        let server = Server::builder().router(router).bind(address).build().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let response = send(address, "GET /admin HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(response.ends_with("unauthorized"));
+        let response = send(address, "GET /admin HTTP/1.1\r\nX-Api-Key: secret\r\nConnection: close\r\n\r\n").await;
+        assert!(response.contains("\r\nx-authenticated: true\r\n"));
+        assert!(response.ends_with("alice 0"));
+        let response = send(address, "GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+        assert!(response.ends_with("hello"));
+
+        shutdown_handle.shutdown();
+        running.await.unwrap().unwrap();
+
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder().route("/", get(hello)).with_global_decorator(auth).build();
+        let server = Server::builder().router(router).bind(address).build().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let response = send(address, "GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+
+        shutdown_handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn shutdown_handle_stops_server() {
+        let (server, _) = server();
+        let shutdown_handle = server.shutdown_handle();
+        shutdown_handle.shutdown();
+
+        let summary = server.start_with_shutdown(std::future::pending()).await.unwrap();
+        assert_eq!(summary, ShutdownSummary::default());
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_idle_connection() {
+        let (server, address) = server();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let mut stream = connect(address).await;
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut buf = vec![0; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(buf[..n].ends_with(b"hello"));
+
+        shutdown_handle.shutdown();
+        let summary = running.await.unwrap().unwrap();
+        assert_eq!(summary.drained(), 1);
+        assert_eq!(summary.aborted(), 0);
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn shutdown_aborts_connection_after_grace_period() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let router = Router::builder().route("/", get(std::future::pending::<&'static str>)).build();
+        let server = Server::builder().router(router).bind(address).shutdown_grace_period(Duration::from_millis(50)).build().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let running = tokio::spawn(server.start_with_shutdown(std::future::pending()));
+
+        let mut stream = connect(address).await;
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        shutdown_handle.shutdown();
+        let summary = running.await.unwrap().unwrap();
+        assert_eq!(summary.drained(), 0);
+        assert_eq!(summary.aborted(), 1);
+    }
+}
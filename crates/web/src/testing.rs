@@ -0,0 +1,452 @@
+//! In-process test client
+//!
+//! [`TestClient`] sends requests to a [`Server`] over an in-memory stream, so a test exercises the
+//! same path as a real client: the request is parsed by micro-http, routed, run through the
+//! decorators and extractors, and the encoded response is parsed back. No socket is opened.
+//!
+//! - `TestClient`: Wraps a built `Server` or `Router`
+//! - `TestRequest`: Builder for the method, headers and body of a request
+//! - `TestResponse`: The collected response, with assertion helpers
+//!
+//! # Example
+//!
+//! ```
+//! use http::StatusCode;
+//! use micro_web::router::{get, Router};
+//! use micro_web::testing::TestClient;
+//!
+//! async fn hello() -> &'static str {
+//!     "hello"
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let client = TestClient::new(Router::builder().route("/", get(hello)).build());
+//!
+//! let response = client.get("/").header("accept", "text/plain").send().await;
+//! response.assert_status(StatusCode::OK).assert_body("hello");
+//! # }
+//! ```
+
+use crate::Server;
+use crate::router::Router;
+use bytes::{Bytes, BytesMut};
+use http::header::{CONNECTION, CONTENT_LENGTH, CONTENT_TYPE, HOST, TRANSFER_ENCODING};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use micro_http::connection::{ConnectionInfo, HttpConnection};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// The peer address requests appear to come from
+const PEER_ADDR: SocketAddr = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 40000);
+
+/// Size of the in-memory stream buffer between the client and the server
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A client sending requests to a server without opening sockets.
+///
+/// Every request is sent on its own connection with `Connection: close`, coming from a peer at
+/// `127.0.0.1`.
+#[derive(Debug)]
+pub struct TestClient {
+    server: Server,
+}
+
+impl TestClient {
+    /// Creates a client for `server`, which may be a [`Server`] or a [`Router`].
+    pub fn new(server: impl Into<TestClient>) -> Self {
+        server.into()
+    }
+
+    /// Starts building a request with `method` to `uri`, such as `/users?page=2`.
+    #[must_use]
+    pub fn request(&self, method: Method, uri: impl Into<String>) -> TestRequest<'_> {
+        TestRequest { client: self, method, uri: uri.into(), headers: HeaderMap::new(), body: Bytes::new() }
+    }
+
+    /// Starts building a `GET` request to `uri`.
+    #[must_use]
+    pub fn get(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::GET, uri)
+    }
+
+    /// Starts building a `POST` request to `uri`.
+    #[must_use]
+    pub fn post(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::POST, uri)
+    }
+
+    /// Starts building a `PUT` request to `uri`.
+    #[must_use]
+    pub fn put(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::PUT, uri)
+    }
+
+    /// Starts building a `PATCH` request to `uri`.
+    #[must_use]
+    pub fn patch(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::PATCH, uri)
+    }
+
+    /// Starts building a `DELETE` request to `uri`.
+    #[must_use]
+    pub fn delete(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::DELETE, uri)
+    }
+
+    /// Starts building a `HEAD` request to `uri`.
+    #[must_use]
+    pub fn head(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::HEAD, uri)
+    }
+}
+
+impl From<Server> for TestClient {
+    fn from(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+impl From<Router> for TestClient {
+    fn from(router: Router) -> Self {
+        let server = Server::builder().router(router).bind(PEER_ADDR).build().expect("router and address are set");
+        Self { server }
+    }
+}
+
+/// Builder for a request sent by a [`TestClient`].
+#[derive(Debug)]
+pub struct TestRequest<'client> {
+    client: &'client TestClient,
+    method: Method,
+    uri: String,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestRequest<'_> {
+    /// Appends a header to the request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name or the value isn't a valid header.
+    #[must_use]
+    pub fn header<K, V>(mut self, name: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        let name = HeaderName::try_from(name).map_err(Into::into).expect("invalid header name");
+        let value = HeaderValue::try_from(value).map_err(Into::into).expect("invalid header value");
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Sets the request body, sent with a `Content-Length`.
+    #[must_use]
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Sets the request body to `value` serialized as JSON, with `Content-Type: application/json`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` can't be serialized.
+    #[must_use]
+    pub fn json<T: Serialize + ?Sized>(mut self, value: &T) -> Self {
+        self.headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self.body(serde_json::to_vec(value).expect("failed to serialize JSON body"))
+    }
+
+    /// Sets the request body to `value` URL-encoded, with `Content-Type: application/x-www-form-urlencoded`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` can't be serialized.
+    #[must_use]
+    pub fn form<T: Serialize + ?Sized>(mut self, value: &T) -> Self {
+        self.headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/x-www-form-urlencoded"));
+        self.body(serde_urlencoded::to_string(value).expect("failed to serialize form body"))
+    }
+
+    /// Sends the request and collects the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server closes the connection without a complete response.
+    pub async fn send(self) -> TestResponse {
+        let request = self.encode();
+        let server = &self.client.server;
+
+        let (client_io, server_io) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+        let (reader, writer) = tokio::io::split(server_io);
+        let connection = HttpConnection::with_config(reader, writer, server.connection_config().clone())
+            .with_connection_info(ConnectionInfo::default().with_peer_addr(PEER_ADDR));
+
+        let (mut client_reader, mut client_writer) = tokio::io::split(client_io);
+        let write = async {
+            // the server may answer without reading the whole body, e.g. when it's too large
+            let _ = client_writer.write_all(&request).await;
+        };
+        let read = async {
+            let mut response = Vec::new();
+            client_reader.read_to_end(&mut response).await.expect("failed to read response");
+            response
+        };
+
+        let (_, (), response) = tokio::join!(connection.process(server), write, read);
+        TestResponse::parse(&self.method, &response)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut headers = self.headers.clone();
+        if !headers.contains_key(HOST) {
+            headers.insert(HOST, HeaderValue::from_static("localhost"));
+        }
+        if !self.body.is_empty() && !headers.contains_key(CONTENT_LENGTH) && !headers.contains_key(TRANSFER_ENCODING) {
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(self.body.len()));
+        }
+        headers.insert(CONNECTION, HeaderValue::from_static("close"));
+
+        let mut request = format!("{} {} HTTP/1.1\r\n", self.method, self.uri).into_bytes();
+        for (name, value) in &headers {
+            request.extend_from_slice(name.as_str().as_bytes());
+            request.extend_from_slice(b": ");
+            request.extend_from_slice(value.as_bytes());
+            request.extend_from_slice(b"\r\n");
+        }
+        request.extend_from_slice(b"\r\n");
+        request.extend_from_slice(&self.body);
+        request
+    }
+}
+
+/// A response collected by a [`TestClient`].
+///
+/// A chunked body is decoded, so [`TestResponse::body`] is always the payload.
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    fn parse(method: &Method, response: &[u8]) -> Self {
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut parsed = httparse::Response::new(&mut headers);
+        let httparse::Status::Complete(header_len) = parsed.parse(response).expect("invalid response") else {
+            panic!("incomplete response: {:?}", String::from_utf8_lossy(response));
+        };
+
+        let status = StatusCode::from_u16(parsed.code.expect("response without status")).expect("invalid status");
+        let headers: HeaderMap = parsed
+            .headers
+            .iter()
+            .map(|header| (HeaderName::from_bytes(header.name.as_bytes()).unwrap(), HeaderValue::from_bytes(header.value).unwrap()))
+            .collect();
+
+        let payload = &response[header_len..];
+        let body = if method == Method::HEAD {
+            Bytes::new()
+        } else if headers.get(TRANSFER_ENCODING).is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"chunked")) {
+            decode_chunked(payload)
+        } else {
+            Bytes::copy_from_slice(payload)
+        };
+
+        Self { status, headers, body }
+    }
+
+    /// Returns the status of the response.
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the headers of the response.
+    #[must_use]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the first value of the header `name`, if present.
+    #[must_use]
+    pub fn header(&self, name: impl AsRef<str>) -> Option<&HeaderValue> {
+        self.headers.get(name.as_ref())
+    }
+
+    /// Returns the body of the response.
+    #[must_use]
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Returns the body of the response as text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body isn't valid UTF-8.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.body).expect("response body isn't UTF-8")
+    }
+
+    /// Deserializes the body of the response from JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body isn't valid JSON for `T`.
+    #[must_use]
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).expect("response body isn't valid JSON")
+    }
+
+    /// Asserts the status of the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the status differs.
+    #[track_caller]
+    pub fn assert_status(&self, status: StatusCode) -> &Self {
+        assert_eq!(self.status, status, "unexpected status, body: {:?}", String::from_utf8_lossy(&self.body));
+        self
+    }
+
+    /// Asserts the first value of the header `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the header is missing or has another value.
+    #[track_caller]
+    pub fn assert_header(&self, name: impl AsRef<str>, value: impl AsRef<str>) -> &Self {
+        let name = name.as_ref();
+        let actual = self.headers.get(name).unwrap_or_else(|| panic!("missing header `{name}`"));
+        assert_eq!(actual, value.as_ref(), "unexpected value of header `{name}`");
+        self
+    }
+
+    /// Asserts the body of the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body differs.
+    #[track_caller]
+    pub fn assert_body(&self, body: impl AsRef<[u8]>) -> &Self {
+        assert_eq!(self.body, body.as_ref(), "unexpected body");
+        self
+    }
+}
+
+/// Decodes a chunked payload, ignoring chunk extensions and trailers
+fn decode_chunked(mut payload: &[u8]) -> Bytes {
+    let mut body = BytesMut::new();
+    loop {
+        let httparse::Status::Complete((start, size)) = httparse::parse_chunk_size(payload).expect("invalid chunk size") else {
+            panic!("incomplete chunked body");
+        };
+        if size == 0 {
+            return body.freeze();
+        }
+        let end = start + usize::try_from(size).expect("chunk too large");
+        body.extend_from_slice(&payload[start..end]);
+        payload = &payload[end + 2..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::{Form, Json};
+    use crate::responder::json::NdJson;
+    use crate::router::{get, post};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        name: String,
+    }
+
+    async fn hello() -> &'static str {
+        "hello"
+    }
+
+    async fn echo_json(Json(user): Json<User>) -> Json<User> {
+        Json(user)
+    }
+
+    async fn echo_form(Form(user): Form<User>) -> String {
+        user.name
+    }
+
+    async fn stream() -> NdJson<impl futures::Stream<Item = User>> {
+        NdJson(futures::stream::iter(["alice", "bob"].map(|name| User { name: name.to_string() })))
+    }
+
+    fn client() -> TestClient {
+        let router = Router::builder()
+            .route("/", get(hello))
+            .route("/json", post(echo_json))
+            .route("/form", post(echo_form))
+            .route("/stream", get(stream))
+            .build();
+        TestClient::new(router)
+    }
+
+    #[tokio::test]
+    async fn get_request() {
+        let client = client();
+
+        client.get("/").send().await.assert_status(StatusCode::OK).assert_header("content-length", "5").assert_body("hello");
+        client.head("/").send().await.assert_status(StatusCode::OK).assert_body("");
+        client.get("/missing").send().await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn json_and_form_bodies() {
+        let client = client();
+        let alice = User { name: "alice".to_string() };
+
+        let response = client.post("/json").json(&alice).send().await;
+        response.assert_status(StatusCode::OK).assert_header("content-type", "application/json");
+        assert_eq!(response.json::<User>(), alice);
+
+        let response = client.post("/form").form(&alice).send().await;
+        assert_eq!(response.text(), "alice");
+    }
+
+    #[tokio::test]
+    async fn chunked_response_is_decoded() {
+        let response = client().get("/stream").send().await;
+
+        response.assert_header("transfer-encoding", "chunked");
+        assert_eq!(response.body(), &b"{\"name\":\"alice\"}\n{\"name\":\"bob\"}\n"[..]);
+    }
+
+    #[tokio::test]
+    async fn server_limits_apply() {
+        let router = Router::builder().route("/json", post(echo_json)).build();
+        let server = Server::builder().router(router).bind(PEER_ADDR).max_body_size(8).build().unwrap();
+        let client = TestClient::new(server);
+
+        let response = client.post("/json").json(&User { name: "alice".to_string() }).send().await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn request_bodies_are_unlimited_by_default() {
+        async fn body_len(body: Bytes) -> String {
+            body.len().to_string()
+        }
+
+        let client = TestClient::new(Router::builder().route("/upload", post(body_len)).build());
+        let response = client.post("/upload").body(vec![b'x'; 3 * 1024 * 1024]).send().await;
+        response.assert_status(StatusCode::OK).assert_body("3145728");
+    }
+}